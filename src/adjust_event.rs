@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    box_plot::BoxPlot,
+    strategy::{BanReason, Stage},
+};
+
+/// Событие автонастройки канала
+/// Передается клиентам как есть, текст для оператора формирует интерфейс
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum AdjustEvent {
+    /// Канал выбран, настройка начата
+    Started { channel: u32, freq: f32 },
+
+    /// Пробный выстрел при поиске края, ожидание реакции
+    Probe { step: u32 },
+
+    /// Найден край
+    EdgeFound { step: u32, freq: f32 },
+
+    /// Стадия настройки завершена
+    StageDone { stage: Stage, freq: f32, steps: u32 },
+
+    /// Прожиг steps шагов
+    Burn { steps: u32, power: f32, soft: bool },
+
+    /// Ожидание остывания после прожига
+    Cooling,
+
+    /// Измерена частота
+    Measured {
+        freq: f32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        interval: Option<(f32, f32)>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        box_plot: Option<BoxPlot<f32>>,
+    },
+
+    /// Цель достигнута, контрольное измерение
+    Verify { freq: f32 },
+
+    /// Дальнейшая настройка невозможна
+    Ban { reason: BanReason, freq: f32 },
+
+    /// Настройка завершена
+    Finished {
+        /// Частота в начале каждой стадии и итоговая
+        path: Vec<f32>,
+        freq: f32,
+        ppm: f32,
+        steps: u32,
+    },
+
+    /// Ошибка оборудования
+    Error { message: String },
+}
+
+impl AdjustEvent {
+    /// Событие последнее в настройке канала
+    pub fn is_final(&self) -> bool {
+        match self {
+            AdjustEvent::Finished { .. } | AdjustEvent::Error { .. } => true,
+            AdjustEvent::Ban { reason, .. } => *reason != BanReason::StepLimit,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn json_format() {
+        let burn = AdjustEvent::Burn {
+            steps: 3,
+            power: 1.0,
+            soft: false,
+        };
+        assert_eq!(
+            serde_json::to_string(&burn).unwrap(),
+            r#"{"Burn":{"steps":3,"power":1.0,"soft":false}}"#
+        );
+
+        let ban = AdjustEvent::Ban {
+            reason: BanReason::TooLow,
+            freq: 32700.5,
+        };
+        assert_eq!(
+            serde_json::to_string(&ban).unwrap(),
+            r#"{"Ban":{"reason":"TooLow","freq":32700.5}}"#
+        );
+        assert!(ban.is_final());
+
+        let measured = AdjustEvent::Measured {
+            freq: 32767.5,
+            interval: None,
+            box_plot: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&measured).unwrap(),
+            r#"{"Measured":{"freq":32767.5}}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&AdjustEvent::Cooling).unwrap(),
+            r#""Cooling""#
+        );
+    }
+}
@@ -0,0 +1,32 @@
+use clap::Parser;
+use std::path::PathBuf;
+
+/// Estimate per-shot frequency growth from saved data and propose `ForecastConfig` and `AutoAdjustLimits`
+#[derive(Parser)]
+#[clap(version)]
+pub struct Cli {
+    /// The json-files with fragments captured by `laser-precision-adjust-server`.
+    /// Use `TYPE=path` to assign a resonator type, otherwise the file name is used as type
+    #[clap(required = true)]
+    pub fragments: Vec<String>,
+
+    /// `auto_adjust_all_*.json` reports to estimate step and offset limits
+    #[clap(short, long)]
+    pub report: Vec<PathBuf>,
+
+    /// Config file to compare the proposal with (default: user config)
+    #[clap(short, long)]
+    pub config: Option<PathBuf>,
+
+    /// Confidence level of the intervals
+    #[clap(long, default_value_t = 0.95)]
+    pub confidence: f64,
+
+    /// Bootstrap resamples count
+    #[clap(long, default_value_t = 1000)]
+    pub resamples: usize,
+
+    /// Print the proposal as JSON instead of text
+    #[clap(long)]
+    pub json: bool,
+}
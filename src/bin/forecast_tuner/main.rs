@@ -0,0 +1,506 @@
+mod cli;
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use clap::Parser;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use laser_precision_adjust::box_plot::{quantile_sorted, QuantileMethod};
+use laser_precision_adjust::predict::Fragment;
+use laser_precision_adjust::{AutoAdjustLimits, Config, ForecastConfig, IDataPoint};
+
+/// Квантили прогноза: минимальный и максимальный прирост частоты за выстрел
+const Q_MIN: f64 = 0.05;
+const Q_MAX: f64 = 0.95;
+
+/// Запас к оценкам лимитов
+const LIMITS_MARGIN: f64 = 1.2;
+
+/// Оценка с доверительным интервалом
+#[derive(Clone, Copy, Serialize)]
+struct Estimate {
+    value: f64,
+    low: f64,
+    high: f64,
+}
+
+impl std::fmt::Display for Estimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.3} [{:.3}; {:.3}]", self.value, self.low, self.high)
+    }
+}
+
+/// Распределение прироста частоты за 1 выстрел
+#[derive(Serialize)]
+struct GrowthStat {
+    count: usize,
+    min: Estimate,
+    median: Estimate,
+    max: Estimate,
+}
+
+#[derive(Serialize)]
+struct ChannelGrowth {
+    channel: usize,
+    stat: GrowthStat,
+}
+
+#[derive(Serialize)]
+struct TypeProposal {
+    resonator_type: String,
+    channels: Vec<ChannelGrowth>,
+    pooled: GrowthStat,
+    forecast_config: ForecastConfig,
+}
+
+#[derive(Serialize)]
+struct LimitsProposal {
+    /// Шагов после края, только каналы с найденным краем
+    steps_used: Option<Estimate>,
+    freq_rise: Estimate,
+    per_step_growth: Option<GrowthStat>,
+    auto_adjust_limits: AutoAdjustLimits,
+}
+
+#[derive(Serialize)]
+struct Proposal {
+    types: Vec<TypeProposal>,
+    limits: Option<LimitsProposal>,
+    current_forecast_config: ForecastConfig,
+    current_auto_adjust_limits: AutoAdjustLimits,
+}
+
+// Подмножество полей отчета auto_adjust_all_*.json, NaN сериализуется как null
+#[derive(Deserialize)]
+struct ReportBoxPlot {
+    median: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct ReportMeasure {
+    current_boxplt: ReportBoxPlot,
+    steps_burned: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ReportRezInfo {
+    current_step: u32,
+    #[serde(default)]
+    edge_step: Option<u32>,
+    initial_freq: Option<f32>,
+    current_freq: Option<f32>,
+    history: Vec<ReportMeasure>,
+}
+
+fn main() -> Result<(), std::io::Error> {
+    let cmd = cli::Cli::parse();
+
+    let config = load_config(cmd.config)?;
+
+    let mut samples_by_type = BTreeMap::<String, Vec<Vec<f64>>>::new();
+    for arg in &cmd.fragments {
+        let (resonator_type, path) = parse_fragments_arg(arg);
+        let file = std::fs::File::open(&path)?;
+        let data = serde_json::from_reader::<_, Vec<Vec<Fragment<f64>>>>(file)
+            .map_err(std::io::Error::other)?;
+
+        let channels = samples_by_type.entry(resonator_type).or_default();
+        if channels.len() < data.len() {
+            channels.resize(data.len(), vec![]);
+        }
+        for (ch, fragments) in data.iter().enumerate() {
+            channels[ch].extend(fragments.iter().filter_map(shot_growth));
+        }
+    }
+
+    let mut rng = rand::thread_rng();
+
+    let types = samples_by_type
+        .into_iter()
+        .filter_map(|(resonator_type, channels)| {
+            let pooled_samples = channels.iter().flatten().copied().collect::<Vec<_>>();
+            let pooled = growth_stat(&pooled_samples, cmd.confidence, cmd.resamples, &mut rng)?;
+            let forecast_config = ForecastConfig {
+                min_freq_grow: pooled.min.value as f32,
+                max_freq_grow: pooled.max.value as f32,
+                median_freq_grow: pooled.median.value as f32,
+                ..config.forecast_config
+            };
+            let channels = channels
+                .iter()
+                .enumerate()
+                .filter_map(|(channel, samples)| {
+                    growth_stat(samples, cmd.confidence, cmd.resamples, &mut rng)
+                        .map(|stat| ChannelGrowth { channel, stat })
+                })
+                .collect();
+            Some(TypeProposal {
+                resonator_type,
+                channels,
+                pooled,
+                forecast_config,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let limits = if cmd.report.is_empty() {
+        None
+    } else {
+        let mut reports = vec![];
+        for path in &cmd.report {
+            let file = std::fs::File::open(path)?;
+            reports.extend(
+                serde_json::from_reader::<_, Vec<ReportRezInfo>>(file)
+                    .map_err(std::io::Error::other)?,
+            );
+        }
+        propose_limits(
+            &reports,
+            &config.auto_adjust_limits,
+            cmd.confidence,
+            cmd.resamples,
+            &mut rng,
+        )
+    };
+
+    let proposal = Proposal {
+        types,
+        limits,
+        current_forecast_config: config.forecast_config,
+        current_auto_adjust_limits: config.auto_adjust_limits,
+    };
+
+    if cmd.json {
+        serde_json::to_writer_pretty(std::io::stdout(), &proposal)
+            .map_err(std::io::Error::other)?;
+        println!();
+    } else {
+        print_proposal(&proposal);
+    }
+
+    Ok(())
+}
+
+fn load_config(path: Option<PathBuf>) -> Result<Config, std::io::Error> {
+    if let Some(path) = path {
+        let contents = std::fs::read_to_string(path)?;
+        serde_json::from_str::<Config>(&contents)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    } else {
+        Ok(Config::load().0)
+    }
+}
+
+fn parse_fragments_arg(arg: &str) -> (String, PathBuf) {
+    if let Some((resonator_type, path)) = arg.split_once('=') {
+        (resonator_type.to_owned(), PathBuf::from(path))
+    } else {
+        let path = PathBuf::from(arg);
+        let resonator_type = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or(arg.to_owned());
+        (resonator_type, path)
+    }
+}
+
+/// Прирост частоты за выстрел: прогноз конечной частоты фрагмента минус частота в момент выстрела
+fn shot_growth(fragment: &Fragment<f64>) -> Option<f64> {
+    if !fragment.is_valid() {
+        return None;
+    }
+
+    let growth = fragment.target() - fragment.points().first()?.y();
+    if growth.is_finite() {
+        Some(growth)
+    } else {
+        None
+    }
+}
+
+/// Квантиль с линейной интерполяцией по отсортированному ряду
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    quantile_sorted(sorted, q.clamp(0.0, 1.0), QuantileMethod::Linear)
+}
+
+/// Оценка квантиля q с доверительным интервалом методом bootstrap
+fn bootstrap_quantile(
+    samples: &[f64],
+    q: f64,
+    confidence: f64,
+    resamples: usize,
+    rng: &mut impl Rng,
+) -> Estimate {
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let mut estimates = (0..resamples)
+        .map(|_| {
+            let mut resample = (0..sorted.len())
+                .map(|_| sorted[rng.gen_range(0..sorted.len())])
+                .collect::<Vec<_>>();
+            resample.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            quantile(&resample, q)
+        })
+        .collect::<Vec<_>>();
+    estimates.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let alpha = (1.0 - confidence) / 2.0;
+    Estimate {
+        value: quantile(&sorted, q),
+        low: quantile(&estimates, alpha),
+        high: quantile(&estimates, 1.0 - alpha),
+    }
+}
+
+fn growth_stat(
+    samples: &[f64],
+    confidence: f64,
+    resamples: usize,
+    rng: &mut impl Rng,
+) -> Option<GrowthStat> {
+    if samples.is_empty() {
+        return None;
+    }
+
+    Some(GrowthStat {
+        count: samples.len(),
+        min: bootstrap_quantile(samples, Q_MIN, confidence, resamples, rng),
+        median: bootstrap_quantile(samples, 0.5, confidence, resamples, rng),
+        max: bootstrap_quantile(samples, Q_MAX, confidence, resamples, rng),
+    })
+}
+
+fn propose_limits(
+    reports: &[ReportRezInfo],
+    current: &AutoAdjustLimits,
+    confidence: f64,
+    resamples: usize,
+    rng: &mut impl Rng,
+) -> Option<LimitsProposal> {
+    let finished = reports
+        .iter()
+        .filter_map(|r| match (r.initial_freq, r.current_freq) {
+            (Some(fi), Some(fc)) if fi.is_finite() && fc.is_finite() => Some((
+                // лимит MaxForwardSteps отсчитывается от края
+                r.edge_step.map(|e| r.current_step.saturating_sub(e) as f64),
+                (fc - fi) as f64,
+            )),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if finished.is_empty() {
+        return None;
+    }
+
+    let steps = finished.iter().filter_map(|(s, _)| *s).collect::<Vec<_>>();
+    let rise = finished.iter().map(|(_, r)| *r).collect::<Vec<_>>();
+
+    // прирост частоты на 1 шаг между соседними измерениями истории
+    let per_step = reports
+        .iter()
+        .flat_map(|r| {
+            r.history.windows(2).filter_map(|w| {
+                match (
+                    w[0].steps_burned,
+                    w[0].current_boxplt.median,
+                    w[1].current_boxplt.median,
+                ) {
+                    (Some(n), Some(f0), Some(f1)) if n > 0 => Some((f1 - f0) as f64 / n as f64),
+                    _ => None,
+                }
+            })
+        })
+        .filter(|g| g.is_finite())
+        .collect::<Vec<_>>();
+
+    let steps_used =
+        (!steps.is_empty()).then(|| bootstrap_quantile(&steps, Q_MAX, confidence, resamples, rng));
+    let freq_rise = bootstrap_quantile(&rise, Q_MAX, confidence, resamples, rng);
+
+    Some(LimitsProposal {
+        steps_used,
+        freq_rise,
+        per_step_growth: growth_stat(&per_step, confidence, resamples, rng),
+        auto_adjust_limits: AutoAdjustLimits {
+            min_freq_offset: ((freq_rise.high * LIMITS_MARGIN * 2.0).ceil() / 2.0) as f32,
+            max_forward_steps: steps_used
+                .map(|s| (s.high * LIMITS_MARGIN).ceil() as u32)
+                .unwrap_or(current.max_forward_steps),
+            ..*current
+        },
+    })
+}
+
+fn diff_line(name: &str, current: f64, proposed: f64, estimate: Option<&Estimate>) {
+    let change = if current != 0.0 {
+        format!("{:+.1}%", (proposed - current) / current.abs() * 100.0)
+    } else {
+        "-".to_owned()
+    };
+    if let Some(estimate) = estimate {
+        println!(
+            "  {name}: {current} -> {proposed:.3} [{:.3}; {:.3}] ({change})",
+            estimate.low, estimate.high
+        );
+    } else {
+        println!("  {name}: {current} -> {proposed} ({change})");
+    }
+}
+
+fn print_proposal(proposal: &Proposal) {
+    let current = &proposal.current_forecast_config;
+
+    for t in &proposal.types {
+        println!("\nResonator type '{}':", t.resonator_type);
+        println!("  Channel\t| Shots\t| Min\t\t\t| Median\t\t| Max");
+        for ch in &t.channels {
+            println!(
+                "  {}\t\t| {}\t| {}\t| {}\t| {}",
+                ch.channel, ch.stat.count, ch.stat.min, ch.stat.median, ch.stat.max
+            );
+        }
+        println!(
+            "  All\t\t| {}\t| {}\t| {}\t| {}",
+            t.pooled.count, t.pooled.min, t.pooled.median, t.pooled.max
+        );
+
+        println!("ForecastConfig:");
+        diff_line(
+            "MinFreqGrow",
+            current.min_freq_grow as f64,
+            t.pooled.min.value,
+            Some(&t.pooled.min),
+        );
+        diff_line(
+            "MaxFreqGrow",
+            current.max_freq_grow as f64,
+            t.pooled.max.value,
+            Some(&t.pooled.max),
+        );
+        diff_line(
+            "MedianFreqGrow",
+            current.median_freq_grow as f64,
+            t.pooled.median.value,
+            Some(&t.pooled.median),
+        );
+    }
+
+    if let Some(limits) = &proposal.limits {
+        let current = &proposal.current_auto_adjust_limits;
+        let proposed = &limits.auto_adjust_limits;
+
+        println!("\nReports:");
+        if let Some(steps_used) = &limits.steps_used {
+            println!("  Steps after edge (q95): {}", steps_used);
+        }
+        println!("  Frequency rise (q95): {}", limits.freq_rise);
+        if let Some(per_step) = &limits.per_step_growth {
+            println!(
+                "  Growth per step: min={}, median={}, max={} ({} samples)",
+                per_step.min, per_step.median, per_step.max, per_step.count
+            );
+        }
+
+        println!("AutoAdjustLimits:");
+        diff_line(
+            "MinFreqOffset",
+            current.min_freq_offset as f64,
+            proposed.min_freq_offset as f64,
+            None,
+        );
+        diff_line(
+            "MaxForwardSteps",
+            current.max_forward_steps as f64,
+            proposed.max_forward_steps as f64,
+            None,
+        );
+        diff_line(
+            "FastForwardStepLimit",
+            current.fast_forward_step_limit as f64,
+            proposed.fast_forward_step_limit as f64,
+            None,
+        );
+        diff_line(
+            "EdgeDetectSintervalSt",
+            current.edge_detect_interval as f64,
+            proposed.edge_detect_interval as f64,
+            None,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn fragments_arg() {
+        let (resonator_type, path) = parse_fragments_arg("RK-169=/data/batch-fragments.json");
+        assert_eq!(resonator_type, "RK-169");
+        assert_eq!(path, PathBuf::from("/data/batch-fragments.json"));
+
+        let (resonator_type, _) = parse_fragments_arg("/data/rk206.json");
+        assert_eq!(resonator_type, "rk206");
+    }
+
+    #[test]
+    fn bootstrap() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let samples = (0..=100).map(|v| v as f64).collect::<Vec<_>>();
+
+        let median = bootstrap_quantile(&samples, 0.5, 0.9, 500, &mut rng);
+        assert_eq!(median.value, 50.0);
+        assert!(median.low < median.value && median.value < median.high);
+        assert!(median.low > 40.0 && median.high < 60.0);
+
+        assert!(growth_stat(&[], 0.9, 500, &mut rng).is_none());
+        let stat = growth_stat(&samples, 0.9, 500, &mut rng).unwrap();
+        assert_eq!(stat.count, samples.len());
+        assert!(stat.min.value < stat.median.value && stat.median.value < stat.max.value);
+    }
+
+    #[test]
+    fn limits() {
+        let current = serde_json::from_str::<AutoAdjustLimits>(
+            r#"{"MinFreqOffset": 5.0, "MaxForwardSteps": 50,
+                "FastForwardStepLimit": 3, "EdgeDetectSintervalSt": 7}"#,
+        )
+        .unwrap();
+        let reports = serde_json::from_str::<Vec<ReportRezInfo>>(
+            r#"[
+                {"current_step": 10, "edge_step": 4, "initial_freq": 32760.0, "current_freq": 32768.0,
+                 "history": [
+                    {"current_boxplt": {"median": 32760.0}, "steps_burned": 2},
+                    {"current_boxplt": {"median": 32761.0}, "steps_burned": null}
+                 ]},
+                {"current_step": 20, "initial_freq": 32758.0, "current_freq": 32768.0,
+                 "history": [
+                    {"current_boxplt": {"median": 32758.0}, "steps_burned": 1},
+                    {"current_boxplt": {"median": null}, "steps_burned": null}
+                 ]},
+                {"current_step": 0, "initial_freq": null, "current_freq": null, "history": []}
+            ]"#,
+        )
+        .unwrap();
+
+        let mut rng = StdRng::seed_from_u64(1);
+        assert!(propose_limits(&reports[2..], &current, 0.9, 500, &mut rng).is_none());
+
+        let proposal = propose_limits(&reports, &current, 0.9, 500, &mut rng).unwrap();
+        // шаги считаются от края, канал без края не учитывается
+        assert_eq!(proposal.steps_used.unwrap().value, 6.0);
+        assert_eq!(proposal.freq_rise.value, 9.9);
+        assert_eq!(proposal.per_step_growth.as_ref().unwrap().median.value, 0.5);
+
+        let limits = proposal.auto_adjust_limits;
+        assert_eq!(limits.max_forward_steps, 8);
+        assert_eq!(limits.min_freq_offset, 12.0);
+        // остальные лимиты не меняются
+        assert_eq!(limits.fast_forward_step_limit, 3);
+        assert_eq!(limits.edge_detect_interval, 7);
+    }
+}
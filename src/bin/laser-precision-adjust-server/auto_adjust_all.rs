@@ -1,18 +1,79 @@
-use std::{path::PathBuf, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 use chrono::{DateTime, Local};
 use laser_precision_adjust::{
-    box_plot::BoxPlot, AdjustConfig, AutoAdjustLimits, ForecastConfig, PrivStatusEvent,
+    adjust_event::AdjustEvent,
+    box_plot::{BoxPlot, BoxPlotConfig},
+    coordinates::CoordiantesCalc,
+    crosstalk::{CrosstalkConfig, CrosstalkStore},
+    drift::{ChannelDrift, VerifySweepConfig},
+    edge_store::EdgeStore,
+    filters::{apply_filters_uniform, FilterStage},
+    fragment_store::save_json,
+    kalman::{FrequencyKalman, MeasureKalmanConfig},
+    noise::NoiseConfig,
+    predict::Predictor,
+    strategy::{
+        Action, AdjustGoal, AdjustStrategy, BanReason, EdgeSearch, GrowForecast, InterleavedConfig,
+        InterleavedStrategy, Observation,
+    },
+    AdjustConfig, AutoAdjustLimits, AxisConfig, ForecastConfig, PrivStatusEvent,
+    ResonatroPlacement, SchedulingConfig,
 };
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use tokio::sync::{watch, Mutex};
 
-use crate::far_long_iterator::{FarLongIterator, FarLongIteratorItem, IntoFarLongIterator};
+use crate::{
+    crosstalk_tracker::CrosstalkTracker,
+    far_long_iterator::{FarLongIterator, FarLongIteratorItem, IntoFarLongIterator},
+    noise_characterization::NoiseProfiles,
+};
 
 const MEASRE_COUNT_NORMAL: u32 = 3;
+const MEASRE_COUNT_MAX: u32 = 20;
 const MIN_TOUCH_WAIT: f32 = 5.0;
 const MAX_NEG_DRIFT_HZ: f32 = 0.1;
+const WORK_TRYS: u32 = 3;
+const CHECKPOINT_FILE_NAME: &str = "auto_adjust_all_checkpoint.json";
+
+/// Фильтр Калмана частоты канала: сохраняется между обращениями к каналу и сбрасывается прожигом
+#[derive(Clone, Debug)]
+struct ChannelKalman {
+    config: MeasureKalmanConfig,
+    filter: FrequencyKalman,
+    last_update: Option<std::time::Instant>,
+}
+
+impl ChannelKalman {
+    fn new(config: MeasureKalmanConfig) -> Self {
+        Self {
+            config,
+            filter: FrequencyKalman::from_config(&config),
+            last_update: None,
+        }
+    }
+
+    fn update(&mut self, timestamp: std::time::Instant, f: f32) {
+        let dt = self
+            .last_update
+            .map(|t| (timestamp - t).as_secs_f64())
+            .unwrap_or_default();
+        self.filter.update(dt, f as f64);
+        self.last_update = Some(timestamp);
+    }
+
+    /// Частота после прожига меняется скачком, прошлые измерения больше не нужны
+    fn reset(&mut self) {
+        self.filter.reset();
+        self.last_update = None;
+    }
+}
 
 enum MeasureResult {
     Stable(f32, BoxPlot<f32>),
@@ -20,15 +81,85 @@ enum MeasureResult {
     OutOfRange(f32, BoxPlot<f32>),
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum ChannelState {
     UnknownInit,
-    Adjustig(String),
+    Adjustig(AdjustingStatus),
     Unsatable,
     OutOfRange,
     Limit,
+    /// Исчерпан запас шагов канала, настройка остановлена до попадания в допуск
+    BudgetExhausted,
     Verify,
     Ok,
+    /// Был настроен, но на контрольном проходе вышел из допуска
+    Drifted,
+    /// Был настроен, но ни одно контрольное измерение не удалось
+    Unverified,
+    Skipped,
+}
+
+/// Итог последнего обращения к настраиваемому каналу
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AdjustingStatus {
+    /// Стабильное измерение, настройка продолжается
+    Normal,
+    /// Частота ниже последней проверенной, измерение отброшено
+    IncorrectMeasure,
+    /// Частота нестабильна, trys - счетчик попыток
+    Unstable { iqr: f32, trys: u32 },
+    /// Вне диапазона настройки, будет повторная попытка
+    OutOfRange,
+}
+
+/// Индивидуальные настройки канала для автонастройки, не заданное берется из общих настроек
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChannelOverride {
+    /// Не настраивать канал, например пустое место в оснастке
+    #[serde(rename = "Skip", default)]
+    pub skip: bool,
+
+    /// Целевая частота канала, Гц
+    #[serde(
+        rename = "TargetFreq",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub target: Option<f32>,
+
+    /// Допустимое отклонение от целевой частоты, Гц
+    #[serde(
+        rename = "ToleranceHz",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub tolerance_hz: Option<f32>,
+
+    /// Максимальный шаг настройки
+    #[serde(rename = "MaxSteps", default, skip_serializing_if = "Option::is_none")]
+    pub max_steps: Option<u32>,
+
+    /// Множитель мощности прожига
+    #[serde(
+        rename = "PowerMultiplier",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub power_multiplier: Option<f32>,
+
+    /// Ожидаемый уход частоты при герметизации, канал настраивается на цель минус уход, Гц
+    #[serde(
+        rename = "SealingShiftHz",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sealing_shift_hz: Option<f32>,
+}
+
+impl ChannelOverride {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
 }
 
 enum BurnEvent {
@@ -37,18 +168,6 @@ enum BurnEvent {
 }
 
 impl ChannelState {
-    pub fn to_status_icon(&self) -> String {
-        match self {
-            ChannelState::UnknownInit => "Неизвестно".to_owned(),
-            ChannelState::Adjustig(s) => format!("Настройка [{s}]"),
-            ChannelState::Unsatable => "Сломан или нестабилен".to_owned(),
-            ChannelState::OutOfRange => "Вне диапазона настройки".to_owned(),
-            ChannelState::Limit => "Превышен лимит шагов".to_owned(),
-            ChannelState::Verify => "Проверка".to_owned(),
-            ChannelState::Ok => "Настроен".to_owned(),
-        }
-    }
-
     pub fn is_adjusting(&self) -> bool {
         if let ChannelState::Adjustig(_) = *self {
             true
@@ -66,36 +185,106 @@ impl ChannelState {
     }
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Measure {
     pub current_boxplt: BoxPlot<f32>,
     pub steps_burned: Option<u32>,
     pub current_state: ChannelState,
+    /// Стабильное измерение, по которому принималось решение
+    #[serde(default)]
+    pub stable: bool,
+}
+
+/// Ограничения на количество шагов канала
+#[derive(Clone, Copy, Debug)]
+struct StepLimits {
+    /// Шагов после обнаружения края
+    max_forward_steps: u32,
+    /// Полный ход лазера
+    total_vertical_steps: u32,
 }
 
 #[derive(Clone)]
 pub struct ChannelRef {
     id: usize,
     last_touched: DateTime<Local>,
-    total_channels: usize,
+    /// Центр резонатора в координатах лазера
+    position: (f32, f32),
+    /// Скорость холостого перемещения лазера, ед./с
+    travel_speed: f32,
     state: ChannelState,
     initial_freq: Option<f32>,
     current_freq: Option<f32>,
     current_step: u32,
+    /// Шаг, на котором обнаружен край
+    edge_step: Option<u32>,
+    step_limits: StepLimits,
     history: Vec<Measure>,
+    ready_at: Option<DateTime<Local>>,
+    overrides: ChannelOverride,
+    /// События последнего обращения к каналу
+    events: Vec<AdjustEvent>,
+    /// Контрольные измерения после окончания настройки
+    drift: Option<ChannelDrift>,
+    /// Фильтр Калмана окон измерения, если включен
+    kalman: Option<ChannelKalman>,
 }
 
 impl ChannelRef {
-    pub fn new(id: usize, total_channels: usize, last_touched: DateTime<Local>) -> Self {
+    pub fn new(
+        id: usize,
+        position: (f32, f32),
+        travel_speed: f32,
+        last_touched: DateTime<Local>,
+        step_limits: StepLimits,
+        overrides: ChannelOverride,
+    ) -> Self {
         Self {
             id,
             last_touched,
-            total_channels,
-            state: ChannelState::UnknownInit,
+            position,
+            travel_speed,
+            state: if overrides.skip {
+                ChannelState::Skipped
+            } else {
+                ChannelState::UnknownInit
+            },
             initial_freq: None,
             current_freq: None,
             current_step: 0,
+            edge_step: None,
+            step_limits,
             history: vec![],
+            ready_at: None,
+            overrides,
+            events: vec![],
+            drift: None,
+            kalman: None,
+        }
+    }
+
+    /// Восстановить состояние канала из контрольной точки
+    fn restore(mut self, saved: ChannelCheckpoint) -> Self {
+        self.state = saved.state;
+        self.initial_freq = saved.initial_freq;
+        self.current_freq = saved.current_freq;
+        self.current_step = saved.current_step;
+        self.edge_step = saved.edge_step;
+        self.history = saved.history;
+        self
+    }
+
+    fn checkpoint(&self, trys: Trys<WORK_TRYS>) -> ChannelCheckpoint {
+        ChannelCheckpoint {
+            id: self.id,
+            state: self.state.clone(),
+            initial_freq: self.initial_freq,
+            current_freq: self.current_freq,
+            current_step: self.current_step,
+            edge_step: self.edge_step,
+            history: self.history.clone(),
+            trys,
+            overrides: self.overrides,
         }
     }
 
@@ -120,10 +309,16 @@ impl ChannelRef {
             ok
         );
 
+        self.events.push(AdjustEvent::Measured {
+            freq,
+            interval: None,
+            box_plot: Some(history_boxplot),
+        });
         self.history.push(Measure {
             current_boxplt: history_boxplot,
             steps_burned: None,
             current_state: state.clone(),
+            stable: ok,
         });
 
         if self.initial_freq.is_none() && ok {
@@ -145,6 +340,23 @@ impl ChannelRef {
         self.state = state;
     }
 
+    fn event(&mut self, event: AdjustEvent) {
+        self.events.push(event);
+    }
+
+    /// Целевая частота в вакууме и допуск канала с учетом его индивидуальных настроек
+    fn target_tolerance(&self, target: f32, precision_ppm: f32) -> (f32, f32) {
+        let target = self.overrides.target.unwrap_or(target);
+        let tolerance = self
+            .overrides
+            .tolerance_hz
+            .unwrap_or(target * precision_ppm / 1_000_000.0);
+        (
+            target - self.overrides.sealing_shift_hz.unwrap_or_default(),
+            tolerance,
+        )
+    }
+
     fn get_state(&self) -> ChannelState {
         self.state.clone()
     }
@@ -153,6 +365,24 @@ impl ChannelRef {
         self.current_step
     }
 
+    /// Последний допустимый шаг: ход лазера, лимит шагов после края и индивидуальный лимит
+    fn step_budget(&self) -> u32 {
+        [
+            Some(self.step_limits.total_vertical_steps),
+            self.edge_step
+                .map(|e| e + self.step_limits.max_forward_steps),
+            self.overrides.max_steps,
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap()
+    }
+
+    fn steps_left(&self) -> u32 {
+        self.step_budget().saturating_sub(self.current_step)
+    }
+
     fn set_step(&mut self, step: u32) {
         let steps_burned = step.saturating_sub(self.current_step);
         self.current_step = step;
@@ -161,20 +391,33 @@ impl ChannelRef {
             .map(|l| l.steps_burned.replace(steps_burned));
     }
 
-    fn age_found(&self, offset: f32) -> bool {
-        if let Some(initial_freq) = self.initial_freq {
-            if let Some(current_freq) = self.current_freq {
-                return current_freq - initial_freq > offset;
-            }
-        }
-        false
+    /// Перемещение без прожига, в историю не попадает
+    fn move_to(&mut self, step: u32) {
+        self.current_step = step;
+    }
+
+    /// Стабильные частоты канала, от старых к новым
+    fn stable_history(&self) -> Vec<f32> {
+        self.history
+            .iter()
+            .filter(|m| m.stable)
+            .map(|m| m.current_boxplt.median())
+            .collect()
     }
 
     fn current_stable_freq(&self) -> Option<f32> {
         match self.state {
-            ChannelState::UnknownInit | ChannelState::Unsatable | ChannelState::OutOfRange => None,
+            ChannelState::UnknownInit
+            | ChannelState::Unsatable
+            | ChannelState::OutOfRange
+            | ChannelState::Skipped => None,
             ChannelState::Adjustig(_) => self.current_freq,
-            ChannelState::Limit | ChannelState::Verify | ChannelState::Ok => self.current_freq,
+            ChannelState::Limit
+            | ChannelState::BudgetExhausted
+            | ChannelState::Drifted
+            | ChannelState::Unverified
+            | ChannelState::Verify
+            | ChannelState::Ok => self.current_freq,
         }
     }
 
@@ -196,25 +439,27 @@ impl ChannelRef {
 }
 
 impl FarLongIteratorItem for ChannelRef {
-    fn distance(&self, other: &Self) -> u64 {
-        let forward_distance = if self.id > other.id {
-            self.id - other.id
-        } else {
-            other.id - self.id
-        };
-        let wraped_distance = if self.id > other.id {
-            other.id + self.total_channels - self.id
-        } else {
-            self.id + self.total_channels - other.id
-        };
+    fn distance(&self, other: &Self) -> f64 {
+        let dx = (self.position.0 - other.position.0) as f64;
+        let dy = (self.position.1 - other.position.1) as f64;
+        dx.hypot(dy)
+    }
 
-        std::cmp::min(forward_distance, wraped_distance) as u64
+    fn travel_time(&self, other: &Self) -> f64 {
+        // оси перемещаются одновременно, время определяет самое длинное перемещение
+        let dx = (self.position.0 - other.position.0).abs();
+        let dy = (self.position.1 - other.position.1).abs();
+        (dx.max(dy) / self.travel_speed) as f64
     }
 
     fn last_touched(&self) -> DateTime<Local> {
         self.last_touched
     }
 
+    fn ready_at(&self) -> Option<DateTime<Local>> {
+        self.ready_at
+    }
+
     fn is_valid(&self) -> bool {
         match self.state {
             ChannelState::UnknownInit | ChannelState::Verify => true,
@@ -228,6 +473,51 @@ impl FarLongIteratorItem for ChannelRef {
 pub enum Error {
     AdjustInProgress,
     NothingToCancel,
+    NotActive,
+    NoCheckpoint,
+}
+
+/// Состояние канала в контрольной точке партии
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ChannelCheckpoint {
+    pub id: usize,
+    pub state: ChannelState,
+    pub initial_freq: Option<f32>,
+    pub current_freq: Option<f32>,
+    pub current_step: u32,
+    #[serde(default)]
+    pub edge_step: Option<u32>,
+    pub history: Vec<Measure>,
+    trys: Trys<WORK_TRYS>,
+    #[serde(default)]
+    overrides: ChannelOverride,
+}
+
+/// Контрольная точка партии, перезаписывается после каждого действия автонастройки
+#[derive(Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub saved: String,
+    pub target: f32,
+    pub channels: Vec<ChannelCheckpoint>,
+}
+
+impl Checkpoint {
+    fn path(report_directory: &Path) -> PathBuf {
+        report_directory.join(CHECKPOINT_FILE_NAME)
+    }
+
+    pub fn load(report_directory: &Path) -> std::io::Result<Self> {
+        let file = std::fs::File::open(Self::path(report_directory))?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    fn save(&self, report_directory: &Path) -> std::io::Result<()> {
+        save_json(&Self::path(report_directory), self)
+    }
+
+    fn remove(report_directory: &Path) {
+        std::fs::remove_file(Self::path(report_directory)).ok();
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -238,6 +528,15 @@ pub enum ProgressStatus {
     /// Настройка
     Adjusting,
 
+    /// Приостановлено оператором
+    Paused,
+
+    /// Выдержка перед контрольным проходом
+    Settling,
+
+    /// Контрольный проход по настроенным каналам
+    Verifying { sweep: u32 },
+
     /// Завершено
     Done,
 
@@ -250,6 +549,9 @@ impl std::fmt::Display for ProgressStatus {
         match self {
             ProgressStatus::Idle => write!(f, "Ожидание"),
             ProgressStatus::Adjusting => write!(f, "Настройка"),
+            ProgressStatus::Paused => write!(f, "Пауза"),
+            ProgressStatus::Settling => write!(f, "Выдержка перед проверкой"),
+            ProgressStatus::Verifying { sweep } => write!(f, "Контрольный проход {sweep}"),
             ProgressStatus::Done => write!(f, "Завершено"),
             ProgressStatus::Error(e) => write!(f, "Ошибка: {e}"),
         }
@@ -260,10 +562,22 @@ impl std::fmt::Display for ProgressStatus {
 pub struct RezInfo {
     pub id: usize,
     pub current_step: u32,
+    /// Сколько шагов канала еще можно сделать
+    pub steps_left: u32,
     pub initial_freq: f32,
     pub current_freq: f32,
-    pub state: String,
+    pub state: ChannelState,
+    pub edge_found: bool,
+    /// Шаг, на котором найден край
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edge_step: Option<u32>,
     pub history: Vec<Measure>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overrides: Option<ChannelOverride>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<AdjustEvent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drift: Option<ChannelDrift>,
 }
 
 #[derive(Debug, Clone)]
@@ -328,12 +642,27 @@ pub struct AutoAdjustAllController {
     update_interval: Duration,
     forecast_config: ForecastConfig,
     fast_forward_step_limit: u32,
+    total_vertical_steps: u32,
     switch_channel_delay_ms: u32,
     freqmeter_config: Arc<Mutex<AdjustConfig>>,
     report_directory: PathBuf,
+    measure_filters: Vec<FilterStage>,
+    measure_kalman: Option<MeasureKalmanConfig>,
+    box_plot_config: BoxPlotConfig,
+    noise_config: NoiseConfig,
+    noise_profiles: NoiseProfiles,
+    predictor: Arc<Mutex<Predictor<f64>>>,
+    resonator_placement: Vec<ResonatroPlacement>,
+    crosstalk: Option<CrosstalkConfig>,
+    axis_config: AxisConfig,
+    scheduling: Option<SchedulingConfig>,
+    verify_sweep: Option<VerifySweepConfig>,
+    edge_store: Option<EdgeStore>,
+    crosstalk_store: Option<CrosstalkStore>,
 
     task: Option<tokio::task::JoinHandle<()>>,
     rx: Option<watch::Receiver<ProgressReport>>,
+    pause_tx: Option<watch::Sender<bool>>,
 }
 
 impl AutoAdjustAllController {
@@ -346,9 +675,23 @@ impl AutoAdjustAllController {
         update_interval: Duration,
         forecast_config: ForecastConfig,
         fast_forward_step_limit: u32,
+        total_vertical_steps: u32,
         switch_channel_delay_ms: u32,
         freqmeter_config: Arc<Mutex<AdjustConfig>>,
         report_directory: PathBuf,
+        measure_filters: Vec<FilterStage>,
+        measure_kalman: Option<MeasureKalmanConfig>,
+        box_plot_config: BoxPlotConfig,
+        noise_config: NoiseConfig,
+        noise_profiles: NoiseProfiles,
+        predictor: Arc<Mutex<Predictor<f64>>>,
+        resonator_placement: Vec<ResonatroPlacement>,
+        crosstalk: Option<CrosstalkConfig>,
+        axis_config: AxisConfig,
+        scheduling: Option<SchedulingConfig>,
+        verify_sweep: Option<VerifySweepConfig>,
+        edge_store: Option<EdgeStore>,
+        crosstalk_store: Option<CrosstalkStore>,
     ) -> Self {
         Self {
             channel_count,
@@ -359,12 +702,27 @@ impl AutoAdjustAllController {
             update_interval,
             forecast_config,
             fast_forward_step_limit,
+            total_vertical_steps,
             switch_channel_delay_ms,
             freqmeter_config,
             report_directory,
+            measure_filters,
+            measure_kalman,
+            box_plot_config,
+            noise_config,
+            noise_profiles,
+            predictor,
+            resonator_placement,
+            crosstalk,
+            axis_config,
+            scheduling,
+            verify_sweep,
+            edge_store,
+            crosstalk_store,
 
             task: None,
             rx: None,
+            pause_tx: None,
         }
     }
 
@@ -384,23 +742,120 @@ impl AutoAdjustAllController {
             .unwrap_or(ProgressReport::default())
     }
 
-    pub async fn adjust(&mut self, target: f32) -> Result<(), Error> {
-        if let Some(task) = &self.task {
-            if !task.is_finished() {
-                return Err(Error::AdjustInProgress);
+    /// Начать настройку каналов selection (все, если None) с индивидуальными настройками overrides
+    pub async fn adjust(
+        &mut self,
+        target: f32,
+        selection: Option<&[usize]>,
+        overrides: &HashMap<usize, ChannelOverride>,
+    ) -> Result<(), Error> {
+        // явные настройки важнее импортированной таблицы целей
+        let adjust_config = self.freqmeter_config.lock().await.clone();
+        let overrides = (0..self.channel_count)
+            .map(|ch| {
+                let mut o = overrides.get(&ch).copied().unwrap_or_default();
+                if selection.is_some_and(|s| !s.contains(&ch)) {
+                    o.skip = true;
+                }
+                if let Some(entry) = adjust_config.channel_target_entry(ch) {
+                    o.target = o.target.or(Some(entry.target));
+                    o.tolerance_hz = o.tolerance_hz.or(entry.tolerance_hz);
+                }
+                let shift = adjust_config.sealing_shift_hz(ch, o.target.unwrap_or(target));
+                if shift != 0.0 {
+                    // в отчете - уход и цель, с которыми шла настройка, а не текущая модель
+                    o.target = o.target.or(Some(target));
+                    o.sealing_shift_hz = o.sealing_shift_hz.or(Some(shift));
+                }
+                o
+            })
+            .collect();
+        self.start(target, vec![None; self.channel_count], overrides)
+            .await
+    }
+
+    /// Продолжить прерванную партию с последней контрольной точки
+    pub async fn resume_batch(&mut self) -> Result<(), Error> {
+        if self.is_running() {
+            return Err(Error::AdjustInProgress);
+        }
+
+        let checkpoint = match Checkpoint::load(&self.report_directory) {
+            Ok(checkpoint) => checkpoint,
+            Err(e) => {
+                tracing::error!("Failed to load adjust all checkpoint: {e}");
+                return Err(Error::NoCheckpoint);
             }
+        };
+        if checkpoint.channels.len() != self.channel_count {
+            tracing::error!(
+                "Checkpoint channel count {} != {}",
+                checkpoint.channels.len(),
+                self.channel_count
+            );
+            return Err(Error::NoCheckpoint);
+        }
+
+        tracing::info!("Resume adjust all from checkpoint {}", checkpoint.saved);
+        let overrides = checkpoint.channels.iter().map(|c| c.overrides).collect();
+        self.start(
+            checkpoint.target,
+            checkpoint.channels.into_iter().map(Some).collect(),
+            overrides,
+        )
+        .await
+    }
+
+    async fn start(
+        &mut self,
+        target: f32,
+        saved: Vec<Option<ChannelCheckpoint>>,
+        overrides: Vec<ChannelOverride>,
+    ) -> Result<(), Error> {
+        if self.is_running() {
+            return Err(Error::AdjustInProgress);
         }
 
-        let channel_count = self.channel_count;
         let fake_last_touch = Local::now() - Duration::from_secs_f32(MIN_TOUCH_WAIT);
-        let channels = (0..channel_count)
-            .map(move |ch_id| ChannelRef::new(ch_id, channel_count, fake_last_touch))
-            .collect::<Vec<_>>();
+        let step_limits = StepLimits {
+            max_forward_steps: self.auto_adjust_limits.max_forward_steps,
+            total_vertical_steps: self.total_vertical_steps,
+        };
+        let travel_speed = self
+            .scheduling
+            .map(|s| s.travel_speed)
+            .unwrap_or(f32::INFINITY);
+        // шаги восстановленных каналов передаются лазеру при следующем прожиге
+        let (channels, trys_counters): (Vec<_>, Vec<_>) = self
+            .resonator_placement
+            .iter()
+            .zip(saved.into_iter().zip(overrides))
+            .enumerate()
+            .map(|(ch_id, (placement, (saved, overrides)))| {
+                let ch = ChannelRef::new(
+                    ch_id,
+                    placement.center_abs(&self.axis_config),
+                    travel_speed,
+                    fake_last_touch,
+                    step_limits,
+                    overrides,
+                );
+                match saved {
+                    Some(saved) => {
+                        let trys = saved.trys;
+                        (ch.restore(saved), trys)
+                    }
+                    None => (ch, Trys::default()),
+                }
+            })
+            .unzip();
 
         let (tx, rx) = watch::channel(ProgressReport::default());
+        let (pause_tx, pause_rx) = watch::channel(false);
         let precision_ppm = self.freqmeter_config.lock().await.working_offset_ppm;
 
         self.rx.replace(rx);
+        self.pause_tx.replace(pause_tx);
 
         self.task.replace(tokio::spawn(adjust_task(
             tx,
@@ -409,25 +864,73 @@ impl AutoAdjustAllController {
             self.auto_adjust_limits,
             self.update_interval,
             precision_ppm,
-            channels.into_far_long_iterator(
-                chrono::Duration::from_std(self.update_interval * 2).unwrap(),
-            ),
+            channels
+                .into_far_long_iterator(
+                    chrono::Duration::from_std(self.update_interval * 2).unwrap(),
+                )
+                .with_policy(self.scheduling),
+            trys_counters,
             target,
             self.forecast_config,
             self.fast_forward_step_limit,
             self.precision_adjust.clone(),
             self.switch_channel_delay_ms,
             self.report_directory.clone(),
+            self.measure_filters.clone(),
+            self.measure_kalman,
+            self.box_plot_config,
+            self.noise_config,
+            self.noise_profiles.clone(),
+            self.predictor.clone(),
+            self.crosstalk.map(|c| {
+                CrosstalkTracker::new(
+                    c,
+                    &self.resonator_placement,
+                    &self.axis_config,
+                    self.crosstalk_store.clone(),
+                )
+            }),
+            self.verify_sweep,
+            self.edge_store.clone(),
+            pause_rx,
         )));
 
         Ok(())
     }
 
+    fn is_running(&self) -> bool {
+        self.task.as_ref().is_some_and(|t| !t.is_finished())
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.is_running() && self.pause_tx.as_ref().is_some_and(|tx| *tx.borrow())
+    }
+
+    /// Приостановить настройку после текущей итерации, состояние каналов сохраняется
+    pub fn pause(&mut self) -> Result<(), Error> {
+        self.set_paused(true)
+    }
+
+    pub fn resume(&mut self) -> Result<(), Error> {
+        self.set_paused(false)
+    }
+
+    fn set_paused(&mut self, paused: bool) -> Result<(), Error> {
+        match &self.pause_tx {
+            Some(tx) if self.is_running() => {
+                tx.send_replace(paused);
+                Ok(())
+            }
+            _ => Err(Error::NotActive),
+        }
+    }
+
     pub fn cancel(&mut self) -> Result<(), Error> {
         if let Some(task) = &self.task {
             if !task.is_finished() {
                 task.abort();
                 self.task = None;
+                self.pause_tx = None;
                 //self.rx = None;
 
                 return Ok(());
@@ -438,7 +941,7 @@ impl AutoAdjustAllController {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 enum Trys<const TRYS: u32> {
     Ok,
     Incorrect(u32),
@@ -507,52 +1010,136 @@ async fn adjust_task(
     update_interval: Duration,
     precision_ppm: f32,
     mut channel_iterator: FarLongIterator<ChannelRef>,
+    mut trys_counters: Vec<Trys<WORK_TRYS>>,
     target: f32,
     forecast_config: ForecastConfig,
     fast_forward_step_limit: u32,
     precision_adjust: Arc<Mutex<laser_precision_adjust::PrecisionAdjust2>>,
     switch_channel_delay_ms: u32,
     report_directory: PathBuf,
+    measure_filters: Vec<FilterStage>,
+    measure_kalman: Option<MeasureKalmanConfig>,
+    box_plot_config: BoxPlotConfig,
+    noise_config: NoiseConfig,
+    noise_profiles: NoiseProfiles,
+    predictor: Arc<Mutex<Predictor<f64>>>,
+    mut crosstalk_tracker: Option<CrosstalkTracker>,
+    verify_sweep: Option<VerifySweepConfig>,
+    edge_store: Option<EdgeStore>,
+    mut pause_rx: watch::Receiver<bool>,
 ) {
-    const WORK_TRYS: u32 = 3;
     const MEASURE_TRYS: usize = 2;
-    const AGE_DETECT_F_OFFSET: f32 = 0.25;
 
     let switch_channel_wait = Duration::from_millis(switch_channel_delay_ms as u64);
 
-    let upper_limit = target * (1.0 + precision_ppm / 1_000_000.0);
-    let lower_limit = target * (1.0 - precision_ppm / 1_000_000.0);
-
-    let stable_range = (upper_limit - lower_limit) / 6.0;
-
-    let absolute_low_limit = target - auto_adjust_limits.min_freq_offset;
     let mut prev_ch = None;
 
-    let mut trys_counters = vec![Trys::<WORK_TRYS>::default(); channel_iterator.len()];
-
     let mut rx = laser_setup_controller.lock().await.subscribe();
 
     let (burn_tx, mut burn_rx) = tokio::sync::mpsc::channel(1);
 
-    while let Some(ch_id) = channel_iterator.next() {
-        let burn_ch = match burn_rx.try_recv() {
-            Ok(BurnEvent::ErrorBan(ch)) => {
-                channel_iterator
-                    .get_mut(ch as usize)
-                    .unwrap()
-                    .ban(ChannelState::Limit);
-                Some(ch)
+    // поиск края продолжается с контрольной точки или начинается около края прошлых партий
+    let edge_history = edge_store
+        .as_ref()
+        .map(|store| {
+            store.load().unwrap_or_else(|e| {
+                tracing::error!("Failed to load edge history {:?}: {e}", store);
+                Default::default()
+            })
+        })
+        .unwrap_or_default();
+    let edge_detect_interval = auto_adjust_limits.edge_detect_interval;
+    let mut strategies = channel_iterator
+        .iter()
+        .map(|ch| {
+            let edge = match (ch.edge_step, edge_history.hint(ch.id)) {
+                (Some(edge), _) => EdgeSearch::found(edge),
+                (None, _) if ch.current_step > 0 => {
+                    EdgeSearch::new(ch.current_step, edge_detect_interval)
+                }
+                (None, Some(hint)) => EdgeSearch::near(hint, edge_detect_interval),
+                (None, None) => EdgeSearch::new(0, edge_detect_interval),
             }
-            Ok(BurnEvent::Done(ch, step)) => {
-                channel_iterator
-                    .get_mut(ch as usize)
-                    .unwrap()
-                    .set_step(step);
-                Some(ch)
+            .with_max_bisections(auto_adjust_limits.edge_max_bisections);
+            Box::new(
+                InterleavedStrategy::new(InterleavedConfig {
+                    edge_detect_interval,
+                    step_limit: fast_forward_step_limit,
+                    edge_offset: auto_adjust_limits.edge_detect_offset_hz,
+                    precision_reserve: auto_adjust_limits.precision_reserve_steps,
+                })
+                .with_edge_search(edge),
+            ) as Box<dyn AdjustStrategy>
+        })
+        .collect::<Vec<_>>();
+    if let Some(kc) = measure_kalman {
+        for i in 0..channel_iterator.len() {
+            channel_iterator.get_mut(i).unwrap().kalman = Some(ChannelKalman::new(kc));
+        }
+    }
+
+    let forecast = GrowForecast {
+        median: forecast_config.median_freq_grow,
+        maximal: forecast_config.max_freq_grow,
+    };
+
+    loop {
+        // пауза только между итерациями, начатый прожиг доводится до конца
+        if *pause_rx.borrow_and_update() {
+            tracing::info!("Adjust all paused");
+            tx.send(ProgressReport::new(
+                ProgressStatus::Paused,
+                None,
+                None,
+                gen_rez_info(channel_iterator.iter()),
+            ))
+            .ok();
+
+            loop {
+                tokio::select! {
+                    Some(event) = burn_rx.recv() => {
+                        let ch = apply_burn_event(&mut channel_iterator, event);
+                        save_checkpoint(&report_directory, target, &channel_iterator, &trys_counters);
+                        tx.send(ProgressReport::new(
+                            ProgressStatus::Paused,
+                            None,
+                            Some(ch),
+                            gen_rez_info(channel_iterator.iter()),
+                        ))
+                        .ok();
+                    }
+                    res = pause_rx.changed() => {
+                        if res.is_err() || !*pause_rx.borrow_and_update() {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            // за время паузы оператор мог переключить канал частотомера
+            prev_ch = None;
+            tracing::info!("Adjust all resumed");
+        }
+
+        // каналы, на которые еще влияет прожиг соседей, откладываются
+        if let Some(tracker) = &crosstalk_tracker {
+            let now = Local::now();
+            for i in 0..channel_iterator.len() {
+                channel_iterator.get_mut(i).unwrap().ready_at = tracker.ready_at(i, now);
             }
-            Err(_) => None,
+        }
+
+        let Some(ch_id) = channel_iterator.next() else {
+            break;
         };
 
+        let burn_ch = burn_rx
+            .try_recv()
+            .ok()
+            .map(|event| apply_burn_event(&mut channel_iterator, event));
+
+        save_checkpoint(&report_directory, target, &channel_iterator, &trys_counters);
+
         let mut wait_interval = Duration::ZERO;
         let channel_swithced = if Some(ch_id) != prev_ch {
             let mut guard = laser_setup_controller.lock().await;
@@ -580,18 +1167,45 @@ async fn adjust_task(
             false
         };
 
-        let rez_info = gen_rez_info(channel_iterator.iter(), AGE_DETECT_F_OFFSET);
+        let rez_info = gen_rez_info(channel_iterator.iter());
         let ch = channel_iterator.get_mut(ch_id).unwrap();
+        ch.events.clear();
+
+        // пределы настройки канала с учетом его индивидуальных настроек
+        let (ch_target, tolerance) = ch.target_tolerance(target, precision_ppm);
+        let upper_limit = ch_target + tolerance;
+        let lower_limit = ch_target - tolerance;
+        let stable_range = (upper_limit - lower_limit) / 6.0;
+        let absolute_low_limit = ch_target - auto_adjust_limits.min_freq_offset;
+        let goal = AdjustGoal {
+            target: ch_target,
+            tolerance,
+            absolute_low: absolute_low_limit,
+            max_steps: Some(ch.step_budget()),
+            power: ch.overrides.power_multiplier.unwrap_or(1.0),
+        };
+
+        // время остывания канала по его фрагментам, если включено адаптивное остывание
+        let min_touch_wait = {
+            let touch_wait = match predictor.lock().await.cooldown_time_ms(ch_id as u32).await {
+                Some(ms) => Duration::from_secs_f64(ms / 1000.0),
+                None => Duration::from_secs_f32(MIN_TOUCH_WAIT),
+            };
+            chrono::Duration::from_std(touch_wait).unwrap()
+        };
         {
             let now = Local::now();
             let after_last_touch = now - ch.last_touched();
-            let min_touch_wait =
-                chrono::Duration::from_std(Duration::from_secs_f32(MIN_TOUCH_WAIT)).unwrap();
             wait_interval = if after_last_touch < min_touch_wait {
                 wait_interval.max((min_touch_wait - after_last_touch).to_std().unwrap())
             } else {
                 wait_interval
             };
+
+            // ни один канал не готов - ждем затухания влияния соседей
+            if let Some(ready_at) = ch.ready_at.filter(|t| *t > now) {
+                wait_interval = wait_interval.max((ready_at - now).to_std().unwrap());
+            }
         }
 
         let step = ch.current_step();
@@ -604,11 +1218,24 @@ async fn adjust_task(
         ))
         .ok();
 
+        // окно измерения и порог стабильности по измеренному шуму канала
+        let (measure_count, ch_stable_range) = match noise_profiles.lock().await.get(ch_id) {
+            Some(Some(profile)) if noise_config.use_for_adjust => {
+                let count = (profile.suggested_measure_count() as u32)
+                    .clamp(MEASRE_COUNT_NORMAL, MEASRE_COUNT_MAX);
+                let range = profile
+                    .suggested_stable_range(noise_config.stable_margin, count as usize)
+                    as f32;
+                (count, range.min((upper_limit - lower_limit) / 2.0))
+            }
+            _ => (MEASRE_COUNT_NORMAL, stable_range),
+        };
+
         let tc = &mut trys_counters[ch_id];
         let measure_result = measure(
             &mut rx,
-            update_interval * (MEASRE_COUNT_NORMAL + 1),
-            stable_range,
+            update_interval * (measure_count + 1),
+            ch_stable_range,
             (
                 upper_limit,
                 // частота должна только рости и быть не ниже absolute_low_limit
@@ -619,36 +1246,68 @@ async fn adjust_task(
             ),
             wait_interval,
             MEASURE_TRYS,
+            &measure_filters,
+            ch.kalman.as_mut(),
+            &box_plot_config,
         )
         .await;
 
-        let current_freq = match measure_result {
+        let action = match measure_result {
             Ok(MeasureResult::Stable(f, b)) => {
                 if ch.sutable(f) {
                     tc.mark_ok();
+                    if let Some(tracker) = &mut crosstalk_tracker {
+                        tracker.measured(ch_id, Local::now(), f, min_touch_wait);
+                    }
                     // успешно
-                    if (f > lower_limit) && (f + forecast_config.median_freq_grow > target) {
+                    let history = ch.stable_history();
+                    let obs = Observation {
+                        freq: f,
+                        samples: &[],
+                        history: &history,
+                        step,
+                        goal,
+                        forecast,
+                    };
+                    let mut action = strategies[ch_id].decide(&obs);
+                    // перемещение без прожига не требует нового измерения
+                    while let Action::Skip(steps) = action {
+                        ch.move_to(ch.current_step().saturating_add_signed(steps));
+                        action = strategies[ch_id].decide(&Observation {
+                            step: ch.current_step(),
+                            ..obs
+                        });
+                    }
+                    if action == Action::Verify {
                         // stop
                         tracing::warn!("Ch {} verify: f={}", ch_id, f);
                         ch.update_state(ChannelState::Verify, f, step, true, b);
+                        ch.event(AdjustEvent::Verify { freq: f });
                         ch.touch();
                         continue;
                     } else {
                         // continue
                         ch.update_state(
-                            ChannelState::Adjustig("Норма".to_owned()),
+                            ChannelState::Adjustig(AdjustingStatus::Normal),
                             f,
                             step,
                             true,
                             b,
                         );
-                        f
+                        if let (None, Some(edge)) = (ch.edge_step, strategies[ch_id].edge()) {
+                            ch.edge_step = Some(edge);
+                            ch.event(AdjustEvent::EdgeFound {
+                                step: edge,
+                                freq: f,
+                            });
+                        }
+                        action
                     }
                 } else {
                     // возможно частота не та, например ниже ожидаемой, бракуем это измерение
                     tc.mark_incorrect();
                     ch.update_state(
-                        ChannelState::Adjustig("Некорректное измерение".to_owned()),
+                        ChannelState::Adjustig(AdjustingStatus::IncorrectMeasure),
                         b.median(),
                         step,
                         false,
@@ -671,11 +1330,10 @@ async fn adjust_task(
 
                 if tc.more_trys_avalable() {
                     ch.update_state(
-                        ChannelState::Adjustig(format!(
-                            "Нестабилен: Δ={:.2} ({})",
-                            bxplt.iqr(),
-                            tc.counter()
-                        )),
+                        ChannelState::Adjustig(AdjustingStatus::Unstable {
+                            iqr: bxplt.iqr(),
+                            trys: tc.counter(),
+                        }),
                         bxplt.median(),
                         step,
                         false,
@@ -693,7 +1351,7 @@ async fn adjust_task(
                 tracing::warn!("Ch {} out of range {}, remaning={:?}", ch_id, f, tc);
                 if tc.more_trys_avalable() {
                     ch.update_state(
-                        ChannelState::Adjustig("Вне диапазона".to_owned()),
+                        ChannelState::Adjustig(AdjustingStatus::OutOfRange),
                         f,
                         step,
                         false,
@@ -712,10 +1370,13 @@ async fn adjust_task(
                 } else {
                     ch.ban(ChannelState::Unsatable);
                 }
+                ch.event(AdjustEvent::Error {
+                    message: e.to_string(),
+                });
 
                 tx.send(ProgressReport::error(
                     e.to_string(),
-                    gen_rez_info(channel_iterator.iter(), AGE_DETECT_F_OFFSET),
+                    gen_rez_info(channel_iterator.iter()),
                 ))
                 .ok();
                 tracing::error!("Failed to measure channel {ch_id}: {e:?}");
@@ -724,40 +1385,51 @@ async fn adjust_task(
             }
         };
 
-        // Ударяем
+        // Ударяем, шаг мог измениться при поиске края
+        let step = ch.current_step();
         {
-            let soft_mode = current_freq > lower_limit;
-            let steps_to_burn = if soft_mode {
-                // soft touch
-                tracing::warn!(
-                    "f:{} > Lower_limit:{} - soft mode",
-                    current_freq,
-                    lower_limit
-                );
-                if ch.age_found(AGE_DETECT_F_OFFSET) {
-                    // всегда 1, самый точный режим
-                    1
-                } else {
-                    // Резонатор изначально очень близок, но край не найден
-                    auto_adjust_limits.edge_detect_interval / 2
+            // пробный выстрел - на месте, без перемещения
+            let (steps_to_burn, burn_step, soft_mode, power_multiplier) = match action {
+                Action::Burn { steps, soft, power } => (steps, 1, soft, power),
+                Action::Probe => (1, 0, false, goal.power),
+                // повторное измерение при следующем обращении к каналу,
+                // перемещения уже выполнены до прожига
+                Action::Wait(_) | Action::Verify | Action::Skip(_) => {
+                    ch.touch();
+                    continue;
+                }
+                Action::Ban(reason) => {
+                    tracing::warn!("Ch {} banned at step {}: {:?}", ch_id, step, reason);
+                    ch.event(AdjustEvent::Ban {
+                        reason,
+                        freq: ch.current_freq.unwrap_or(f32::NAN),
+                    });
+                    ch.ban(match reason {
+                        BanReason::StepLimit => ChannelState::BudgetExhausted,
+                        BanReason::TooLow | BanReason::TooHigh => ChannelState::OutOfRange,
+                    });
+                    continue;
                 }
-            } else if !ch.age_found(AGE_DETECT_F_OFFSET) {
-                // край еще не найден
-                auto_adjust_limits.edge_detect_interval
-            } else {
-                // расчет количества шагов
-                // кол_во = (целевое_изменеие / прогноз.макс_изменение).ceil()
-                fast_forward_step_limit
-                    .min(((target - current_freq) / forecast_config.max_freq_grow).ceil() as u32)
             };
 
+            ch.event(AdjustEvent::Burn {
+                steps: steps_to_burn,
+                power: power_multiplier,
+                soft: soft_mode,
+            });
             ch.touch();
+            if let Some(kalman) = &mut ch.kalman {
+                kalman.reset();
+            }
+            if let Some(tracker) = &mut crosstalk_tracker {
+                tracker.burned(ch_id, Local::now());
+            }
             precision_adjust
                 .lock()
                 .await
                 .push_event(PrivStatusEvent {
                     shot_mark: Some(true),
-                    step: Some(steps_to_burn as i32),
+                    step: Some(steps_to_burn as i32 * burn_step),
                     ..Default::default()
                 })
                 .await;
@@ -767,9 +1439,11 @@ async fn adjust_task(
                 tokio::spawn(burn_task(
                     laser_controller.clone(),
                     steps_to_burn,
+                    burn_step,
                     ch_id as u32,
                     Some(step),
                     soft_mode,
+                    power_multiplier,
                     burn_tx.clone(),
                 ));
             } else {
@@ -777,9 +1451,11 @@ async fn adjust_task(
                 burn_task(
                     laser_controller.clone(),
                     steps_to_burn,
+                    burn_step,
                     ch_id as u32,
                     Some(step),
                     soft_mode,
+                    power_multiplier,
                     burn_tx.clone(),
                 )
                 .await;
@@ -787,8 +1463,58 @@ async fn adjust_task(
         }
     }
 
+    if let Some(tracker) = &crosstalk_tracker {
+        tracing::info!(
+            "Crosstalk model: {:?} ({} observations)",
+            tracker.model(),
+            tracker.observations_count()
+        );
+        tracker.save();
+    }
+
+    // дождаться начатых прожигов, чтобы их шаги попали в отчет
+    drop(burn_tx);
+    while let Some(event) = burn_rx.recv().await {
+        apply_burn_event(&mut channel_iterator, event);
+    }
+
+    let mut verify_error = None;
+    if let Some(verify_sweep) = verify_sweep {
+        if let Err(e) = verify_sweeps(
+            &tx,
+            &laser_setup_controller,
+            &precision_adjust,
+            &mut rx,
+            &mut channel_iterator,
+            verify_sweep,
+            target,
+            precision_ppm,
+            update_interval,
+            switch_channel_wait,
+            &measure_filters,
+            &box_plot_config,
+            &mut pause_rx,
+        )
+        .await
+        {
+            tracing::error!("Verify sweep failed: {e:?}");
+            verify_error = Some(e);
+        }
+    }
+
+    // края этой партии - подсказка для следующих партий того же рецепта
+    if let Some(store) = &edge_store {
+        let edges = channel_iterator
+            .iter()
+            .filter_map(|ch| ch.edge_step.map(|edge| (ch.id, edge)))
+            .collect::<Vec<_>>();
+        if let Err(e) = store.record(&edges) {
+            tracing::error!("Failed to save edges {:?}: {e}", store);
+        }
+    }
+
     {
-        let rez_info = gen_rez_info(channel_iterator.iter(), AGE_DETECT_F_OFFSET);
+        let rez_info = gen_rez_info(channel_iterator.iter());
         let save_file_path = report_directory.join(format!(
             "auto_adjust_all_{}.json",
             Local::now().format("%Y-%m-%d_%H-%M-%S")
@@ -796,14 +1522,249 @@ async fn adjust_task(
 
         let mut file = std::fs::File::create(&save_file_path).unwrap();
         serde_json::to_writer_pretty(&mut file, &rez_info).unwrap();
+
+        // партия завершена, продолжать нечего
+        Checkpoint::remove(&report_directory);
+    }
+
+    // результаты сохранены, но проверка не завершена - не "Готово"
+    if let Some(e) = verify_error {
+        tx.send(ProgressReport::error(
+            e.to_string(),
+            gen_rez_info(channel_iterator.iter()),
+        ))
+        .ok();
+        return;
     }
 
     // Готово
-    tx.send(ProgressReport::done(gen_rez_info(
-        channel_iterator.iter(),
-        AGE_DETECT_F_OFFSET,
-    )))
+    tx.send(ProgressReport::done(gen_rez_info(channel_iterator.iter())))
+        .ok();
+}
+
+/// Контрольные проходы по настроенным каналам после выдержки и наблюдение за их дрейфом
+/// Проверяются все каналы с известной частотой, в т.ч. завершившие настройку с ошибкой.
+/// Настроенные каналы, ушедшие из допуска, помечаются [`ChannelState::Drifted`],
+/// не прошедшие ни одного контрольного измерения - [`ChannelState::Unverified`]
+async fn verify_sweeps(
+    tx: &watch::Sender<ProgressReport>,
+    laser_setup_controller: &Mutex<laser_precision_adjust::LaserSetupController>,
+    precision_adjust: &Mutex<laser_precision_adjust::PrecisionAdjust2>,
+    rx: &mut watch::Receiver<laser_precision_adjust::LaserSetupStatus>,
+    channel_iterator: &mut FarLongIterator<ChannelRef>,
+    config: VerifySweepConfig,
+    target: f32,
+    precision_ppm: f32,
+    update_interval: Duration,
+    switch_channel_wait: Duration,
+    measure_filters: &[FilterStage],
+    box_plot_config: &BoxPlotConfig,
+    pause_rx: &mut watch::Receiver<bool>,
+) -> Result<(), watch::error::RecvError> {
+    const MEASURE_TRYS: usize = 2;
+
+    let trimmed_at = std::time::Instant::now();
+
+    let mut channels = vec![];
+    for i in 0..channel_iterator.len() {
+        let ch = channel_iterator.get_mut(i).unwrap();
+        if ch.state == ChannelState::Skipped {
+            continue;
+        }
+        if let Some(f) = ch.current_freq {
+            ch.drift = Some(ChannelDrift::new(f));
+            channels.push(i);
+        }
+    }
+    if channels.is_empty() {
+        return Ok(());
+    }
+
+    tracing::info!("Verify sweep: settle {} ms", config.settle_time_ms);
+    tx.send(ProgressReport::new(
+        ProgressStatus::Settling,
+        None,
+        None,
+        gen_rez_info(channel_iterator.iter()),
+    ))
     .ok();
+    tokio::time::sleep(Duration::from_millis(config.settle_time_ms as u64)).await;
+
+    let mut sweep = 0;
+    let mut monitor_end = None;
+    loop {
+        sweep += 1;
+        for &ch_id in &channels {
+            // пауза между каналами
+            while *pause_rx.borrow_and_update() {
+                tx.send(ProgressReport::new(
+                    ProgressStatus::Paused,
+                    None,
+                    None,
+                    gen_rez_info(channel_iterator.iter()),
+                ))
+                .ok();
+                if pause_rx.changed().await.is_err() {
+                    break;
+                }
+            }
+
+            {
+                let mut guard = laser_setup_controller.lock().await;
+                if let Err(e) = guard.select_channel(ch_id as u32).await {
+                    tracing::error!("Failed to switch freqmeter channel: {e:?}");
+                    continue;
+                }
+                guard.delay(switch_channel_wait).await;
+            }
+            precision_adjust
+                .lock()
+                .await
+                .push_event(PrivStatusEvent {
+                    chanel_select: Some(ch_id as u32),
+                    ..Default::default()
+                })
+                .await;
+
+            tx.send(ProgressReport::new(
+                ProgressStatus::Verifying { sweep },
+                Some(ch_id as u32),
+                None,
+                gen_rez_info(channel_iterator.iter()),
+            ))
+            .ok();
+
+            let ch = channel_iterator.get_mut(ch_id).unwrap();
+            let (_, tolerance) = ch.target_tolerance(target, precision_ppm);
+            // частота может уйти куда угодно, диапазон не ограничивается
+            match measure(
+                rx,
+                update_interval * (MEASRE_COUNT_NORMAL + 1),
+                tolerance / 3.0,
+                (f32::INFINITY, f32::NEG_INFINITY),
+                Duration::ZERO,
+                MEASURE_TRYS,
+                measure_filters,
+                ch.kalman.as_mut(),
+                box_plot_config,
+            )
+            .await?
+            {
+                MeasureResult::Stable(f, b) | MeasureResult::OutOfRange(f, b) => {
+                    tracing::info!("Ch {} verify sweep {}: f={}", ch_id, sweep, f);
+                    ch.events.clear();
+                    ch.events.push(AdjustEvent::Measured {
+                        freq: f,
+                        interval: None,
+                        box_plot: Some(b),
+                    });
+                    if let Some(drift) = &mut ch.drift {
+                        drift.push(trimmed_at.elapsed().as_secs_f32(), f);
+                    }
+                }
+                MeasureResult::Unstable(b) => {
+                    tracing::warn!("Ch {} unstable on verify sweep {}: {:?}", ch_id, sweep, b);
+                }
+            }
+        }
+
+        if sweep < config.sweeps {
+            continue;
+        }
+
+        // наблюдение за дрейфом
+        let end = *monitor_end.get_or_insert_with(|| {
+            std::time::Instant::now()
+                + Duration::from_secs_f32(config.monitor_minutes.max(0.0) * 60.0)
+        });
+        let next =
+            std::time::Instant::now() + Duration::from_secs_f32(config.monitor_interval_s.max(0.0));
+        if next > end {
+            break;
+        }
+        tx.send(ProgressReport::new(
+            ProgressStatus::Verifying { sweep },
+            None,
+            None,
+            gen_rez_info(channel_iterator.iter()),
+        ))
+        .ok();
+        tokio::time::sleep_until(next.into()).await;
+    }
+
+    for ch_id in channels {
+        let ch = channel_iterator.get_mut(ch_id).unwrap();
+        let (ch_target, tolerance) = ch.target_tolerance(target, precision_ppm);
+        let in_tolerance = ch
+            .drift
+            .as_ref()
+            .and_then(|d| d.in_tolerance(ch_target - tolerance, ch_target + tolerance));
+        let trimmed = matches!(ch.state, ChannelState::Ok | ChannelState::Verify);
+        match in_tolerance {
+            None => {
+                tracing::warn!("Ch {} ({:?}) not verified", ch_id, ch.state);
+                if trimmed {
+                    ch.ban(ChannelState::Unverified);
+                }
+            }
+            Some(false) if trimmed => {
+                tracing::warn!("Ch {} drifted out of tolerance: {:?}", ch_id, ch.drift);
+                ch.ban(ChannelState::Drifted);
+            }
+            Some(ok) => {
+                tracing::info!(
+                    "Ch {} ({:?}) verified, in tolerance: {}, drift: {:?}",
+                    ch_id,
+                    ch.state,
+                    ok,
+                    ch.drift.as_ref().and_then(|d| d.drift())
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn save_checkpoint(
+    report_directory: &Path,
+    target: f32,
+    channel_iterator: &FarLongIterator<ChannelRef>,
+    trys_counters: &[Trys<WORK_TRYS>],
+) {
+    let checkpoint = Checkpoint {
+        saved: Local::now().to_rfc3339(),
+        target,
+        channels: channel_iterator
+            .iter()
+            .zip(trys_counters)
+            .map(|(ch, trys)| ch.checkpoint(*trys))
+            .collect(),
+    };
+
+    if let Err(e) = checkpoint.save(report_directory) {
+        tracing::error!("Failed to save adjust all checkpoint: {e}");
+    }
+}
+
+/// Применить результат прожига к каналу, возвращает номер канала
+fn apply_burn_event(channel_iterator: &mut FarLongIterator<ChannelRef>, event: BurnEvent) -> u32 {
+    match event {
+        BurnEvent::ErrorBan(ch) => {
+            channel_iterator
+                .get_mut(ch as usize)
+                .unwrap()
+                .ban(ChannelState::Limit);
+            ch
+        }
+        BurnEvent::Done(ch, step) => {
+            channel_iterator
+                .get_mut(ch as usize)
+                .unwrap()
+                .set_step(step);
+            ch
+        }
+    }
 }
 
 async fn measure(
@@ -813,6 +1774,9 @@ async fn measure(
     work_range: (f32, f32),
     wait_before: Duration,
     trys: usize,
+    filters: &[FilterStage],
+    mut kalman: Option<&mut ChannelKalman>,
+    box_plot_config: &BoxPlotConfig,
 ) -> Result<MeasureResult, watch::error::RecvError> {
     fn to_result(data: &[f32], boxplot: BoxPlot<f32>, work_range: &(f32, f32)) -> MeasureResult {
         let last_f = *data.last().unwrap();
@@ -822,6 +1786,10 @@ async fn measure(
             boxplot.median()
         };
 
+        check_range(f, boxplot, work_range)
+    }
+
+    fn check_range(f: f32, boxplot: BoxPlot<f32>, work_range: &(f32, f32)) -> MeasureResult {
         if f > work_range.0 || f < work_range.1 {
             MeasureResult::OutOfRange(f, boxplot)
         } else {
@@ -840,6 +1808,7 @@ async fn measure(
         tokio::time::sleep(wait_before).await;
 
         let mut data = vec![];
+        let mut timestamps = vec![];
 
         let start = std::time::Instant::now();
         while std::time::Instant::now() - start < timeout {
@@ -847,14 +1816,53 @@ async fn measure(
             let status = mq.borrow();
             tracing::trace!("\tF={}", status.current_frequency);
             data.push(status.current_frequency);
+            timestamps.push(std::time::Instant::now());
+        }
+
+        if !filters.is_empty() {
+            match apply_filters_uniform(filters, &data) {
+                Ok(filtered) => data = filtered,
+                Err(e) => tracing::warn!("Measure filter failed: {e:?}"),
+            }
+        }
+
+        boxplot = box_plot_config.box_plot(&data);
+
+        if let Some(kalman) = kalman.as_deref_mut() {
+            // оценка частоты и дрейфа по всем окнам с последнего прожига
+            for (f, ts) in data.iter().zip(timestamps.iter()) {
+                kalman.update(*ts, *f);
+            }
+
+            let kc = kalman.config;
+            let filter = &kalman.filter;
+            let freq_sd = filter.frequency_variance().sqrt();
+            let converged = filter.is_converged(kc.max_drift, kc.confidence);
+            let stable = filter.is_drift_below(kc.max_drift, kc.confidence)
+                && kc.confidence * freq_sd < stable_range as f64;
+            tracing::debug!(
+                "Measure: F={:.3}±{:.3} Hz, drift={:.4}±{:.4} Hz/s, converged={}, stable={}",
+                filter.frequency(),
+                freq_sd,
+                filter.drift(),
+                filter.drift_variance().sqrt(),
+                converged,
+                stable
+            );
+
+            if stable {
+                return Ok(check_range(filter.frequency() as f32, boxplot, &work_range));
+            } else if converged {
+                continue;
+            }
+            // дрейф еще не известен, решает BoxPlot
         }
 
-        boxplot = BoxPlot::new(&data);
         if boxplot.iqr() < stable_range {
             return Ok(to_result(&data, boxplot, &work_range));
         } else if data.len() > MEASRE_COUNT_NORMAL as usize {
             // пробуем без первой точки
-            boxplot = BoxPlot::new(&data[1..]);
+            boxplot = box_plot_config.box_plot(&data[1..]);
             if boxplot.iqr() < stable_range {
                 return Ok(to_result(&data, boxplot, &work_range));
             }
@@ -864,24 +1872,20 @@ async fn measure(
     return Ok(MeasureResult::Unstable(boxplot));
 }
 
-fn gen_rez_info<'a>(
-    iter: impl Iterator<Item = &'a ChannelRef>,
-    adge_found_offset: f32,
-) -> Vec<RezInfo> {
+fn gen_rez_info<'a>(iter: impl Iterator<Item = &'a ChannelRef>) -> Vec<RezInfo> {
     iter.map(|r| RezInfo {
         id: r.id,
         initial_freq: r.initial_freq.unwrap_or(f32::NAN),
         current_freq: r.current_freq.unwrap_or(f32::NAN),
         current_step: r.current_step,
-        state: {
-            let state = r.get_state();
-            if !state.is_adjusting() || r.age_found(adge_found_offset) {
-                state.to_status_icon()
-            } else {
-                "Поиск края".to_owned()
-            }
-        },
+        steps_left: r.steps_left(),
+        state: r.get_state(),
+        edge_found: r.edge_step.is_some(),
+        edge_step: r.edge_step,
         history: r.history.clone(),
+        overrides: Some(r.overrides).filter(|o| !o.is_default()),
+        events: r.events.clone(),
+        drift: r.drift.clone(),
     })
     .collect()
 }
@@ -889,9 +1893,11 @@ fn gen_rez_info<'a>(
 async fn burn_task(
     laser_controller: Arc<Mutex<laser_precision_adjust::LaserController>>,
     burn_count: u32,
+    burn_step: i32,
     channel: u32,
     initial_step: Option<u32>,
     soft_mode: bool,
+    power_multiplier: f32,
     burn_tx: tokio::sync::mpsc::Sender<BurnEvent>,
 ) {
     const BURN_TRYS: usize = 5;
@@ -919,10 +1925,94 @@ async fn burn_task(
         return;
     }
     match guard
-        .burn(burn_count, Some(1), Some(BURN_TRYS), soft_mode)
+        .burn_scaled(
+            burn_count,
+            Some(burn_step),
+            Some(BURN_TRYS),
+            soft_mode,
+            power_multiplier,
+        )
         .await
     {
         Ok(_) => post_result(Ok((channel, guard.get_current_step()))),
         Err(e) => post_result(Err((channel, e))),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use laser_precision_adjust::LaserSetupStatus;
+    use laser_setup_interface::{CameraState, ValveState};
+
+    fn status(f: f32) -> LaserSetupStatus {
+        LaserSetupStatus {
+            current_frequency: f,
+            camera_state: CameraState::Close,
+            valve_state: ValveState::Atmosphere,
+            channel: 0,
+            freq_offset: 0.0,
+        }
+    }
+
+    /// Частотомер: отсчет f(t) с шумом каждые 100 мс
+    fn freqmeter(f: impl Fn(f32) -> f32 + Send + 'static) -> watch::Receiver<LaserSetupStatus> {
+        const NOISE: [f32; 7] = [0.05, -0.08, 0.02, -0.03, 0.07, -0.06, 0.01];
+
+        let (tx, rx) = watch::channel(status(f(0.0)));
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_millis(100));
+            for i in 0.. {
+                interval.tick().await;
+                let f = f(i as f32 * 0.1) + NOISE[i % NOISE.len()];
+                if tx.send(status(f)).is_err() {
+                    break;
+                }
+            }
+        });
+        rx
+    }
+
+    #[tokio::test]
+    async fn measure_kalman_noisy() {
+        let config: MeasureKalmanConfig =
+            serde_json::from_str(r#"{"ProcessNoise": 0.0001, "MeasurementNoise": 0.01}"#).unwrap();
+        let window = Duration::from_millis(100) * (MEASRE_COUNT_NORMAL + 1);
+        let mut kalman = ChannelKalman::new(config);
+
+        // стабильная частота с шумом частотомера
+        let mut rx = freqmeter(|_| 32768.0);
+        let result = measure(
+            &mut rx,
+            window,
+            0.3,
+            (32770.0, 32760.0),
+            Duration::ZERO,
+            2,
+            &[],
+            Some(&mut kalman),
+            &BoxPlotConfig::default(),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(result, MeasureResult::Stable(f, _) if (f - 32768.0).abs() < 0.1));
+
+        // остывание после прожига: частота растет на 5 Гц/с
+        kalman.reset();
+        let mut rx = freqmeter(|t| 32768.0 + 5.0 * t);
+        let result = measure(
+            &mut rx,
+            window,
+            0.3,
+            (f32::INFINITY, f32::NEG_INFINITY),
+            Duration::ZERO,
+            2,
+            &[],
+            Some(&mut kalman),
+            &BoxPlotConfig::default(),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(result, MeasureResult::Unstable(_)));
+    }
+}
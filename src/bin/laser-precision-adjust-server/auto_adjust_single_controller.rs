@@ -7,8 +7,14 @@ use tokio::{
 };
 
 use laser_precision_adjust::{
+    adjust_event::AdjustEvent,
     box_plot::BoxPlot,
+    edge_store::EdgeStore,
     predict::{Fragment, Predictor},
+    strategy::{
+        Action, AdjustGoal, AdjustStrategy, BanReason, EdgeSearch, GrowForecast, Observation,
+        Stage, StagedConfig, StagedStrategy,
+    },
     AutoAdjustLimits, PrecisionAdjust2, AdjustConfig,
 };
 
@@ -45,26 +51,27 @@ impl std::fmt::Display for HardwareLogickError {
 
 impl std::error::Error for HardwareLogickError {}
 
-pub enum AutoAdjustSingleStateReport {
-    Progress(String),
-    Error(String),
-    Finished(String),
-}
-
 pub struct AutoAdjustSingleController {
     config: AutoAdjustLimits,
     update_interval_ms: u32,
     freqmeter_config: Arc<Mutex<AdjustConfig>>,
+    edge_store: Option<EdgeStore>,
     state: Arc<Mutex<State>>,
     task: Option<JoinHandle<Result<(), anyhow::Error>>>,
 }
 
 impl AutoAdjustSingleController {
-    pub fn new(config: AutoAdjustLimits, update_interval_ms: u32, freqmeter_config: Arc<Mutex<AdjustConfig>>) -> Self {
+    pub fn new(
+        config: AutoAdjustLimits,
+        update_interval_ms: u32,
+        freqmeter_config: Arc<Mutex<AdjustConfig>>,
+        edge_store: Option<EdgeStore>,
+    ) -> Self {
         Self {
             config,
             update_interval_ms,
             freqmeter_config,
+            edge_store,
             state: Arc::new(Mutex::new(State::Idle)),
             task: None,
         }
@@ -76,7 +83,7 @@ impl AutoAdjustSingleController {
         predictor: Arc<Mutex<Predictor<f64>>>,
         precision_adjust: Arc<Mutex<PrecisionAdjust2>>,
         traget_frequency: f32,
-    ) -> Result<mpsc::Receiver<AutoAdjustSingleStateReport>, &'static str> {
+    ) -> Result<mpsc::Receiver<AdjustEvent>, &'static str> {
         if *self.state.lock().await == State::Idle {
             let (tx, rx) = mpsc::channel(1);
 
@@ -91,6 +98,7 @@ impl AutoAdjustSingleController {
                 self.config,
                 traget_frequency as f64,
                 self.freqmeter_config.clone(),
+                self.edge_store.clone(),
             )));
 
             Ok(rx)
@@ -131,207 +139,94 @@ impl AutoAdjustSingleController {
 async fn adjust_task(
     channel: u32,
     update_interval_ms: u32,
-    status_report_q: mpsc::Sender<AutoAdjustSingleStateReport>,
+    status_report_q: mpsc::Sender<AdjustEvent>,
     state: Arc<Mutex<State>>,
     predictor: Arc<Mutex<Predictor<f64>>>,
     precision_adjust: Arc<Mutex<PrecisionAdjust2>>,
     config: AutoAdjustLimits,
     traget_frequency: f64,
     freqmeter_config: Arc<Mutex<AdjustConfig>>,
+    edge_store: Option<EdgeStore>,
 ) -> anyhow::Result<()> {
-    const PRECISION_ADJ_ZAPAS: u32 = 3;
+    let precision_ppm = freqmeter_config
+        .lock()
+        .await
+        .channel_precision_ppm(channel as usize);
+    let goal = AdjustGoal {
+        target: traget_frequency as f32,
+        tolerance: traget_frequency as f32 * precision_ppm / 1_000_000.0,
+        absolute_low: traget_frequency as f32 - config.min_freq_offset,
+        max_steps: Some(config.max_forward_steps),
+        power: 1.0,
+    };
+    let mut strategy = StagedStrategy::new(StagedConfig {
+        edge_detect_interval: config.edge_detect_interval,
+        step_limit: config.fast_forward_step_limit,
+        precision_reserve: config.precision_reserve_steps,
+        edge_detect_peak: config.edge_detect_peak_hz,
+        recheck_delay: Duration::from_millis((update_interval_ms * 5) as u64),
+    });
+
+    // поиск начинается около края, найденного в прошлых партиях
+    let edge_hint = edge_store.as_ref().and_then(|store| match store.load() {
+        Ok(history) => history.hint(channel as usize),
+        Err(e) => {
+            tracing::error!("Failed to load edge history {:?}: {e}", store);
+            None
+        }
+    });
+    if let Some(hint) = edge_hint {
+        strategy = strategy.with_edge_search(EdgeSearch::near(hint, config.edge_detect_interval));
+    }
 
-    // Стадия 1: поиск края
     *state.lock().await = State::DetctingEdge;
-    display_progress(&status_report_q, "Поиск края".to_owned()).await?;
 
-    let (initial_freq, edge_freq, last_freq_boxplot) = match find_edge(
+    let event = match drive(
+        &mut strategy,
+        goal,
         channel,
         update_interval_ms,
+        &status_report_q,
+        &state,
         &predictor,
         &precision_adjust,
-        config.edge_detect_interval,
-        status_report_q.clone(),
-        (traget_frequency - config.min_freq_offset as f64).into(),
-        traget_frequency.into(),
     )
     .await
     {
-        Ok((edge_pos, start_freq, box_plot)) => {
-            let end_freq = box_plot.median();
-            tracing::warn!("Edge found at step {}", edge_pos);
-            display_progress(
-                &status_report_q,
-                format!("Поиск края: {:.2} -> {:.2} Гц", start_freq, end_freq),
-            )
-            .await?;
-            *state.lock().await = State::DihotomyStepping;
-            (start_freq, end_freq, box_plot)
-        }
+        Ok(event) => event,
         Err(e) => {
-            tracing::error!("Edge not found: {}", e);
-            status_report_q
-                .send(AutoAdjustSingleStateReport::Error(e.to_string()))
-                .await?;
-            *state.lock().await = State::Idle;
-            return Ok(());
+            tracing::error!("Auto-adjust failed at {:?}: {}", strategy.stage(), e);
+            AdjustEvent::Error {
+                message: e.to_string(),
+            }
         }
     };
+    status_report_q.send(event).await?;
 
-    // Стадия 2: "Грубая" настройка
-    let (new_state, fast_forward_end_freq, fast_forward_steps_used) = match do_fast_forward_adjust(
-        traget_frequency,
-        freqmeter_config.lock().await.working_offset_ppm as f64,
-        last_freq_boxplot,
-        &status_report_q,
-        &precision_adjust,
-        update_interval_ms,
-        &predictor,
-        channel,
-        config.max_forward_steps - PRECISION_ADJ_ZAPAS,
-        config.fast_forward_step_limit,
-    )
-    .await
-    {
-        Ok((state, freq, steps_used)) => {
-            display_progress(
-                &status_report_q,
-                format!("Грубая настройка: -> {:.2} Гц ({} шага)", freq, steps_used),
-            )
-            .await?;
-            (state, freq, steps_used)
-        }
-        Err(e) => {
-            tracing::error!("Fast-forward failed: {}", e);
-            status_report_q
-                .send(AutoAdjustSingleStateReport::Error(e.to_string()))
-                .await?;
-            *state.lock().await = State::Idle;
-            return Ok(());
+    if let Some((store, edge)) = edge_store.zip(strategy.edge()) {
+        if let Err(e) = store.record(&[(channel as usize, edge)]) {
+            tracing::error!("Failed to save edge of channel {channel}: {e}");
         }
-    };
-
-    *state.lock().await = new_state;
-
-    let (_new_state, precision_end_freq, precision_steps_used) =
-        if new_state == State::PrecisionStepping {
-            match do_precision_adjust(
-                traget_frequency,
-                freqmeter_config.lock().await.working_offset_ppm as f64,
-                fast_forward_end_freq,
-                config.max_forward_steps - fast_forward_steps_used,
-                update_interval_ms,
-                &status_report_q,
-                &precision_adjust,
-                &predictor,
-                channel,
-            )
-            .await
-            {
-                Ok((state, freq, steps_used)) => {
-                    display_progress(
-                        &status_report_q,
-                        format!("Точная настройка: -> {:.2} Гц ({} шага)", freq, steps_used),
-                    )
-                    .await?;
-                    (state, Some(freq), steps_used)
-                }
-                Err(e) => {
-                    tracing::error!("Precision adjust failed: {}", e);
-                    status_report_q
-                        .send(AutoAdjustSingleStateReport::Error(e.to_string()))
-                        .await?;
-                    *state.lock().await = State::Idle;
-                    return Ok(());
-                }
-            }
-        } else {
-            display_progress(&status_report_q, "Точная настройка пропущена".to_owned()).await?;
-            (new_state, None, 0)
-        };
-
-    // report
-    {
-        let precision_end_freq = precision_end_freq.unwrap_or(fast_forward_end_freq);
-
-        let result = precision_end_freq;
-        let offset_ppm = (result - traget_frequency) / traget_frequency * 1_000_000.0;
-        let total_steps_used = fast_forward_steps_used + precision_steps_used;
-
-        status_report_q
-            .send(AutoAdjustSingleStateReport::Finished(format!(
-                "Настройка завершена: {initial_freq:.2} -> {edge_freq:.2} -> {fast_forward_end_freq:.2} -> {precision_end_freq:.2} Гц ({offset_ppm:+.1} ppm) за {total_steps_used} шагов",
-            )))
-            .await?;
     }
 
-    // success
     *state.lock().await = State::Idle;
     Ok(())
 }
 
-async fn display_progress(
-    status_report_q: &mpsc::Sender<AutoAdjustSingleStateReport>,
-    msg: String,
-) -> Result<(), mpsc::error::SendError<AutoAdjustSingleStateReport>> {
-    status_report_q
-        .send(AutoAdjustSingleStateReport::Progress(msg))
-        .await
-}
-
-async fn burn(
-    precision_adjust: &Mutex<PrecisionAdjust2>,
-    soft_mode: bool,
-) -> Result<(), HardwareLogickError> {
-    precision_adjust
-        .lock()
-        .await
-        .burn(soft_mode)
-        .await
-        .map_err(|e| HardwareLogickError(format!("Не удалось включить лазер ({e:?})")))
-}
-
-async fn step(
-    precision_adjust: &Mutex<PrecisionAdjust2>,
-    count: i32,
-) -> Result<(), laser_precision_adjust::Error> {
-    precision_adjust.lock().await.step(count).await
-}
-
-async fn sleep_ms(ms: u64) {
-    tokio::time::sleep(Duration::from_millis(ms)).await;
-}
-
-async fn get_last_fragment(
-    predictor: &Mutex<Predictor<f64>>,
-    channel: u32,
-) -> Option<Fragment<f64>> {
-    predictor.lock().await.get_last_fragment(channel).await
-}
-
-async fn capture(predictor: &Mutex<Predictor<f64>>) -> (Vec<f64>, Option<u128>) {
-    let capture = predictor.lock().await.capture().await;
-
-    let data = capture.iter().map(|d| d.1 as f64).collect();
-    let start_itmestamp = capture.first().map(|d| d.0);
-
-    (data, start_itmestamp)
-}
-
-async fn find_edge(
+/// Выполнение решений стратегии на оборудовании до окончания настройки канала
+/// Возвращает последнее событие настройки
+async fn drive(
+    strategy: &mut dyn AdjustStrategy,
+    goal: AdjustGoal,
     channel: u32,
     update_interval_ms: u32,
+    status_report_q: &mpsc::Sender<AdjustEvent>,
+    state: &Mutex<State>,
     predictor: &Mutex<Predictor<f64>>,
     precision_adjust: &Mutex<PrecisionAdjust2>,
-    edge_detect_interval: u32,
-    status_report_q: mpsc::Sender<AutoAdjustSingleStateReport>,
-    min_frequency: f64,
-    max_frequency: f64,
-) -> anyhow::Result<(u32, f64, BoxPlot<f64>)> {
+) -> anyhow::Result<AdjustEvent> {
     use std::cmp::min;
 
-    let mut start_freq = None;
-
     // switch channel
     precision_adjust
         .lock()
@@ -340,323 +235,259 @@ async fn find_edge(
         .await
         .map_err(|e| HardwareLogickError(format!("Не удалось переключить канал ({e:?})")))?;
 
-    display_progress(&status_report_q, format!("Канал {channel}")).await?;
-
     // switch delay
     sleep_ms(min((update_interval_ms * 5) as u64, 500)).await;
 
-    let mut current_step = 0;
-
-    loop {
-        // Прожиг
-        burn(precision_adjust, false).await?;
-        display_progress(
-            &status_report_q,
-            format!("Ожидаине реакции на шаге {current_step}"),
-        )
+    let mut freq = reread_freq(predictor).await as f32;
+    status_report_q
+        .send(AdjustEvent::Started { channel, freq })
         .await?;
-        sleep_ms((update_interval_ms * 10) as u64).await;
 
-        // поиск во фрагменте повышения частоты не менее чем на 0.35 Гц
-        const EDGE_DETECT_PEAK: f64 = 0.35;
-        {
-            let (last_fragment, _) = capture(predictor).await;
-            let box_plot = BoxPlot::new(&last_fragment);
+    let mut samples: Vec<f32> = vec![];
+    let mut history: Vec<f32> = vec![];
+    // частота в начале каждой стадии
+    let mut stage_freqs = vec![freq];
+    let mut stage = strategy.stage();
+    let mut steps_used = 0;
+    let mut edge_step = 0;
+    let mut edge_found = strategy.edge().is_some();
 
-            if start_freq.is_none() {
-                start_freq.replace(box_plot.median());
-            }
-
-            if box_plot.q1() < min_frequency && current_step == 0 {
-                Err(HardwareLogickError(format!(
-                    "Частота ниже минимально-допустимой ({} < {:.2})",
-                    min_frequency,
-                    box_plot.q1()
-                )))?
-            } else if box_plot.q3() > max_frequency {
-                Err(HardwareLogickError(format!(
-                    "Частота выше максимально-допустимой ({} > {:.2})",
-                    max_frequency,
-                    box_plot.q3()
-                )))?
-            } else if (box_plot.q3() - box_plot.q1() > EDGE_DETECT_PEAK)
-                && ((last_fragment.first().map_or(box_plot.q1(), |v| *v)
-                    - last_fragment.last().map_or(box_plot.q3(), |v| *v))
-                .abs()
-                    > EDGE_DETECT_PEAK)
-            {
-                // нашли
-                display_progress(&status_report_q, format!("Реакция обнаружена!")).await?;
-                return Ok((current_step, start_freq.unwrap(), box_plot));
-            }
-        }
+    loop {
+        let forecast = predictor
+            .lock()
+            .await
+            .get_prediction(channel, 0.0)
+            .await
+            .ok_or(HardwareLogickError("Отсутвует прогноз!".to_owned()))?;
 
-        // не найдено, шагаем на edge_detect_interval
-        match step(precision_adjust, edge_detect_interval as i32).await {
-            Ok(_) => {
-                current_step += edge_detect_interval;
-            } // ok
-            Err(laser_precision_adjust::Error::Logick(_)) => break, // конец хода
-            Err(e) => Err(HardwareLogickError(format!(
-                "Не удалось сделать шаг ({e:?})"
-            )))?,
+        let action = strategy.decide(&Observation {
+            freq,
+            samples: &samples,
+            history: &history,
+            step: steps_used,
+            goal,
+            forecast: GrowForecast {
+                median: forecast.median as f32,
+                maximal: forecast.maximal as f32,
+            },
+        });
+        tracing::trace!("{:?}: {:?}", strategy.stage(), action);
+
+        if let (false, Some(edge)) = (edge_found, strategy.edge()) {
+            edge_found = true;
+            status_report_q
+                .send(AdjustEvent::EdgeFound { step: edge, freq })
+                .await?;
         }
-    }
-
-    Err(HardwareLogickError(format!(
-        "Край не найден, достигнут лимит перемещения ({current_step})"
-    )))?
-}
 
-/// Итеративно делаем N шагов вперед, где N = (traget_frequency - forecast) / max_prediction
-/// Если N < 1, то переходим к точной настройке
-async fn do_fast_forward_adjust(
-    traget_frequency: f64,
-    precision_ppm: f64,
-    last_freq_boxplot: BoxPlot<f64>,
-    status_report_q: &mpsc::Sender<AutoAdjustSingleStateReport>,
-    precision_adjust: &Mutex<PrecisionAdjust2>,
-    update_interval_ms: u32,
-    predictor: &Mutex<Predictor<f64>>,
-    channel: u32,
-    max_forward_steps: u32,
-    step_limit: u32,
-) -> Result<(State, f64, u32), anyhow::Error> {
-    let f_lower_baund = traget_frequency * (1.0 - precision_ppm / 1_000_000.0);
-
-    let mut total_step_counter: u32 = 0;
-    let mut step_limit_over = false;
-
-    let mut forecast = last_freq_boxplot.upper_bound();
-
-    Ok(loop {
-        // определяем сколько шагов нужно сделать, но не менее 1
-        let mut steps_forecast = ((traget_frequency as f64 - forecast)
-            / predictor
-                .lock()
-                .await
-                .get_prediction(channel, 0.0)
-                .await
-                .unwrap()
-                .maximal)
-            .floor() as i32;
-        tracing::trace!("New fast-forward forecast: {} steps", steps_forecast);
-        if steps_forecast < 1 {
-            // ожидание + переизмеренть частоту
-            sleep_ms((update_interval_ms * 5) as u64).await;
-            let current_freq = reread_freq(predictor).await;
-
-            // ложное срабатывание?
-            let fr = predictor
-                .lock()
-                .await
-                .get_prediction(channel, current_freq)
-                .await
-                .unwrap()
-                .median;
-            if fr < f_lower_baund {
-                forecast = current_freq;
-                tracing::warn!(
-                    "Fast-forward: detected false-stop: predict-median={:.2}, lb={:.2}",
-                    fr,
-                    f_lower_baund
-                );
-                continue;
+        if let (Some(prev), Some(next)) = (stage, strategy.stage()) {
+            if prev != next {
+                if prev != Stage::EdgeSearch {
+                    status_report_q
+                        .send(AdjustEvent::StageDone {
+                            stage: prev,
+                            freq,
+                            steps: steps_used,
+                        })
+                        .await?;
+                }
+                stage = Some(next);
+                stage_freqs.push(freq);
+                *state.lock().await = match next {
+                    Stage::EdgeSearch => State::DetctingEdge,
+                    Stage::FastForward => State::DihotomyStepping,
+                    Stage::Precision => State::PrecisionStepping,
+                    Stage::Done => State::End,
+                };
             }
-
-            // переход к точной настройке
-            return Ok((State::PrecisionStepping, current_freq, total_step_counter));
-        } else if steps_forecast > step_limit as i32 {
-            tracing::warn!("Fast-forawrd: trimmed steps to {}", step_limit);
-            steps_forecast = step_limit as i32;
         }
 
-        // прожиг steps_forecast шагов
-        status_report_q
-            .send(AutoAdjustSingleStateReport::Progress(format!(
-                "Прожиг {} шагов",
-                steps_forecast
-            )))
-            .await?;
-        let mut last_timestamp: Option<u128> = None;
-
-        total_step_counter += steps_forecast as u32;
-        if total_step_counter > max_forward_steps {
-            steps_forecast -= (total_step_counter - max_forward_steps) as i32;
-            total_step_counter = max_forward_steps;
-            step_limit_over = true;
-        }
-
-        tracing::trace!("Burn {} steps...", steps_forecast);
+        match action {
+            Action::Probe => {
+                // Прожиг
+                burn(precision_adjust, false).await?;
+                status_report_q
+                    .send(AdjustEvent::Probe { step: edge_step })
+                    .await?;
+                sleep_ms((update_interval_ms * 10) as u64).await;
 
-        for _ in 0..steps_forecast {
-            burn(&precision_adjust, false).await?;
-            sleep_ms((update_interval_ms * 4) as u64).await;
-            match step(&precision_adjust, 1).await {
-                Ok(_) => {
-                    let (_, ts) = capture(&predictor).await;
-                    if let Some(ts) = ts {
-                        last_timestamp.replace(ts);
-                    }
-                }
-                Err(laser_precision_adjust::Error::Logick(_)) => {
+                let (last_fragment, _) = capture(predictor).await;
+                if last_fragment.is_empty() {
                     Err(HardwareLogickError(
-                        "Достигнут лимит перемещения, невозможно продолжить".to_owned(),
+                        "Не удалось получить данные с частотмера, аварийный останов".to_owned(),
                     ))?;
                 }
+                samples = last_fragment.iter().map(|f| *f as f32).collect();
+                let box_plot = BoxPlot::new(&samples);
+                // сразу после выстрела резонатор нагрет и частота занижена - реакция по верхней границе
+                freq = box_plot.upper_bound();
+                status_report_q
+                    .send(AdjustEvent::Measured {
+                        freq,
+                        interval: None,
+                        box_plot: Some(box_plot),
+                    })
+                    .await?;
+            }
+            // к следующему пробному выстрелу или к найденному краю
+            Action::Skip(steps) => match step(precision_adjust, steps).await {
+                Ok(_) => edge_step = edge_step.saturating_add_signed(steps),
+                Err(laser_precision_adjust::Error::Logick(_)) => Err(HardwareLogickError(
+                    format!("Край не найден, достигнут лимит перемещения ({edge_step})"),
+                ))?,
                 Err(e) => Err(HardwareLogickError(format!(
                     "Не удалось сделать шаг ({e:?})"
                 )))?,
-            }
-        }
+            },
+            Action::Burn { steps, soft, power } => {
+                status_report_q
+                    .send(AdjustEvent::Burn { steps, power, soft })
+                    .await?;
 
-        tracing::trace!("...Success");
+                let mut last_timestamp: Option<u128> = None;
+                for _ in 0..steps {
+                    burn(precision_adjust, soft).await?;
+                    sleep_ms((update_interval_ms * 4) as u64).await;
+                    match step(precision_adjust, 1).await {
+                        Ok(_) => {
+                            if let (_, Some(ts)) = capture(predictor).await {
+                                last_timestamp.replace(ts);
+                            }
+                        }
+                        Err(laser_precision_adjust::Error::Logick(_)) => {
+                            Err(HardwareLogickError(
+                                "Достигнут лимит перемещения, невозможно продолжить".to_owned(),
+                            ))?;
+                        }
+                        Err(e) => Err(HardwareLogickError(format!(
+                            "Не удалось сделать шаг ({e:?})"
+                        )))?,
+                    }
+                }
+                steps_used += steps;
 
-        // защита от "залипания"
-        if last_timestamp.is_none() {
-            Err(HardwareLogickError(
-                "Не удалось получить данные с частотмера, аварийный останов".to_owned(),
-            ))?;
-        }
+                // защита от "залипания"
+                let Some(ts) = last_timestamp else {
+                    Err(HardwareLogickError(
+                        "Не удалось получить данные с частотмера, аварийный останов".to_owned(),
+                    ))?
+                };
+
+                // ожидаем полного охлаждения
+                status_report_q.send(AdjustEvent::Cooling).await?;
+                let (target, interval) =
+                    wait_cooldown(predictor, channel, ts, update_interval_ms).await;
+
+                history.push(freq);
+                freq = target as f32;
+                samples.clear();
+                status_report_q
+                    .send(AdjustEvent::Measured {
+                        freq,
+                        interval: interval.map(|(lo, hi)| (lo as f32, hi as f32)),
+                        box_plot: None,
+                    })
+                    .await?;
+            }
+            Action::Wait(delay) => {
+                // ожидание + переизмеренть частоту
+                time::sleep(delay).await;
+                freq = reread_freq(predictor).await as f32;
+                samples.clear();
+            }
+            Action::Verify | Action::Ban(BanReason::StepLimit) => {
+                status_report_q
+                    .send(match action {
+                        Action::Ban(reason) => AdjustEvent::Ban { reason, freq },
+                        _ => AdjustEvent::Verify { freq },
+                    })
+                    .await?;
 
-        // ожидаем полного охлаждения
-        display_progress(&status_report_q, "Ожидание охлаждения".to_owned()).await?;
-        tracing::trace!("Cooling...");
-        let last_fragment = loop {
-            sleep_ms(update_interval_ms as u64).await;
-            let last_fragment = get_last_fragment(&predictor, channel).await;
-            if let Some(fragment) = &last_fragment {
-                if fragment.start_timestamp() >= last_timestamp.unwrap() as f64 {
-                    break last_fragment.unwrap();
-                }
+                // ожидание + переизмеренть частоту
+                sleep_ms((update_interval_ms * 5) as u64).await;
+                freq = reread_freq(predictor).await as f32;
+                stage_freqs.push(freq);
+                break;
             }
-        };
-
-        // обновляем прогноз
-        forecast = last_fragment.target();
-        if step_limit_over {
-            // Достигнут лимит шагов, принудительно выходим
-            tracing::info!("Fast-forward: Sep limit");
-            break (State::PrecisionStepping, forecast, total_step_counter);
-        } else if forecast > traget_frequency {
-            tracing::info!("Fast-forward: forecast ok");
-            // прогноз показывает, что частота уже выше целевой, переходим к PrecisionStepping
-            break (
-                State::PrecisionStepping,
-                reread_freq(predictor).await,
-                total_step_counter,
-            );
-        } else if forecast > f_lower_baund {
-            tracing::info!("Fast-forward: forecast upper min");
-            // достигнута нижняя граница, останов грубой настройки, переходим к PrecisionStepping
-            break (
-                State::PrecisionStepping,
-                reread_freq(predictor).await,
-                total_step_counter,
-            );
-        } else {
-            // продолжаем грубую настройку
-            tracing::trace!("Need more fast-forward steps");
+            Action::Ban(reason) => return Ok(AdjustEvent::Ban { reason, freq }),
         }
+    }
+
+    Ok(AdjustEvent::Finished {
+        path: stage_freqs,
+        freq,
+        ppm: (freq - goal.target) / goal.target * 1_000_000.0,
+        steps: steps_used,
     })
 }
 
-/// Итеративно делаем шаги вперед, пока прогноз не станет выше целевой частоты
-async fn do_precision_adjust(
-    traget_frequency: f64,
-    precision_ppm: f64,
-    mut current_freq: f64,
-    max_steps: u32,
-    update_interval_ms: u32,
-    status_report_q: &mpsc::Sender<AutoAdjustSingleStateReport>,
+async fn burn(
+    precision_adjust: &Mutex<PrecisionAdjust2>,
+    soft_mode: bool,
+) -> Result<(), HardwareLogickError> {
+    precision_adjust
+        .lock()
+        .await
+        .burn(soft_mode)
+        .await
+        .map_err(|e| HardwareLogickError(format!("Не удалось включить лазер ({e:?})")))
+}
+
+async fn step(
     precision_adjust: &Mutex<PrecisionAdjust2>,
+    count: i32,
+) -> Result<(), laser_precision_adjust::Error> {
+    precision_adjust.lock().await.step(count).await
+}
+
+async fn sleep_ms(ms: u64) {
+    tokio::time::sleep(Duration::from_millis(ms)).await;
+}
+
+async fn get_last_fragment(
     predictor: &Mutex<Predictor<f64>>,
     channel: u32,
-) -> Result<(State, f64, u32), anyhow::Error> {
-    let f_lower_baund = traget_frequency * (1.0 - precision_ppm / 1_000_000.0);
-    let f_lower_stop_baund = traget_frequency * (1.0 - (precision_ppm * 2.0 / 3.0) / 1_000_000.0);
-    let f_upper_baund = traget_frequency * (1.0 + precision_ppm / 1_000_000.0);
-    let mut total_step_counter: u32 = 0;
-
-    let target_state = loop {
-        if current_freq > f_lower_stop_baund {
-            break State::End;
-        }
-
-        let forecast = predictor
-            .lock()
-            .await
-            .get_prediction(channel, current_freq)
-            .await
-            .ok_or(HardwareLogickError("Отсутвует прогноз!".to_owned()))?;
+) -> Option<Fragment<f64>> {
+    predictor.lock().await.get_last_fragment(channel).await
+}
 
-        if forecast.maximal >= f_upper_baund {
-            // Иногда прогноз дает очень большое преввышение, проверим на всякий случай
-            current_freq = reread_freq(predictor).await;
-            if current_freq > f_lower_baund {
-                break State::End;
+/// Ожидание охлаждения после выстрела, фрагмент которого начинается не раньше timestamp
+/// Возвращает прогноз частоты и его интервал, как только оценка достаточно уверенная,
+/// либо по окончании фрагмента
+async fn wait_cooldown(
+    predictor: &Mutex<Predictor<f64>>,
+    channel: u32,
+    timestamp: u128,
+    update_interval_ms: u32,
+) -> (f64, Option<(f64, f64)>) {
+    loop {
+        sleep_ms(update_interval_ms as u64).await;
+        if let Some(fragment) = get_last_fragment(predictor, channel).await {
+            if fragment.start_timestamp() >= timestamp as f64 {
+                return (fragment.target(), fragment.target_interval());
             }
         }
 
-        if total_step_counter >= max_steps {
-            // достигнут лимит шагов, принудительно выходим
-            display_progress(&status_report_q, "Достигнут лимит шагов".to_owned()).await?;
-            break State::End;
-        }
-
-        // прожиг 1 шага
-        burn(&precision_adjust, true).await?;
-        total_step_counter += 1;
-        sleep_ms((update_interval_ms * 4) as u64).await;
-        match step(&precision_adjust, 1).await {
-            Ok(_) => {
-                if let (_, Some(ts)) = capture(&predictor).await {
-                    // ожидаем полного охлаждения
-                    display_progress(&status_report_q, "Ожидание охлаждения".to_owned()).await?;
-                    let last_fragment = loop {
-                        sleep_ms(update_interval_ms as u64).await;
-                        let last_fragment = get_last_fragment(&predictor, channel).await;
-                        if let Some(fragment) = &last_fragment {
-                            if fragment.start_timestamp() >= ts as f64 {
-                                break last_fragment.unwrap();
-                            }
-                        }
-                    };
-
-                    // обновляем текущую частоту
-                    current_freq = last_fragment.target();
-                    display_progress(
-                        &status_report_q,
-                        format!("Текущая частота: ~{:.2} Гц", current_freq),
-                    )
-                    .await?;
-                    if current_freq > f_lower_stop_baund {
-                        // на всякий случай
-                        sleep_ms((update_interval_ms * 5) as u64).await;
-                        current_freq = reread_freq(predictor).await;
-                    }
-                } else {
-                    Err(HardwareLogickError(
-                        "Не удалось получить данные с частотмера, аварийный останов".to_owned(),
-                    ))?;
-                }
-            }
-            Err(laser_precision_adjust::Error::Logick(_)) => {
-                Err(HardwareLogickError(
-                    "Достигнут лимит перемещения, невозможно продолжить".to_owned(),
-                ))?;
+        let online = predictor.lock().await.get_online_estimate(channel).await;
+        if let Some((start, estimate)) = online {
+            if start >= timestamp as f64 && estimate.confident {
+                tracing::trace!(
+                    "Cooldown: confident estimate after {} samples",
+                    estimate.samples
+                );
+                return (estimate.target, Some(estimate.interval));
             }
-            Err(e) => Err(HardwareLogickError(format!(
-                "Не удалось сделать шаг ({e:?})"
-            )))?,
         }
-    };
+    }
+}
 
-    // ожидание + переизмеренть частоту
-    sleep_ms((update_interval_ms * 5) as u64).await;
-    current_freq = reread_freq(predictor).await;
+async fn capture(predictor: &Mutex<Predictor<f64>>) -> (Vec<f64>, Option<u128>) {
+    let capture = predictor.lock().await.capture().await;
+
+    let data = capture.iter().map(|d| d.1 as f64).collect();
+    let start_itmestamp = capture.first().map(|d| d.0);
 
-    Ok((target_state, current_freq, total_step_counter))
+    (data, start_itmestamp)
 }
 
 async fn reread_freq(predictor: &Mutex<Predictor<f64>>) -> f64 {
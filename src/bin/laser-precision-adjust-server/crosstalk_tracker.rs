@@ -0,0 +1,156 @@
+use chrono::{DateTime, Local};
+use laser_precision_adjust::{
+    crosstalk::{
+        placement_distance, CrosstalkConfig, CrosstalkModel, CrosstalkObservation, CrosstalkStore,
+    },
+    AxisConfig, ResonatroPlacement,
+};
+
+/// Учет теплового влияния прожигов на соседние каналы во время автонастройки
+/// Модель уточняется по наблюдениям: изменению стабильной частоты канала, который сам не
+/// прожигался, после прожига другого канала. Уточненная модель сохраняется для следующих партий
+pub struct CrosstalkTracker {
+    config: CrosstalkConfig,
+    model: CrosstalkModel,
+    /// Модель уточнена по наблюдениям этой партии
+    fitted: bool,
+    store: Option<CrosstalkStore>,
+    distances: Vec<Vec<f64>>,
+    last_burn: Vec<Option<DateTime<Local>>>,
+    last_stable: Vec<Option<(DateTime<Local>, f32)>>,
+    observations: Vec<CrosstalkObservation>,
+}
+
+fn seconds(d: chrono::Duration) -> f64 {
+    d.num_milliseconds() as f64 / 1000.0
+}
+
+impl CrosstalkTracker {
+    pub fn new(
+        config: CrosstalkConfig,
+        placement: &[ResonatroPlacement],
+        axis_config: &AxisConfig,
+        store: Option<CrosstalkStore>,
+    ) -> Self {
+        let distances = placement
+            .iter()
+            .map(|a| {
+                placement
+                    .iter()
+                    .map(|b| placement_distance(a, b, axis_config))
+                    .collect()
+            })
+            .collect();
+
+        // модель прошлых партий точнее начальной оценки из настроек
+        let saved = store.as_ref().and_then(|store| match store.load() {
+            Ok(model) => model,
+            Err(e) => {
+                tracing::error!("Failed to load crosstalk model {:?}: {e}", store);
+                None
+            }
+        });
+
+        Self {
+            model: saved.unwrap_or(CrosstalkModel::from(&config)),
+            fitted: false,
+            store,
+            config,
+            distances,
+            last_burn: vec![None; placement.len()],
+            last_stable: vec![None; placement.len()],
+            observations: vec![],
+        }
+    }
+
+    pub fn model(&self) -> CrosstalkModel {
+        self.model
+    }
+
+    pub fn observations_count(&self) -> usize {
+        self.observations.len()
+    }
+
+    pub fn burned(&mut self, channel: usize, at: DateTime<Local>) {
+        if let Some(b) = self.last_burn.get_mut(channel) {
+            b.replace(at);
+        }
+    }
+
+    /// Ожидаемое смещение частоты канала от прожигов других каналов в момент now, Гц
+    pub fn residual(&self, channel: usize, now: DateTime<Local>) -> f64 {
+        self.last_burn
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != channel)
+            .filter_map(|(i, burn)| {
+                burn.map(|t| {
+                    self.model
+                        .shift(self.distances[channel][i], seconds(now - t))
+                })
+            })
+            .sum()
+    }
+
+    /// Момент, после которого влияние соседей на канал не превышает допустимого
+    pub fn ready_at(&self, channel: usize, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        let wait = self
+            .model
+            .settle_time(self.residual(channel, now), self.config.tolerance);
+        if wait > 0.0 {
+            Some(now + chrono::Duration::milliseconds((wait * 1000.0) as i64))
+        } else {
+            None
+        }
+    }
+
+    /// Стабильное измерение частоты канала, cooldown - время остывания канала после своего прожига
+    pub fn measured(
+        &mut self,
+        channel: usize,
+        at: DateTime<Local>,
+        f: f32,
+        cooldown: chrono::Duration,
+    ) {
+        if let Some((prev_at, prev_f)) = self.last_stable[channel] {
+            // канал прожигался сам или еще остывал после своего прожига - изменение не от соседа
+            let self_burned = self.last_burn[channel].is_some_and(|t| t + cooldown > prev_at);
+
+            // последний прожиг другого канала между измерениями
+            let neighbour = self
+                .last_burn
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != channel)
+                .filter_map(|(i, b)| b.filter(|t| *t > prev_at && *t < at).map(|t| (i, t)))
+                .max_by_key(|(_, t)| *t);
+
+            if let (false, Some((i, t))) = (self_burned, neighbour) {
+                self.observations.push(CrosstalkObservation {
+                    distance: self.distances[channel][i],
+                    dt: seconds(at - t),
+                    shift: (f - prev_f) as f64,
+                });
+
+                if self.observations.len() >= self.config.min_observations {
+                    if let Some(model) = CrosstalkModel::fit(&self.observations, &self.model) {
+                        tracing::debug!("Crosstalk model updated: {:?}", model);
+                        self.model = model;
+                        self.fitted = true;
+                    }
+                }
+            }
+        }
+
+        self.last_stable[channel] = Some((at, f));
+    }
+
+    /// Сохранить модель, уточненную в этой партии
+    pub fn save(&self) {
+        if let (true, Some(store)) = (self.fitted, &self.store) {
+            if let Err(e) = store.save(&self.model) {
+                tracing::error!("Failed to save crosstalk model {:?}: {e}", store);
+            }
+        }
+    }
+}
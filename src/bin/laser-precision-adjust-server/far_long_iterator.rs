@@ -1,15 +1,26 @@
 use chrono::{DateTime, Duration, Local};
+use laser_precision_adjust::SchedulingConfig;
 
 /// Трейт элемента коллекции
 pub trait FarLongIteratorItem: Clone {
     /// расстояние в пространстве до другого такого-же элемента
-    fn distance(&self, other: &Self) -> u64;
+    fn distance(&self, other: &Self) -> f64;
+
+    /// Время перехода от другого элемента к этому, с
+    fn travel_time(&self, _other: &Self) -> f64 {
+        0.0
+    }
 
     /// Возвращает штамп времени последнего выбора канала
     fn last_touched(&self) -> DateTime<Local>;
 
     /// Элемент валиден?
     fn is_valid(&self) -> bool;
+
+    /// Момент, раньше которого элемент выбирать нежелательно, None - готов сразу
+    fn ready_at(&self) -> Option<DateTime<Local>> {
+        None
+    }
 }
 
 /// бесконечный итератор на слайсе, который возвращает элемент максимально далекий от превыдущего как во времени так и в пространстве
@@ -17,6 +28,7 @@ pub struct FarLongIterator<T: FarLongIteratorItem> {
     elemnts: Vec<T>,
     current_selected: Option<usize>,
     time_tolerance: Duration,
+    policy: Option<SchedulingConfig>,
 }
 
 #[allow(dead_code)]
@@ -25,6 +37,12 @@ impl<T: FarLongIteratorItem> FarLongIterator<T> {
         self.elemnts.get_mut(index)
     }
 
+    /// Выбор по взвешенной оценке вместо поиска самого дальнего из давно не выбиравшихся
+    pub fn with_policy(mut self, policy: Option<SchedulingConfig>) -> Self {
+        self.policy = policy;
+        self
+    }
+
     pub fn reset(&mut self) {
         self.current_selected = None;
     }
@@ -48,6 +66,7 @@ impl<T: FarLongIteratorItem> IntoFarLongIterator<T> for Vec<T> {
             elemnts: self,
             current_selected: None,
             time_tolerance,
+            policy: None,
         }
     }
 }
@@ -59,20 +78,42 @@ impl<T: FarLongIteratorItem> Iterator for FarLongIterator<T> {
     fn next(&mut self) -> Option<Self::Item> {
         struct ItemDistance {
             index: usize,
-            distance: u64,
+            distance: f64,
+            travel_time: f64,
             last_selected: DateTime<Local>,
         }
 
+        // если среди валидных элементов нет готовых - берется тот, что будет готов раньше всех
+        let now = Local::now();
+        let is_ready = |item: &T| item.ready_at().is_none_or(|t| t <= now);
+        if !self
+            .elemnts
+            .iter()
+            .any(|item| item.is_valid() && is_ready(item))
+        {
+            return self
+                .elemnts
+                .iter()
+                .enumerate()
+                .filter(|(_, item)| item.is_valid())
+                .min_by_key(|(_, item)| item.ready_at())
+                .map(|(i, _)| {
+                    self.current_selected.replace(i);
+                    i
+                });
+        }
+
         if let Some(c) = self.current_selected {
             let mut distances = self
                 .elemnts
                 .iter()
                 .enumerate()
                 .filter_map(|(i, item)| {
-                    if item.is_valid() {
+                    if item.is_valid() && is_ready(item) {
                         Some(ItemDistance {
                             index: i,
                             distance: item.distance(&self.elemnts[c]),
+                            travel_time: item.travel_time(&self.elemnts[c]),
                             last_selected: item.last_touched(),
                         })
                     } else {
@@ -80,13 +121,29 @@ impl<T: FarLongIteratorItem> Iterator for FarLongIterator<T> {
                     }
                 })
                 .collect::<Vec<_>>();
+
             // сортировка по времени последнего выбора
             distances.sort_by(move |a, b| b.last_selected.cmp(&a.last_selected));
             if let Some(oldest) = distances.last() {
+                // кандидаты - только давно не выбиравшиеся, иначе дальние каналы чередуются между собой
                 let older_time = oldest.last_selected + self.time_tolerance;
                 distances.retain(|item| item.last_selected < older_time);
-                distances.sort_by(|a, b| a.distance.cmp(&b.distance));
-                distances.last().map(|item| {
+
+                let selected = if let Some(policy) = &self.policy {
+                    let score = |item: &ItemDistance| {
+                        let age = (now - item.last_selected).num_milliseconds() as f64 / 1000.0;
+                        policy.distance_weight as f64 * item.distance
+                            - policy.travel_weight as f64 * item.travel_time
+                            + policy.age_weight as f64 * age
+                    };
+                    distances
+                        .iter()
+                        .max_by(|a, b| score(a).total_cmp(&score(b)))
+                } else {
+                    distances.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+                    distances.last()
+                };
+                selected.map(|item| {
                     self.current_selected.replace(item.index);
                     item.index
                 })
@@ -98,7 +155,13 @@ impl<T: FarLongIteratorItem> Iterator for FarLongIterator<T> {
                 .elemnts
                 .iter_mut()
                 .enumerate()
-                .filter_map(|(i, item)| if item.is_valid() { Some(i) } else { None })
+                .filter_map(|(i, item)| {
+                    if item.is_valid() && is_ready(item) {
+                        Some(i)
+                    } else {
+                        None
+                    }
+                })
                 .next()
             {
                 self.current_selected.replace(i);
@@ -119,6 +182,7 @@ mod test {
         id: u32,
         last_selected: DateTime<Local>,
         select_count: usize,
+        ready_at: Option<DateTime<Local>>,
     }
 
     impl<const TOTAL: u32> Item<TOTAL> {
@@ -127,6 +191,7 @@ mod test {
                 id,
                 last_selected: Local::now(),
                 select_count: 0,
+                ready_at: None,
             }
         }
 
@@ -152,8 +217,8 @@ mod test {
     }
 
     impl<const TOTAL: u32> FarLongIteratorItem for Item<TOTAL> {
-        fn distance(&self, other: &Self) -> u64 {
-            self.priv_distance(other) as u64
+        fn distance(&self, other: &Self) -> f64 {
+            self.priv_distance(other) as f64
         }
 
         fn last_touched(&self) -> DateTime<Local> {
@@ -163,6 +228,10 @@ mod test {
         fn is_valid(&self) -> bool {
             self.select_count < TOTAL as usize
         }
+
+        fn ready_at(&self) -> Option<DateTime<Local>> {
+            self.ready_at
+        }
     }
 
     #[test]
@@ -206,4 +275,104 @@ mod test {
             i += 1;
         }
     }
+
+    #[test]
+    fn test_ready_at() {
+        const SIZE: u32 = 5;
+
+        type SItem = Item<SIZE>;
+
+        let mut elemnts = (0..SIZE).map(SItem::new).collect::<Vec<_>>();
+        let later = Local::now() + Duration::seconds(10);
+        elemnts[0].ready_at = Some(later);
+        elemnts[2].ready_at = Some(later + Duration::seconds(1));
+        elemnts[3].ready_at = Some(later - Duration::seconds(1));
+
+        let mut iterator = elemnts.into_far_long_iterator(Duration::microseconds(100));
+
+        // первый готовый
+        assert_eq!(iterator.next(), Some(1));
+        // не готовые пропускаются
+        iterator.get_mut(1).unwrap().select_count = SIZE as usize;
+        assert_eq!(iterator.next(), Some(4));
+
+        // готовых нет - ближайший по времени готовности
+        iterator.get_mut(4).unwrap().select_count = SIZE as usize;
+        assert_eq!(iterator.next(), Some(3));
+    }
+
+    #[test]
+    fn test_policy() {
+        const SIZE: u32 = 5;
+
+        type SItem = Item<SIZE>;
+
+        let mut elemnts = (0..SIZE).map(SItem::new).collect::<Vec<_>>();
+        // канал 1 ждет дольше всех
+        elemnts[1].last_selected = Local::now() - Duration::seconds(60);
+
+        let policy = SchedulingConfig {
+            distance_weight: 1.0,
+            travel_weight: 0.0,
+            age_weight: 0.0,
+            travel_speed: 1.0,
+        };
+
+        // все каналы в пределах допуска по времени, выбор - по оценке
+        let tolerance = Duration::seconds(120);
+        let mut iterator = elemnts
+            .clone()
+            .into_far_long_iterator(tolerance)
+            .with_policy(Some(policy));
+        assert_eq!(iterator.next(), Some(0));
+        // только расстояние - самый дальний
+        assert!(matches!(iterator.next(), Some(2 | 3)));
+
+        let mut iterator =
+            elemnts
+                .into_far_long_iterator(tolerance)
+                .with_policy(Some(SchedulingConfig {
+                    age_weight: 1.0,
+                    ..policy
+                }));
+        assert_eq!(iterator.next(), Some(0));
+        // давно ожидающий сосед важнее дальнего
+        assert_eq!(iterator.next(), Some(1));
+    }
+
+    #[test]
+    fn test_policy_fairness() {
+        const SIZE: u32 = 5;
+
+        type SItem = Item<SIZE>;
+
+        let elemnts = (0..SIZE).map(SItem::new).collect::<Vec<_>>();
+        let duraton = Duration::microseconds(100);
+
+        // только расстояние: без учета времени дальние каналы выбирались бы по очереди друг за другом
+        let mut iterator =
+            elemnts
+                .into_far_long_iterator(duraton)
+                .with_policy(Some(SchedulingConfig {
+                    distance_weight: 1.0,
+                    travel_weight: 0.0,
+                    age_weight: 0.0,
+                    travel_speed: 1.0,
+                }));
+
+        let mut visited = vec![];
+        while let Some(id) = iterator.next() {
+            visited.push(id);
+            std::thread::sleep((duraton * 2).to_std().unwrap());
+            iterator.get_mut(id).map(SItem::select);
+        }
+
+        // каждый готовый канал выбирается, пока не закончится
+        assert_eq!(visited.len(), (SIZE * SIZE) as usize);
+        for round in visited.chunks(SIZE as usize) {
+            let mut round = round.to_vec();
+            round.sort();
+            assert_eq!(round, (0..SIZE as usize).collect::<Vec<_>>());
+        }
+    }
 }
@@ -61,7 +61,8 @@ pub(crate) async fn handle_auto_adjust(
 pub(crate) async fn handle_auto_adjust_status(
     State(auto_adjust_all_ctrl): State<Arc<Mutex<AutoAdjustAllController>>>,
 ) -> impl IntoResponse {
-    use crate::auto_adjust_all::{ProgressReport, ProgressStatus};
+    use crate::auto_adjust_all::{ChannelState, ProgressReport, ProgressStatus};
+    use laser_precision_adjust::{adjust_event::AdjustEvent, drift::ChannelDrift};
 
     const MAX_STEPS: usize = 100;
 
@@ -71,7 +72,14 @@ pub(crate) async fn handle_auto_adjust_status(
         pub current_step: u32,
         pub initial_freq: f32,
         pub current_freq: f32,
-        pub state: String,
+        pub state: ChannelState,
+        pub edge_found: bool,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub events: Vec<AdjustEvent>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub drift: Option<ChannelDrift>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub sealing_shift_hz: Option<f32>,
     }
 
     #[derive(Debug, Clone, Serialize)]
@@ -101,6 +109,10 @@ pub(crate) async fn handle_auto_adjust_status(
                         initial_freq: i.initial_freq,
                         current_freq: i.current_freq,
                         state: i.state,
+                        edge_found: i.edge_found,
+                        events: i.events,
+                        drift: i.drift,
+                        sealing_shift_hz: i.overrides.and_then(|o| o.sealing_shift_hz),
                     })
                     .collect(),
             }
@@ -109,7 +121,6 @@ pub(crate) async fn handle_auto_adjust_status(
 
     #[derive(Serialize)]
     struct AutoAdjustStatusReport {
-        progress_string: String,
         report: AAProgressReport,
         reset_marker: bool,
     }
@@ -130,7 +141,6 @@ pub(crate) async fn handle_auto_adjust_status(
 
                     let report = rx.borrow().clone();
                     yield AutoAdjustStatusReport {
-                        progress_string: report.status.to_string(),
                         report: report.into(),
                         reset_marker,
                     };
@@ -159,6 +169,14 @@ pub(crate) async fn handle_generate_report_excel(
     use super::into_body::IntoBody;
 
     const ROW_OFFSET: usize = 12;
+    // колонки F-I заполняются при выходном контроле, контрольный проход - после них
+    const VERIFY_COLUMNS: [(char, &str); 3] =
+        [('J', "Частота пров."), ('K', "Дрейф"), ('L', "ok?")];
+    const SEALING_COLUMNS: [(char, &str); 3] = [
+        ('M', "Уход герм."),
+        ('N', "Цель корп."),
+        ('O', "Частота корп."),
+    ];
     let report_template_xlsx = include_bytes!("report.xlsx");
 
     if let Ok(mut book) =
@@ -201,6 +219,7 @@ pub(crate) async fn handle_generate_report_excel(
             .set_value(format2digits(working_offset_ppm));
 
         // min-max
+        let adjust_config = freqmeter_config.lock().await.clone();
         let limits = Limits::from_config(freq_target, &config, working_offset_ppm);
         sheet
             .get_cell_value_mut("G7")
@@ -218,6 +237,18 @@ pub(crate) async fn handle_generate_report_excel(
         let report = auto_adjust_all_ctrl.lock().await.get_status();
         if !report.rezonator_info.is_empty() {
             for (i, r) in report.rezonator_info.iter().enumerate() {
+                // каждый канал сравнивается со своей целью, уход - тот, с которым шла настройка
+                let limits = match r.overrides.and_then(|o| o.target.zip(o.sealing_shift_hz)) {
+                    Some((target, shift)) => Limits::sealed(
+                        target,
+                        shift,
+                        r.overrides
+                            .and_then(|o| o.tolerance_hz)
+                            .unwrap_or(adjust_config.channel_tolerance_hz(r.id)),
+                        &config,
+                    ),
+                    None => Limits::for_channel(r.id, &adjust_config, &config),
+                };
                 let row = ROW_OFFSET + i; // row in table
 
                 let current_freq = r.current_freq;
@@ -233,6 +264,60 @@ pub(crate) async fn handle_generate_report_excel(
 
                 let ok = limits.to_status_icon(current_freq).to_owned();
                 sheet.get_cell_value_mut(format!("E{row}")).set_value(ok);
+
+                if let Some(verified_freq) = r.drift.as_ref().and_then(|d| d.verified_freq()) {
+                    let drift = verified_freq - current_freq;
+                    sheet
+                        .get_cell_value_mut(format!("J{row}"))
+                        .set_value(format2digits(verified_freq));
+                    sheet
+                        .get_cell_value_mut(format!("K{row}"))
+                        .set_value(format!("{:+.2}", drift));
+                    let ok = limits.to_status_icon(verified_freq).to_owned();
+                    sheet.get_cell_value_mut(format!("L{row}")).set_value(ok);
+                } else if r.drift.is_some() {
+                    sheet
+                        .get_cell_value_mut(format!("L{row}"))
+                        .set_value("не проверен");
+                }
+
+                // настройка шла на цель в вакууме, ожидаемая частота в корпусе - со сдвигом
+                if let Some(shift) = r.overrides.and_then(|o| o.sealing_shift_hz) {
+                    let freq = r
+                        .drift
+                        .as_ref()
+                        .and_then(|d| d.verified_freq())
+                        .unwrap_or(current_freq);
+                    sheet
+                        .get_cell_value_mut(format!("M{row}"))
+                        .set_value(format!("{:+.2}", shift));
+                    sheet
+                        .get_cell_value_mut(format!("N{row}"))
+                        .set_value(format2digits(limits.sealed_target()));
+                    sheet
+                        .get_cell_value_mut(format!("O{row}"))
+                        .set_value(format2digits(freq + shift));
+                }
+            }
+
+            if report
+                .rezonator_info
+                .iter()
+                .any(|r| r.overrides.is_some_and(|o| o.sealing_shift_hz.is_some()))
+            {
+                for (col, title) in SEALING_COLUMNS {
+                    sheet
+                        .get_cell_value_mut(format!("{col}{}", ROW_OFFSET - 1))
+                        .set_value(title);
+                }
+            }
+
+            if report.rezonator_info.iter().any(|r| r.drift.is_some()) {
+                for (col, title) in VERIFY_COLUMNS {
+                    sheet
+                        .get_cell_value_mut(format!("{col}{}", ROW_OFFSET - 1))
+                        .set_value(title);
+                }
             }
         } else {
             // clear table
@@ -1,4 +1,9 @@
-use std::{cmp::min, collections::HashSet, sync::Arc, time::Duration};
+use std::{
+    cmp::min,
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 
 use axum::{
     extract::{Path, State},
@@ -7,15 +12,18 @@ use axum::{
 };
 
 use laser_precision_adjust::{
-    box_plot::BoxPlot, predict::Predictor, Config, IDataPoint, PrecisionAdjust2,
+    adjust_event::AdjustEvent, box_plot::BoxPlot, channel_targets::parse_channel_targets,
+    predict::Predictor, sealing::parse_sealing_table, Config, IDataPoint, PrecisionAdjust2,
 };
 
 use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
 
 use crate::{
-    auto_adjust_all::AutoAdjustAllController,
-    auto_adjust_single_controller::AutoAdjustSingleController, AdjustConfig, ChannelState,
+    auto_adjust_all::{AutoAdjustAllController, ChannelOverride},
+    auto_adjust_single_controller::AutoAdjustSingleController,
+    noise_characterization::NoiseCharacterizationController,
+    AdjustConfig, ChannelState,
 };
 
 #[derive(Deserialize, Debug)]
@@ -31,6 +39,22 @@ pub struct ControlRequest {
 
     #[serde(rename = "MoveOffset", skip_serializing_if = "Option::is_none")]
     move_offset: Option<i32>,
+
+    /// Каналы для автонастройки, по умолчанию - все
+    #[serde(rename = "Channels", default)]
+    channels: Option<Vec<usize>>,
+
+    /// Индивидуальные настройки каналов для автонастройки
+    #[serde(rename = "Overrides", default)]
+    overrides: HashMap<usize, ChannelOverride>,
+
+    /// Таблица целей каналов в формате CSV или JSON, пустая - сброс
+    #[serde(rename = "TargetsTable", default)]
+    targets_table: Option<String>,
+
+    /// Замеры партии до и после герметизации в формате CSV
+    #[serde(rename = "SealingTable", default)]
+    sealing_table: Option<String>,
 }
 
 #[derive(Serialize, Debug, Default)]
@@ -38,6 +62,8 @@ pub struct ControlResult {
     success: bool,
     error: Option<String>,
     message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    event: Option<AdjustEvent>,
 }
 
 impl ControlResult {
@@ -46,6 +72,22 @@ impl ControlResult {
             success,
             error,
             message,
+            event: None,
+        }
+    }
+
+    pub fn event(event: AdjustEvent) -> Self {
+        match &event {
+            AdjustEvent::Error { message } => Self {
+                error: Some(message.clone()),
+                event: Some(event),
+                ..Default::default()
+            },
+            _ => Self {
+                success: true,
+                event: Some(event),
+                ..Default::default()
+            },
         }
     }
 
@@ -70,6 +112,7 @@ pub(crate) async fn handle_control(
     State(predictor): State<Arc<Mutex<Predictor<f64>>>>,
     State(freqmeter_config): State<Arc<Mutex<AdjustConfig>>>,
     State(auto_adjust_all_ctrl): State<Arc<Mutex<AutoAdjustAllController>>>,
+    State(noise_ctrl): State<Arc<Mutex<NoiseCharacterizationController>>>,
     Json(payload): Json<ControlRequest>,
 ) -> impl IntoResponse {
     const POINTS_TO_AVG: usize = 15;
@@ -353,6 +396,13 @@ pub(crate) async fn handle_control(
             axum_streams::StreamBodyAs::json_nl(stream).into_response()
         }
         "auto-adjust" => {
+            if noise_ctrl.lock().await.is_active() {
+                return Json(ControlResult::error(
+                    "Идет характеризация шума каналов".to_owned(),
+                ))
+                .into_response();
+            }
+
             if let Err(_) = try_block_interface(&select_channel_blocked).await {
                 return match auto_adjust_ctrl.lock().await.cancel().await {
                     Ok(()) => {
@@ -403,26 +453,16 @@ pub(crate) async fn handle_control(
                     status.current_channel,
                     predictor.clone(),
                     precision_adjust.clone(),
-                    freqmeter_config.lock().await.target_freq,
+                    freqmeter_config
+                        .lock()
+                        .await
+                        .channel_trim_target(status.current_channel as usize),
                 )
                 .await
             {
                 let stream = async_stream::stream! {
-                    while let Some(msg) = status_channel.recv().await {
-                        use crate::auto_adjust_single_controller::AutoAdjustSingleStateReport;
-                        yield match msg {
-                            AutoAdjustSingleStateReport::Progress(msg) => {
-                                ControlResult::success(Some(msg))
-                            },
-
-                            AutoAdjustSingleStateReport::Error(e) => {
-                                ControlResult::error(e)
-
-                            },
-                            AutoAdjustSingleStateReport::Finished(msg) => {
-                                 ControlResult::success(Some(msg))
-                            },
-                        };
+                    while let Some(event) = status_channel.recv().await {
+                        yield ControlResult::event(event);
                     }
 
                     // unblock interface
@@ -435,9 +475,19 @@ pub(crate) async fn handle_control(
             }
         }
         "adjust-all" => {
+            if noise_ctrl.lock().await.is_active() {
+                return Json(ControlResult::error(
+                    "Идет характеризация шума каналов".to_owned(),
+                ))
+                .into_response();
+            }
+
             let mut guard = auto_adjust_all_ctrl.lock().await;
             let target = freqmeter_config.lock().await.target_freq;
-            match guard.adjust(target).await {
+            match guard
+                .adjust(target, payload.channels.as_deref(), &payload.overrides)
+                .await
+            {
                 Ok(_) => Json(ControlResult::success(Some(
                     "Автонастройка начата.".to_owned(),
                 )))
@@ -461,6 +511,148 @@ pub(crate) async fn handle_control(
                 .into_response(),
             }
         }
+        "import-targets" => {
+            let text = payload.targets_table.unwrap_or_default();
+            match parse_channel_targets(&text, config.resonator_placement.len()) {
+                Ok(targets) => {
+                    if let Err(e) = config.channel_targets_store().save(&targets) {
+                        tracing::error!("Failed to save channel targets: {e}");
+                        return Json(ControlResult::error(format!(
+                            "Не удалось сохранить таблицу целей: {e}"
+                        )))
+                        .into_response();
+                    }
+
+                    let count = targets.len();
+                    tracing::info!("Imported {} channel targets", count);
+                    freqmeter_config.lock().await.channel_targets = targets;
+                    Json(ControlResult::success(Some(if count == 0 {
+                        "Таблица целей каналов сброшена.".to_owned()
+                    } else {
+                        format!("Импортировано целей каналов: {count}.")
+                    })))
+                }
+                Err(e) => Json(ControlResult::error(format!(
+                    "Не удалось импортировать таблицу целей: {e}"
+                ))),
+            }
+            .into_response()
+        }
+        "import-sealing" => {
+            let Some(store) = config.sealing_store() else {
+                return Json(ControlResult::error(
+                    "Учет ухода частоты при герметизации не настроен".to_owned(),
+                ))
+                .into_response();
+            };
+
+            let text = payload.sealing_table.unwrap_or_default();
+            let samples = match parse_sealing_table(&text, config.resonator_placement.len()) {
+                Ok(samples) => samples,
+                Err(e) => {
+                    return Json(ControlResult::error(format!(
+                        "Не удалось импортировать замеры: {e}"
+                    )))
+                    .into_response()
+                }
+            };
+
+            if let Err(e) = store.append(&samples) {
+                tracing::error!("Failed to save sealing samples: {e}");
+                return Json(ControlResult::error(format!(
+                    "Не удалось сохранить замеры: {e}"
+                )))
+                .into_response();
+            }
+
+            tracing::info!("Imported {} sealing samples", samples.len());
+            freqmeter_config.lock().await.sealing = config.sealing_model();
+            Json(ControlResult::success(Some(format!(
+                "Импортировано замеров герметизации: {}.",
+                samples.len()
+            ))))
+            .into_response()
+        }
+        "adjust-all-resume" => {
+            if noise_ctrl.lock().await.is_active() {
+                return Json(ControlResult::error(
+                    "Идет характеризация шума каналов".to_owned(),
+                ))
+                .into_response();
+            }
+
+            match auto_adjust_all_ctrl.lock().await.resume_batch().await {
+                Ok(()) => Json(ControlResult::success(Some(
+                    "Автонастройка продолжена с контрольной точки.".to_owned(),
+                ))),
+                Err(crate::auto_adjust_all::Error::NoCheckpoint) => Json(ControlResult::error(
+                    "Нет сохраненной партии для продолжения".to_owned(),
+                )),
+                Err(e) => Json(ControlResult::error(format!(
+                    "Не удалось продолжить партию: {e:?}"
+                ))),
+            }
+            .into_response()
+        }
+        "adjust-all-pause" => {
+            let mut guard = auto_adjust_all_ctrl.lock().await;
+            if guard.is_paused() {
+                match guard.resume() {
+                    Ok(()) => Json(ControlResult::success(Some(
+                        "Автонастройка продолжена.".to_owned(),
+                    ))),
+                    Err(e) => Json(ControlResult::error(format!(
+                        "Не удалось продолжить автонастройку: {e:?}"
+                    ))),
+                }
+            } else {
+                match guard.pause() {
+                    Ok(()) => Json(ControlResult::success(Some(
+                        "Автонастройка будет приостановлена после текущего канала.".to_owned(),
+                    ))),
+                    Err(e) => Json(ControlResult::error(format!(
+                        "Не удалось приостановить автонастройку: {e:?}"
+                    ))),
+                }
+            }
+            .into_response()
+        }
+        "characterize" => {
+            let mut guard = noise_ctrl.lock().await;
+            if guard.is_active() {
+                return match guard.cancel() {
+                    Ok(()) => Json(ControlResult::success(Some(
+                        "Характеризация шума отменена.".to_owned(),
+                    ))),
+                    Err(e) => Json(ControlResult::error(format!(
+                        "Не удалось отменить характеризацию: {e:?}"
+                    ))),
+                }
+                .into_response();
+            }
+
+            if auto_adjust_all_ctrl.lock().await.subscribe().is_some() {
+                return Json(ControlResult::error("Идет автонастройка".to_owned())).into_response();
+            }
+
+            // настройка одного канала или другая операция с каналами
+            if *select_channel_blocked.lock().await {
+                return Json(ControlResult::error("Идет настройка канала".to_owned()))
+                    .into_response();
+            }
+
+            let park_time = Duration::from_millis(config.noise.park_time_ms as u64);
+            match guard.start(park_time) {
+                Ok(()) => Json(ControlResult::success(Some(format!(
+                    "Характеризация шума начата, {:.0} с на канал.",
+                    park_time.as_secs_f32()
+                )))),
+                Err(e) => Json(ControlResult::error(format!(
+                    "Не удалось начать характеризацию: {e:?}"
+                ))),
+            }
+            .into_response()
+        }
         _ => {
             tracing::error!("Unknown command: {}", path);
             Json(ControlResult::error("Unknown command".to_owned())).into_response()
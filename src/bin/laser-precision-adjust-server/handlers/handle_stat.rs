@@ -38,12 +38,7 @@ pub(crate) async fn handle_stat_manual(
     State(freqmeter_config): State<Arc<Mutex<AdjustConfig>>>,
     State(engine): State<AppEngine>,
 ) -> impl IntoResponse {
-    let (target_freq, working_offset_ppm) = {
-        let guard = freqmeter_config.lock().await;
-        (guard.target_freq, guard.working_offset_ppm)
-    };
-
-    let limits = Limits::from_config(target_freq, &config, working_offset_ppm);
+    let adjust_config = freqmeter_config.lock().await.clone();
 
     RenderHtml(
         Key("stat".to_owned()),
@@ -53,8 +48,10 @@ pub(crate) async fn handle_stat_manual(
                 .lock()
                 .await
                 .iter()
-                .map(|r| {
+                .enumerate()
+                .map(|(ch, r)| {
                     let current_freq = r.points.last().cloned().unwrap_or_default().y() as f32;
+                    let limits = Limits::for_channel(ch, &adjust_config, &config);
                     RezData {
                         current_step: r.current_step,
                         initial_freq: r
@@ -78,11 +75,7 @@ pub(crate) async fn handle_stat_auto(
     State(freqmeter_config): State<Arc<Mutex<AdjustConfig>>>,
     State(engine): State<AppEngine>,
 ) -> impl IntoResponse {
-    let (target_freq, working_offset_ppm) = {
-        let guard = freqmeter_config.lock().await;
-        (guard.target_freq, guard.working_offset_ppm)
-    };
-    let limits = Limits::from_config(target_freq, &config, working_offset_ppm);
+    let adjust_config = freqmeter_config.lock().await.clone();
 
     RenderHtml(
         Key("stat".to_owned()),
@@ -93,12 +86,15 @@ pub(crate) async fn handle_stat_auto(
                 status
                     .rezonator_info
                     .iter()
-                    .map(|r| RezData {
-                        current_step: r.current_step,
-                        initial_freq: format2digits(r.initial_freq),
-                        current_freq: format2digits(r.current_freq),
-                        status: limits.to_status(r.current_freq),
-                        ppm: format2digits(limits.ppm(r.current_freq)),
+                    .map(|r| {
+                        let limits = Limits::for_channel(r.id, &adjust_config, &config);
+                        RezData {
+                            current_step: r.current_step,
+                            initial_freq: format2digits(r.initial_freq),
+                            current_freq: format2digits(r.current_freq),
+                            status: limits.to_status(r.current_freq),
+                            ppm: format2digits(limits.ppm(r.current_freq)),
+                        }
                     })
                     .collect()
             },
@@ -119,6 +115,7 @@ pub(crate) async fn handle_stat_rez_manual(
     struct DisplayFragment {
         points: Vec<DataPoint<f64>>,
         color_code_rgba: String,
+        score: Option<f64>,
     }
 
     #[derive(Serialize)]
@@ -139,28 +136,29 @@ pub(crate) async fn handle_stat_rez_manual(
     }
 
     impl DrawLimits {
-        pub fn new(l: Limits, target: f32) -> Self {
+        pub fn new(l: Limits) -> Self {
             Self {
                 upper_limit: l.upper_limit,
                 lower_limit: l.lower_limit,
-                target,
+                target: l.target,
             }
         }
     }
 
-    let limits = {
-        let (target_freq, working_offset_ppm) = {
-            let guard = freqmeter_config.lock().await;
-            (guard.target_freq, guard.working_offset_ppm)
-        };
-        DrawLimits::new(
-            Limits::from_config(target_freq, &config, working_offset_ppm),
-            target_freq,
+    let limits = DrawLimits::new(Limits::for_channel(
+        rez_id as usize,
+        &*freqmeter_config.lock().await,
+        &config,
+    ));
+
+    let (fragments, shot_mismatches) = {
+        let guard = predictor.lock().await;
+        (
+            guard.get_fragments(rez_id, None).await,
+            guard.shot_mismatches(rez_id).await,
         )
     };
 
-    let fragments = predictor.lock().await.get_fragments(rez_id, None).await;
-
     let mut display_fragments: Vec<DisplayFragment> = vec![];
     let mut adj_values: Vec<f64> = vec![];
     for (i, fragment) in fragments.iter().enumerate() {
@@ -168,14 +166,10 @@ pub(crate) async fn handle_stat_rez_manual(
         display_fragments.push(DisplayFragment {
             points: fragment.points().to_vec(),
             color_code_rgba: format!("rgba(103, 145, 102, {opacity:.2})"),
+            score: fragment.fit_quality().map(|q| q.score),
         });
 
-        adj_values.push(if let Some((a, _)) = fragment.aprox_coeffs() {
-            fragment.points()[fragment.minimum_index()].y() + a - fragment.points()[0].y()
-        } else {
-            let box_plot = fragment.box_plot();
-            box_plot.upper_bound() - fragment.points()[0].y()
-        });
+        adj_values.push(fragment.target() - fragment.points()[0].y());
     }
 
     let interval_count = ((adj_values.len() as f64).log(10.0) * 3.0 + 1.0).floor() as u32;
@@ -208,6 +202,7 @@ pub(crate) async fn handle_stat_rez_manual(
             "DisplayFragments": display_fragments,
             "Hystogramm": hystogramm,
             "Limits": limits,
+            "ShotMismatches": shot_mismatches,
     }))
 }
 
@@ -244,25 +239,20 @@ pub(crate) async fn handle_stat_rez_auto(
     }
 
     impl DrawLimits {
-        pub fn new(l: Limits, target: f32) -> Self {
+        pub fn new(l: Limits) -> Self {
             Self {
                 upper_limit: l.upper_limit,
                 lower_limit: l.lower_limit,
-                target,
+                target: l.target,
             }
         }
     }
 
-    let limits = {
-        let (target_freq, working_offset_ppm) = {
-            let guard = freqmeter_config.lock().await;
-            (guard.target_freq, guard.working_offset_ppm)
-        };
-        DrawLimits::new(
-            Limits::from_config(target_freq, &config, working_offset_ppm),
-            target_freq,
-        )
-    };
+    let limits = DrawLimits::new(Limits::for_channel(
+        rez_id as usize,
+        &*freqmeter_config.lock().await,
+        &config,
+    ));
 
     let fragments = auto_adjust_all_ctrl
         .lock()
@@ -1,4 +1,4 @@
-use laser_precision_adjust::Config;
+use laser_precision_adjust::{AdjustConfig, Config};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone, Copy, Debug)]
@@ -16,9 +16,12 @@ pub enum RezStatus {
 }
 
 pub struct Limits {
+    pub target: f32,
     pub upper_limit: f32,
     pub lower_limit: f32,
     pub ultra_low_limit: f32,
+    /// Ожидаемый уход частоты при герметизации, target - цель в вакууме
+    pub sealing_shift: f32,
 }
 
 impl Limits {
@@ -57,10 +60,40 @@ impl Limits {
 
     pub fn from_config(target: f32, config: &Config, working_offset_ppm: f32) -> Self {
         let ppm2hz = target * working_offset_ppm / 1_000_000.0;
+        Self::from_target(target, ppm2hz, config)
+    }
+
+    pub fn from_target(target: f32, tolerance_hz: f32, config: &Config) -> Self {
         Self {
-            upper_limit: target + ppm2hz,
-            lower_limit: target - ppm2hz,
+            target,
+            upper_limit: target + tolerance_hz,
+            lower_limit: target - tolerance_hz,
             ultra_low_limit: target - config.auto_adjust_limits.min_freq_offset,
+            sealing_shift: 0.0,
+        }
+    }
+
+    /// Пределы канала с учетом импортированной таблицы целей и ухода частоты при герметизации
+    pub fn for_channel(channel: usize, adjust_config: &AdjustConfig, config: &Config) -> Self {
+        let target = adjust_config.channel_target(channel);
+        Self::sealed(
+            target,
+            adjust_config.sealing_shift_hz(channel, target),
+            adjust_config.channel_tolerance_hz(channel),
+            config,
+        )
+    }
+
+    /// Пределы в вакууме для цели в корпусе target и ухода при герметизации shift
+    pub fn sealed(target: f32, shift: f32, tolerance_hz: f32, config: &Config) -> Self {
+        Self {
+            sealing_shift: shift,
+            ..Self::from_target(target - shift, tolerance_hz, config)
         }
     }
+
+    /// Цель в корпусе, после герметизации
+    pub fn sealed_target(&self) -> f32 {
+        self.target + self.sealing_shift
+    }
 }
@@ -1,4 +1,4 @@
-use std::{borrow::Borrow, sync::Arc, time::SystemTime};
+use std::{borrow::Borrow, collections::HashMap, sync::Arc, time::SystemTime};
 
 use axum::{
     extract::{Path, State},
@@ -6,7 +6,7 @@ use axum::{
 };
 use axum_template::{Key, RenderHtml};
 use laser_precision_adjust::{
-    box_plot::BoxPlot, predict::Predictor, Config, DataPoint, IDataPoint,
+    box_plot::RollingBoxPlot, predict::Predictor, Config, DataPoint, IDataPoint,
 };
 
 use num_traits::Float;
@@ -104,16 +104,8 @@ pub(crate) async fn handle_work(
     // force release lock
     *select_channel_blocked.lock().await = false;
 
-    let (target_freq, work_offset_hz, working_offset_ppm) = {
-        let guard = freqmeter_config.lock().await;
-        (
-            guard.target_freq,
-            guard.work_offset_hz,
-            guard.working_offset_ppm,
-        )
-    };
-
-    let limits = Limits::from_config(target_freq, &config, working_offset_ppm);
+    let adjust_config = freqmeter_config.lock().await.clone();
+    let (target_freq, work_offset_hz) = (adjust_config.target_freq, adjust_config.work_offset_hz);
 
     RenderHtml(
         Key("work".to_owned()),
@@ -123,8 +115,10 @@ pub(crate) async fn handle_work(
                 .lock()
                 .await
                 .iter()
-                .map(|r| {
+                .enumerate()
+                .map(|(ch, r)| {
                     let current_freq = r.points.last().cloned().unwrap_or_default().y() as f32;
+                    let limits = Limits::for_channel(ch, &adjust_config, &config);
                     RezData {
                         current_step: r.current_step,
                         initial_freq: r
@@ -155,7 +149,11 @@ pub(crate) async fn handle_state(
 ) -> impl IntoResponse {
     const MAX_POINTS: usize = 100;
 
+    const MEDIAN_LEN: usize = 5;
+
     let mut counter = 0;
+    // скользящие окна последних точек каждого канала для медианы
+    let mut medians: HashMap<u32, RollingBoxPlot<f64>> = HashMap::new();
 
     let stream = async_stream::stream! {
         loop {
@@ -164,12 +162,10 @@ pub(crate) async fn handle_state(
             status_rx.changed().await.ok();
 
             let status = status_rx.borrow().clone();
-            let (target_freq, working_offset_ppm) = {
+            // цель и допуск текущего канала с учетом таблицы целей
+            let limits = {
                 let guard = freqmeter_config.lock().await;
-                (
-                    guard.target_freq,
-                    guard.working_offset_ppm,
-                )
+                Limits::for_channel(status.current_channel as usize, &guard, &config)
             };
 
             let timestamp = status.since_start.as_millis();
@@ -183,9 +179,16 @@ pub(crate) async fn handle_state(
                 (channel.initial_freq, channel.points.clone())
             };
 
-            const MEDIAN_LEN: usize = 5;
+            let window = medians.entry(status.current_channel).or_insert_with(|| {
+                // окно заполняется уже накопленными точками, последняя добавляется ниже
+                let mut window = config.box_plot.rolling(MEDIAN_LEN);
+                points[..points.len() - 1].iter().for_each(|p| window.push(p.y()));
+                window
+            });
+            window.push(points.last().unwrap().y());
+
             let (aproximations, mut prediction) = if points.len() > MEDIAN_LEN {
-                let median = BoxPlot::new(&points[(points.len() - MEDIAN_LEN)..].iter().map(|p| p.y()).collect::<Vec<_>>()).median();
+                let median = window.box_plot().median();
                 get_prediction(predictor.lock().await.borrow(),
                                status.current_channel,
                                median,
@@ -233,15 +236,12 @@ pub(crate) async fn handle_state(
             // update start offset
             prediction.as_mut().map(|p| p.start_offset = config.display_points_count - MEDIAN_LEN);
 
-            // status code
-            let limits = Limits::from_config(target_freq, &config, working_offset_ppm);
-
             yield StateResult {
                 timestamp,
                 seleced_channel: status.current_channel,
                 current_freq: status.current_frequency,
-                target_freq: target_freq,
-                work_offset_hz: target_freq * working_offset_ppm / 1_000_000.0,
+                target_freq: limits.target,
+                work_offset_hz: limits.upper_limit - limits.target,
                 channel_step: status.current_step,
                 initial_freq,
                 points: points.iter().map(|p| (p.x(), p.y())).collect(),
@@ -274,6 +274,10 @@ pub(crate) async fn handle_generate_report(
 
     #[derive(Serialize)]
     struct RezInfo {
+        target: String,
+        /// Цель и ожидаемая частота в корпусе, если учитывается уход при герметизации
+        sealed_target: Option<String>,
+        sealed: Option<String>,
         start: String,
         end: String,
         ppm: String,
@@ -294,14 +298,12 @@ pub(crate) async fn handle_generate_report(
         rezonators: Vec<RezInfo>,
     }
 
-    let (freq_target, work_offset_hz, working_offset_ppm) = {
-        let guard = freqmeter_config.lock().await;
-        (
-            guard.target_freq,
-            guard.work_offset_hz,
-            guard.working_offset_ppm,
-        )
-    };
+    let adjust_config = freqmeter_config.lock().await.clone();
+    let (freq_target, work_offset_hz, working_offset_ppm) = (
+        adjust_config.target_freq,
+        adjust_config.work_offset_hz,
+        adjust_config.working_offset_ppm,
+    );
 
     let limits = Limits::from_config(freq_target, &config, working_offset_ppm);
 
@@ -323,9 +325,19 @@ pub(crate) async fn handle_generate_report(
             .lock()
             .await
             .iter()
-            .map(|r| {
+            .enumerate()
+            .map(|(ch, r)| {
                 let current_freq = r.points.last().cloned().unwrap_or_default().y() as f32;
+                // каждый канал сравнивается со своей целью
+                let limits = Limits::for_channel(ch, &adjust_config, &config);
+                let sealed = |f: f32| {
+                    Some(format2digits(f + limits.sealing_shift))
+                        .filter(|_| limits.sealing_shift != 0.0)
+                };
                 RezInfo {
+                    target: format2digits(limits.target),
+                    sealed_target: sealed(limits.target),
+                    sealed: sealed(current_freq),
                     start: r
                         .initial_freq
                         .map(|f| format2digits(f))
@@ -6,6 +6,7 @@ pub mod handle_stat;
 pub mod into_body;
 pub mod limits;
 pub mod manual;
+pub mod noise;
 pub mod static_files;
 
 pub(crate) use auto::{
@@ -17,3 +18,4 @@ pub(crate) use handle_stat::{
     handle_stat_auto, handle_stat_manual, handle_stat_rez_auto, handle_stat_rez_manual,
 };
 pub(crate) use manual::{handle_generate_report, handle_state, handle_work};
+pub(crate) use noise::handle_noise;
@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use axum::{extract::State, response::IntoResponse, Json};
+use laser_precision_adjust::{noise::NoiseProfile, Config};
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+use crate::noise_characterization::{NoiseCharacterizationController, NoiseProgress};
+
+// Результаты характеризации шума каналов
+pub(crate) async fn handle_noise(
+    State(config): State<Config>,
+    State(noise_ctrl): State<Arc<Mutex<NoiseCharacterizationController>>>,
+) -> impl IntoResponse {
+    #[derive(Serialize)]
+    struct ChannelNoise {
+        profile: NoiseProfile,
+        /// Рекомендуемое количество измерений в окне
+        measure_count: usize,
+        /// Рекомендуемый порог стабильности, Гц
+        stable_range: f64,
+    }
+
+    #[derive(Serialize)]
+    struct NoiseReport {
+        status: NoiseProgress,
+        channels: Vec<Option<ChannelNoise>>,
+    }
+
+    let (status, profiles) = {
+        let guard = noise_ctrl.lock().await;
+        (guard.get_status(), guard.profiles())
+    };
+
+    let channels = profiles
+        .lock()
+        .await
+        .iter()
+        .map(|p| {
+            p.as_ref().map(|profile| {
+                let measure_count = profile.suggested_measure_count();
+                ChannelNoise {
+                    stable_range: profile
+                        .suggested_stable_range(config.noise.stable_margin, measure_count),
+                    measure_count,
+                    profile: profile.clone(),
+                }
+            })
+        })
+        .collect();
+
+    Json(NoiseReport { status, channels })
+}
@@ -2,8 +2,10 @@
 
 mod auto_adjust_all;
 mod auto_adjust_single_controller;
+mod crosstalk_tracker;
 mod far_long_iterator;
 mod handlers;
+mod noise_characterization;
 
 use std::{net::SocketAddr, sync::Arc};
 
@@ -53,6 +55,7 @@ struct AppState {
     predictor: Arc<Mutex<Predictor<f64>>>,
     auto_adjust_ctrl: Arc<Mutex<auto_adjust_single_controller::AutoAdjustSingleController>>,
     auto_adjust_all_ctrl: Arc<Mutex<auto_adjust_all::AutoAdjustAllController>>,
+    noise_ctrl: Arc<Mutex<noise_characterization::NoiseCharacterizationController>>,
 }
 
 fn float2dgt(value: String) -> String {
@@ -131,19 +134,45 @@ async fn main() -> Result<(), std::io::Error> {
         target_freq: config.target_freq_center,
         work_offset_hz: config.freqmeter_offset,
         working_offset_ppm: config.working_offset_ppm,
+        channel_targets: config
+            .channel_targets_store()
+            .load(config.resonator_placement.len())
+            .unwrap_or_else(|e| {
+                tracing::error!("Failed to load channel targets: {e}");
+                vec![]
+            }),
+        sealing: config.sealing_model(),
     }));
 
-    let predictor = Predictor::new(
+    let predictor = Arc::new(Mutex::new(Predictor::new(
         status_rx.clone(),
         config.forecast_config,
         config.resonator_placement.len(),
         (config.cooldown_time_ms / config.update_interval_ms) as usize,
-    );
+        config.filters.fragment.clone(),
+        config.history_store(),
+        config.adaptive_cooldown,
+    )));
 
     let auto_adjust_controller = auto_adjust_single_controller::AutoAdjustSingleController::new(
         config.auto_adjust_limits,
         config.update_interval_ms,
         freqmeter_config.clone(),
+        config.edge_store(),
+    );
+
+    let noise_profiles: noise_characterization::NoiseProfiles = Arc::new(Mutex::new(vec![
+        None;
+        config.resonator_placement.len()
+    ]));
+
+    let noise_controller = noise_characterization::NoiseCharacterizationController::new(
+        config.resonator_placement.len(),
+        laser_setup_controller.clone(),
+        precision_adjust.clone(),
+        config.switch_channel_delay_ms,
+        config.report_directory(),
+        noise_profiles.clone(),
     );
 
     let auto_adjust_all_controller = auto_adjust_all::AutoAdjustAllController::new(
@@ -155,9 +184,23 @@ async fn main() -> Result<(), std::io::Error> {
         std::time::Duration::from_millis(config.update_interval_ms as u64),
         config.forecast_config,
         config.auto_adjust_limits.fast_forward_step_limit,
+        config.total_vertical_steps,
         config.switch_channel_delay_ms,
         freqmeter_config.clone(),
         config.report_directory(),
+        config.filters.measure.clone(),
+        config.measure_kalman,
+        config.box_plot,
+        config.noise,
+        noise_profiles,
+        predictor.clone(),
+        config.resonator_placement.clone(),
+        config.crosstalk,
+        config.axis_config,
+        config.scheduling,
+        config.verify_sweep,
+        config.edge_store(),
+        config.crosstalk_store(),
     );
 
     // State for our application
@@ -198,9 +241,10 @@ async fn main() -> Result<(), std::io::Error> {
         close_timestamp: Arc::new(Mutex::new(None)),
         select_channel_blocked: Arc::new(Mutex::new(false)),
 
-        predictor: Arc::new(Mutex::new(predictor)),
+        predictor,
         auto_adjust_ctrl: Arc::new(Mutex::new(auto_adjust_controller)),
         auto_adjust_all_ctrl: Arc::new(Mutex::new(auto_adjust_all_controller)),
+        noise_ctrl: Arc::new(Mutex::new(noise_controller)),
     };
 
     // Build our application with some routes
@@ -211,6 +255,7 @@ async fn main() -> Result<(), std::io::Error> {
         .route("/work", get(handle_work))
         .route("/auto", get(handle_auto_adjust))
         .route("/auto_status", get(handle_auto_adjust_status))
+        .route("/noise", get(handle_noise))
         .route("/stat_manual", get(handle_stat_manual))
         .route("/stat_manual/:rez_id", get(handle_stat_rez_manual))
         .route("/stat_auto", get(handle_stat_auto))
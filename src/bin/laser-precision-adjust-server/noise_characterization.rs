@@ -0,0 +1,202 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use chrono::Local;
+use laser_precision_adjust::{noise::NoiseProfile, PrivStatusEvent};
+use serde::Serialize;
+use tokio::sync::{watch, Mutex};
+
+/// Результаты характеризации шума по каналам
+pub type NoiseProfiles = Arc<Mutex<Vec<Option<NoiseProfile>>>>;
+
+#[derive(Debug)]
+pub enum Error {
+    InProgress,
+    NothingToCancel,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct NoiseProgress {
+    pub active: bool,
+    pub current_channel: Option<u32>,
+    pub error: Option<String>,
+}
+
+pub struct NoiseCharacterizationController {
+    channel_count: usize,
+    laser_setup_controller: Arc<Mutex<laser_precision_adjust::LaserSetupController>>,
+    precision_adjust: Arc<Mutex<laser_precision_adjust::PrecisionAdjust2>>,
+    switch_channel_delay_ms: u32,
+    report_directory: PathBuf,
+    profiles: NoiseProfiles,
+
+    task: Option<tokio::task::JoinHandle<()>>,
+    rx: Option<watch::Receiver<NoiseProgress>>,
+}
+
+impl NoiseCharacterizationController {
+    pub fn new(
+        channel_count: usize,
+        laser_setup_controller: Arc<Mutex<laser_precision_adjust::LaserSetupController>>,
+        precision_adjust: Arc<Mutex<laser_precision_adjust::PrecisionAdjust2>>,
+        switch_channel_delay_ms: u32,
+        report_directory: PathBuf,
+        profiles: NoiseProfiles,
+    ) -> Self {
+        Self {
+            channel_count,
+            laser_setup_controller,
+            precision_adjust,
+            switch_channel_delay_ms,
+            report_directory,
+            profiles,
+
+            task: None,
+            rx: None,
+        }
+    }
+
+    pub fn profiles(&self) -> NoiseProfiles {
+        self.profiles.clone()
+    }
+
+    pub fn get_status(&self) -> NoiseProgress {
+        self.rx
+            .as_ref()
+            .map(|rx| rx.borrow().clone())
+            .unwrap_or_default()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.task.as_ref().is_some_and(|t| !t.is_finished())
+    }
+
+    pub fn start(&mut self, park_time: Duration) -> Result<(), Error> {
+        if self.is_active() {
+            return Err(Error::InProgress);
+        }
+
+        let (tx, rx) = watch::channel(NoiseProgress {
+            active: true,
+            ..Default::default()
+        });
+        self.rx.replace(rx);
+
+        self.task.replace(tokio::spawn(characterize_task(
+            tx,
+            self.channel_count,
+            self.laser_setup_controller.clone(),
+            self.precision_adjust.clone(),
+            Duration::from_millis(self.switch_channel_delay_ms as u64),
+            park_time,
+            self.report_directory.clone(),
+            self.profiles.clone(),
+        )));
+
+        Ok(())
+    }
+
+    pub fn cancel(&mut self) -> Result<(), Error> {
+        if let Some(task) = &self.task {
+            if !task.is_finished() {
+                task.abort();
+                self.task = None;
+                self.rx = None;
+                return Ok(());
+            }
+        }
+        Err(Error::NothingToCancel)
+    }
+}
+
+async fn characterize_task(
+    tx: watch::Sender<NoiseProgress>,
+    channel_count: usize,
+    laser_setup_controller: Arc<Mutex<laser_precision_adjust::LaserSetupController>>,
+    precision_adjust: Arc<Mutex<laser_precision_adjust::PrecisionAdjust2>>,
+    switch_channel_wait: Duration,
+    park_time: Duration,
+    report_directory: PathBuf,
+    profiles: NoiseProfiles,
+) {
+    let mut rx = laser_setup_controller.lock().await.subscribe();
+
+    for ch_id in 0..channel_count as u32 {
+        tx.send(NoiseProgress {
+            active: true,
+            current_channel: Some(ch_id),
+            error: None,
+        })
+        .ok();
+
+        {
+            let mut guard = laser_setup_controller.lock().await;
+            if let Err(e) = guard.select_channel(ch_id).await {
+                tracing::error!("Failed to switch freqmeter channel: {e:?}");
+                tx.send(NoiseProgress {
+                    active: true,
+                    current_channel: Some(ch_id),
+                    error: Some(format!("Не удалось переключить канал {}: {e:?}", ch_id + 1)),
+                })
+                .ok();
+                continue;
+            }
+            guard.delay(switch_channel_wait).await;
+        }
+        precision_adjust
+            .lock()
+            .await
+            .push_event(PrivStatusEvent {
+                chanel_select: Some(ch_id),
+                ..Default::default()
+            })
+            .await;
+
+        // переходной процесс после переключения канала не учитывается
+        tokio::time::sleep(switch_channel_wait).await;
+        rx.borrow_and_update();
+
+        let mut t = vec![];
+        let mut f = vec![];
+        let start = std::time::Instant::now();
+        while start.elapsed() < park_time {
+            if rx.changed().await.is_err() {
+                tracing::error!("Freqmeter status channel closed");
+                return;
+            }
+            t.push(start.elapsed().as_secs_f64());
+            f.push(rx.borrow().current_frequency as f64);
+        }
+
+        let profile = NoiseProfile::from_samples(&t, &f);
+        match &profile {
+            Some(p) => tracing::info!(
+                "Channel {} noise: F={:.3} Hz, white={:.4} Hz, drift={:.5} Hz/s, optimal window={} pts",
+                ch_id,
+                p.mean,
+                p.white_noise,
+                p.drift,
+                p.suggested_measure_count()
+            ),
+            None => tracing::warn!("Channel {}: not enough samples ({})", ch_id, f.len()),
+        }
+        profiles.lock().await[ch_id as usize] = profile;
+    }
+
+    {
+        let save_file_path = report_directory.join(format!(
+            "noise_{}.json",
+            Local::now().format("%Y-%m-%d_%H-%M-%S")
+        ));
+
+        match std::fs::File::create(&save_file_path) {
+            Ok(mut file) => {
+                if let Err(e) = serde_json::to_writer_pretty(&mut file, &*profiles.lock().await) {
+                    tracing::error!("Failed to save noise profiles: {e}");
+                }
+            }
+            Err(e) => tracing::error!("Failed to create {save_file_path:?}: {e}"),
+        }
+    }
+
+    tx.send(NoiseProgress::default()).ok();
+}
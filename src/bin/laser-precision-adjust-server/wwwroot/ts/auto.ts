@@ -2,6 +2,8 @@
 type Done = "Done";
 type Adjusting = "Adjusting";
 type Idle = "Idle";
+type Paused = "Paused";
+type Settling = "Settling";
 
 interface ISearchingEdge {
     ch: number,
@@ -10,26 +12,61 @@ interface ISearchingEdge {
 
 interface IProgressStatus {
     SearchingEdge?: ISearchingEdge,
+    Verifying?: { sweep: number },
     Error?: string,
 }
 
+interface IAdjustingStatus {
+    Unstable?: { iqr: number, trys: number },
+}
+
+interface IChannelStateAdjusting {
+    Adjustig: "Normal" | "IncorrectMeasure" | "OutOfRange" | IAdjustingStatus,
+}
+
+type ChannelState = "UnknownInit" | "Unsatable" | "OutOfRange" | "Limit" | "BudgetExhausted" | "Verify" | "Ok" | "Drifted" | "Unverified"
+    | "Skipped" | IChannelStateAdjusting;
+
+interface IChannelDrift {
+    trimmed_freq: number,
+    samples: Array<[number, number]>,
+}
+
 interface IRezInfo {
     id: number,
     current_step: number,
+    steps_left: number,
     initial_freq: number,
     current_freq: number,
-    state: string,
+    state: ChannelState,
+    edge_found: boolean,
+    events?: Array<AdjustEvent>,
+    drift?: IChannelDrift,
+    sealing_shift_hz?: number,
 }
 
 interface IProgressReport {
-    status: IProgressStatus | Idle | Done | Adjusting,
+    status: IProgressStatus | Idle | Done | Adjusting | Paused | Settling,
     measure_channel_id?: number,
     burn_channel_id?: number,
     rezonator_info: Array<IRezInfo>,
 }
 
+interface IChannelOverride {
+    Skip?: boolean,
+    TargetFreq?: number,
+    ToleranceHz?: number,
+    MaxSteps?: number,
+    PowerMultiplier?: number,
+    SealingShiftHz?: number,
+}
+
+interface IAdjustAllRequest {
+    Channels: Array<number>,
+    Overrides: { [ch: number]: IChannelOverride },
+}
+
 interface IAutoAdjustStatusReport {
-    progress_string: string,
     report: IProgressReport,
     reset_marker: boolean,
 }
@@ -67,11 +104,29 @@ $(() => {
         })
     });
 
+    $('#rez-select-all').on('change', (ev) => {
+        $('.rez-select').prop('checked', (ev.target as HTMLInputElement).checked);
+    });
+
+    $('.rez-override').on('input', (ev) => {
+        $(ev.target).removeClass('is-invalid');
+    });
+
     $('#adj-all-ctrl-btn').on('click', (ev) => {
+        // остановка - индивидуальные настройки не нужны
+        const request = $(ev.target).hasClass('btn-warning')
+            ? { Channels: [], Overrides: {} }
+            : collect_adjust_request();
+        if (request === null) {
+            noty_error('Неверные индивидуальные настройки каналов');
+            ev.preventDefault();
+            return;
+        }
+
         $.ajax({
             url: '/control/adjust-all',
             method: 'POST',
-            data: JSON.stringify({}),
+            data: JSON.stringify(request),
             contentType: 'application/json',
             success: (data) => {
                 if (!data.success) {
@@ -86,6 +141,76 @@ $(() => {
         ev.preventDefault();
     });
 
+    $('#adj-all-pause-btn').on('click', (ev) => {
+        $.ajax({
+            url: '/control/adjust-all-pause',
+            method: 'POST',
+            data: JSON.stringify({}),
+            contentType: 'application/json',
+            success: (data) => {
+                if (!data.success) {
+                    noty_error('Ошибка: ' + data.error);
+                } else {
+                    noty_success(data.message);
+                }
+            }
+        });
+        ev.preventDefault();
+    });
+
+    $('#adj-all-resume-btn').on('click', (ev) => {
+        $.ajax({
+            url: '/control/adjust-all-resume',
+            method: 'POST',
+            data: JSON.stringify({}),
+            contentType: 'application/json',
+            success: (data) => {
+                if (!data.success) {
+                    noty_error('Ошибка: ' + data.error);
+                } else {
+                    start_autoadjust_updater();
+                }
+            }
+        });
+        ev.preventDefault();
+    });
+
+    // таблица целей каналов, отмена выбора файла - таблица не меняется
+    $('#import-targets').on('click', (ev) => {
+        $('#import-targets-file').val('').trigger('click');
+        ev.preventDefault();
+    });
+
+    // пустая таблица - сброс целей каналов
+    $('#clear-targets').on('click', (ev) => {
+        if (confirm('Сбросить таблицу целей каналов?')) {
+            import_targets('');
+        }
+        ev.preventDefault();
+    });
+
+    $('#import-targets-file').on('change', (ev) => {
+        const file = (ev.target as HTMLInputElement).files[0];
+        if (file === undefined) {
+            return;
+        }
+        file.text().then((text) => import_targets(text));
+    });
+
+    // замеры партии до и после герметизации для уточнения ухода частоты каналов
+    $('#import-sealing').on('click', (ev) => {
+        $('#import-sealing-file').val('').trigger('click');
+        ev.preventDefault();
+    });
+
+    $('#import-sealing-file').on('change', (ev) => {
+        const file = (ev.target as HTMLInputElement).files[0];
+        if (file === undefined) {
+            return;
+        }
+        file.text().then((text) => import_sealing(text));
+    });
+
     // report
     $('#gen-report').on('click', (ev) => {
         let report_id = prompt('Введите номер партии:');
@@ -101,11 +226,129 @@ $(() => {
     start_autoadjust_updater();
 });
 
-function update_autoadjust(report: IProgressReport, progress_string: string) {
+function import_targets(text: string) {
+    $.ajax({
+        url: '/control/import-targets',
+        method: 'POST',
+        data: JSON.stringify({ TargetsTable: text }),
+        contentType: 'application/json',
+        success: (data) => {
+            if (!data.success) {
+                noty_error('Ошибка: ' + data.error);
+            } else {
+                noty_success(data.message);
+            }
+        }
+    });
+}
+
+function import_sealing(text: string) {
+    $.ajax({
+        url: '/control/import-sealing',
+        method: 'POST',
+        data: JSON.stringify({ SealingTable: text }),
+        contentType: 'application/json',
+        success: (data) => {
+            if (!data.success) {
+                noty_error('Ошибка: ' + data.error);
+            } else {
+                noty_success(data.message);
+            }
+        }
+    });
+}
+
+// Выбранные каналы и их индивидуальные настройки, null - есть неверные значения
+function collect_adjust_request(): IAdjustAllRequest | null {
+    const request: IAdjustAllRequest = { Channels: [], Overrides: {} };
+    let valid = true;
+
+    $('.rez-select').each((_, el) => {
+        const ch = parseInt($(el).attr('rez-pos')) - 1;
+        if ((el as HTMLInputElement).checked) {
+            request.Channels.push(ch);
+        }
+    });
+
+    $('.rez-override').each((_, el) => {
+        const input = el as HTMLInputElement;
+        const value = input.value;
+        if (value === '' && !input.validity.badInput) {
+            $(el).removeClass('is-invalid');
+            return;
+        }
+
+        // MaxSteps на сервере - целое без знака
+        const key = $(el).attr('override');
+        const number = parseFloat(value);
+        const ok = !input.validity.badInput
+            && !input.validity.rangeUnderflow
+            && isFinite(number)
+            && (key != 'MaxSteps' || Number.isInteger(number));
+        $(el).toggleClass('is-invalid', !ok);
+        if (!ok) {
+            valid = false;
+            return;
+        }
+
+        const ch = parseInt($(el).attr('rez-pos')) - 1;
+        if (request.Overrides[ch] === undefined) {
+            request.Overrides[ch] = {};
+        }
+        (request.Overrides[ch] as { [key: string]: number })[key] =
+            key == 'MaxSteps' ? parseInt(value, 10) : number;
+    });
+
+    return valid ? request : null;
+}
+
+function progress_status_text(status: IProgressStatus | Idle | Done | Adjusting | Paused | Settling): string {
+    switch (status) {
+        case "Idle": return "Ожидание";
+        case "Adjusting": return "Настройка";
+        case "Paused": return "Пауза";
+        case "Settling": return "Выдержка перед проверкой";
+        case "Done": return "Завершено";
+        default:
+            return status.Verifying !== undefined
+                ? "Контрольный проход " + status.Verifying.sweep
+                : "Ошибка: " + status.Error;
+    }
+}
+
+function adjusting_status_text(status: IChannelStateAdjusting["Adjustig"]): string {
+    switch (status) {
+        case "Normal": return "Норма";
+        case "IncorrectMeasure": return "Некорректное измерение";
+        case "OutOfRange": return "Вне диапазона";
+        default: return "Нестабилен: Δ=" + round_to_2_digits(status.Unstable.iqr) + " (" + status.Unstable.trys + ")";
+    }
+}
+
+function channel_state_text(rez: IRezInfo): string {
+    switch (rez.state) {
+        case "UnknownInit": return "Неизвестно";
+        case "Unsatable": return "Сломан или нестабилен";
+        case "OutOfRange": return "Вне диапазона настройки";
+        case "Limit": return "Превышен лимит шагов";
+        case "BudgetExhausted": return "Исчерпан запас шагов";
+        case "Verify": return "Проверка";
+        case "Ok": return "Настроен";
+        case "Drifted": return "Ушел из допуска после настройки";
+        case "Unverified": return "Настроен, не проверен";
+        case "Skipped": return "Пропущен";
+        default:
+            return rez.edge_found
+                ? "Настройка [" + adjusting_status_text(rez.state.Adjustig) + "]"
+                : "Поиск края";
+    }
+}
+
+function update_autoadjust(report: IProgressReport) {
     const measure_class = 'sensor';
     const burn_class = 'burner';
 
-    $('#adjust-step').text(progress_string);
+    $('#adjust-step').text(progress_status_text(report.status));
 
     function set_btn_state(start: boolean) {
         const button = $('#adj-all-ctrl-btn');
@@ -116,6 +359,12 @@ function update_autoadjust(report: IProgressReport, progress_string: string) {
         }
     }
 
+    function set_pause_btn_state(active: boolean, paused: boolean) {
+        const button = $('#adj-all-pause-btn');
+        button.prop('disabled', !active).text(paused ? 'Продолжить' : 'Пауза');
+        $('#adj-all-resume-btn').prop('disabled', active);
+    }
+
     function reset_laser_pos() {
         $('tr').removeClass(burn_class);
     }
@@ -127,16 +376,19 @@ function update_autoadjust(report: IProgressReport, progress_string: string) {
     if ((<IProgressStatus>report.status).Error != undefined) {
         noty_error((report.status as IProgressStatus).Error);
         set_btn_state(true);
+        set_pause_btn_state(false, false);
         reset_laser_pos();
         reset_freqmeter_pos();
         return;
     } else if (report.status == "Done") {
         noty_success("Настройка завершена!");
         set_btn_state(false);
+        set_pause_btn_state(false, false);
         reset_laser_pos();
         reset_freqmeter_pos();
     } else {
         set_btn_state(report.status !== "Idle");
+        set_pause_btn_state(report.status !== "Idle", report.status === "Paused");
     }
 
     const sel_header = (ch: number) => 'th[rez-pos="' + (ch + 1).toString() + '"]'
@@ -171,19 +423,50 @@ function update_autoadjust(report: IProgressReport, progress_string: string) {
     for (const rez of report.rezonator_info) {
         const posid: string = '[rez-pos="' + (rez.id + 1).toString() + '"]';
         update_text_if_changed('td.position-display' + posid, rez.current_step.toString());
+        $('td.position-display' + posid).attr('title', 'Осталось шагов: ' + rez.steps_left);
         update_text_if_changed('td.start-freq-display' + posid, round_to_2_digits(rez.initial_freq));
-        update_text_if_changed('td.status-display' + posid, rez.state);
+        update_text_if_changed('td.status-display' + posid, channel_state_text(rez));
+        $('td.status-display' + posid).attr('title', (rez.events || []).map(adjust_event_text).join('\n'));
 
         draw_progress(rez.initial_freq, rez.current_freq, $('td.frequency-display' + posid));
+        $('td.frequency-display' + posid).attr('title', [sealing_text(rez), drift_text(rez.drift)]
+            .filter((t) => t !== '').join('\n'));
     }
 }
 
+// Уход частоты при герметизации и ожидаемая частота в корпусе
+function sealing_text(rez: IRezInfo): string {
+    if (rez.sealing_shift_hz === undefined) {
+        return '';
+    }
+
+    const shift = rez.sealing_shift_hz;
+    return 'Уход при герметизации: ' + (shift >= 0 ? '+' : '') + round_to_2_digits(shift) + ' Гц\n'
+        + 'В корпусе: ≈' + round_to_2_digits(rez.current_freq + shift) + ' Гц';
+}
+
+// Настроенная и проверенная частоты канала
+function drift_text(drift?: IChannelDrift): string {
+    if (drift === undefined) {
+        return '';
+    }
+    if (drift.samples.length == 0) {
+        return 'Настроено: ' + round_to_2_digits(drift.trimmed_freq) + ' Гц\n'
+            + 'Контрольное измерение не удалось';
+    }
+
+    const verified = drift.samples[drift.samples.length - 1][1];
+    const delta = verified - drift.trimmed_freq;
+    return 'Настроено: ' + round_to_2_digits(drift.trimmed_freq) + ' Гц\n'
+        + 'Проверено: ' + round_to_2_digits(verified) + ' Гц (Δ' + (delta >= 0 ? '+' : '') + round_to_2_digits(delta) + ')';
+}
+
 function start_autoadjust_updater() {
     updater = oboe('/auto_status')
         .done((report: IAutoAdjustStatusReport | IControlResult) => {
             if ((<IControlResult>report).success == undefined) {
                 const rep = report as IAutoAdjustStatusReport;
-                update_autoadjust(rep.report, rep.progress_string);
+                update_autoadjust(rep.report);
                 if (rep.reset_marker) {
                     setTimeout(() => start_autoadjust_updater(), 0)
                 }
@@ -198,7 +481,7 @@ function reset_gui() {
     update_autoadjust({
         status: "Idle",
         rezonator_info: []
-    }, "Ожидание");
+    });
 }
 
 function draw_progress(inital_freq: number, current_freq: number, cell: JQuery<HTMLElement>) {
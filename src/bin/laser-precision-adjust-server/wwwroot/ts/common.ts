@@ -66,4 +66,97 @@ function patch_value(target: HTMLInputElement, field_name: string) {
             noty_error(e.responseText || e.statusText);
         }
     });
-}
\ No newline at end of file
+}
+// ---------------------------------------------------------------------------------------------
+// События автонастройки (laser_precision_adjust::adjust_event::AdjustEvent)
+
+type AdjustStage = "EdgeSearch" | "FastForward" | "Precision" | "Done";
+type BanReason = "StepLimit" | "TooLow" | "TooHigh";
+
+interface IBoxPlot {
+    median: number,
+    q1: number,
+    q3: number,
+    iqr: number,
+    lower_bound: number,
+    upper_bound: number,
+}
+
+interface IAdjustEventVariants {
+    Started?: { channel: number, freq: number },
+    Probe?: { step: number },
+    EdgeFound?: { step: number, freq: number },
+    StageDone?: { stage: AdjustStage, freq: number, steps: number },
+    Burn?: { steps: number, power: number, soft: boolean },
+    Measured?: { freq: number, interval?: [number, number], box_plot?: IBoxPlot },
+    Verify?: { freq: number },
+    Ban?: { reason: BanReason, freq: number },
+    Finished?: { path: number[], freq: number, ppm: number, steps: number },
+    Error?: { message: string },
+}
+
+type AdjustEvent = "Cooling" | IAdjustEventVariants;
+
+function adjust_stage_text(stage: AdjustStage): string {
+    switch (stage) {
+        case "EdgeSearch": return "Поиск края";
+        case "FastForward": return "Грубая настройка";
+        case "Precision": return "Точная настройка";
+        case "Done": return "Конец";
+    }
+}
+
+function ban_reason_text(reason: BanReason): string {
+    switch (reason) {
+        case "StepLimit": return "Достигнут лимит шагов";
+        case "TooLow": return "Частота ниже минимально-допустимой";
+        case "TooHigh": return "Частота выше максимально-допустимой";
+    }
+}
+
+function adjust_event_text(event: AdjustEvent): string {
+    if (event === "Cooling") {
+        return "Ожидание охлаждения";
+    }
+
+    const f = (freq: number) => round_to_2_digits(freq) + " Гц";
+
+    if (event.Started !== undefined) {
+        return "Канал " + event.Started.channel + ": поиск края, " + f(event.Started.freq);
+    } else if (event.Probe !== undefined) {
+        return "Ожидание реакции на шаге " + event.Probe.step;
+    } else if (event.EdgeFound !== undefined) {
+        return "Реакция обнаружена на шаге " + event.EdgeFound.step + ": " + f(event.EdgeFound.freq);
+    } else if (event.StageDone !== undefined) {
+        const s = event.StageDone;
+        return adjust_stage_text(s.stage) + ": -> " + f(s.freq) + " (" + s.steps + " шагов)";
+    } else if (event.Burn !== undefined) {
+        const b = event.Burn;
+        return "Прожиг " + b.steps + " шагов" + (b.power !== 1.0 ? " (мощность x" + b.power + ")" : "");
+    } else if (event.Measured !== undefined) {
+        const m = event.Measured;
+        return "Текущая частота: ~" + f(m.freq)
+            + (m.interval !== undefined
+                ? " [" + round_to_2_digits(m.interval[0]) + " - " + round_to_2_digits(m.interval[1]) + "]"
+                : "");
+    } else if (event.Verify !== undefined) {
+        return "Проверка: " + f(event.Verify.freq);
+    } else if (event.Ban !== undefined) {
+        return ban_reason_text(event.Ban.reason) + " (" + f(event.Ban.freq) + ")";
+    } else if (event.Finished !== undefined) {
+        const r = event.Finished;
+        const ppm = (r.ppm >= 0 ? "+" : "") + r.ppm.toFixed(1);
+        return "Настройка завершена: " + r.path.map(round_to_2_digits).join(" -> ")
+            + " Гц (" + ppm + " ppm) за " + r.steps + " шагов";
+    } else if (event.Error !== undefined) {
+        return event.Error.message;
+    }
+    return JSON.stringify(event);
+}
+
+// Событие последнее в настройке канала, см. AdjustEvent::is_final()
+function adjust_event_is_final(event: AdjustEvent): boolean {
+    return event !== "Cooling" && (event.Finished !== undefined
+        || event.Error !== undefined
+        || (event.Ban !== undefined && event.Ban.reason !== "StepLimit"));
+}
@@ -20,10 +20,17 @@ interface ILimits {
     Target: number,
 }
 
+interface IShotMismatch {
+    timestamp: number,
+    channel: number,
+    kind: 'MarkOnly' | 'DetectedOnly',
+}
+
 interface IAdjustReport {
     DisplayFragments: IDisplayFragment[],
     Hystogramm: IHystogramFragment[],
     Limits: ILimits,
+    ShotMismatches: IShotMismatch[],
 }
 
 
@@ -76,10 +83,24 @@ function select_row_table(rez: number): void {
         success: (data: IAdjustReport) => {
             plot_history(data.DisplayFragments, data.Limits);
             plot_hystogramm(data.Hystogramm);
+            display_shot_mismatches(data.ShotMismatches);
         }
     })
 }
 
+function display_shot_mismatches(mismatches: IShotMismatch[]) {
+    const element = $('#shot-mismatches');
+    if (mismatches.length == 0) {
+        element.hide();
+        return;
+    }
+
+    const mark_only = mismatches.filter((m) => m.kind == 'MarkOnly').length;
+    const detected_only = mismatches.length - mark_only;
+    element.text('Несовпавших выстрелов: без реакции - ' + mark_only
+        + ', без метки - ' + detected_only).show();
+}
+
 function plot_history(fragments: IDisplayFragment[], limits: ILimits) {
     const total_points = fragments.reduce((a, f) => a + f.points.length, 0);
 
@@ -36,6 +36,7 @@ interface IControlResult {
     success: boolean,
     error?: string,
     message?: string,
+    event?: AdjustEvent,
 }
 
 // ---------------------------------------------------------------------------------------------
@@ -143,18 +144,30 @@ $(() => {
             method: 'POST',
             body: {}
         }).done((state: IControlResult) => {
-            if (state.success) {
-                console.log(state.message);
-
-                if ((state.message.startsWith('Настройка завершена') || state.message.startsWith('Настройка отменена')) && present_noty !== null) {
+            const text = state.event !== undefined ? adjust_event_text(state.event) : state.message;
+            if (state.success && state.event !== undefined && adjust_event_is_final(state.event)
+                && (state.event as IAdjustEventVariants).Ban !== undefined) {
+                // канал настроить невозможно
+                if (present_noty !== null) {
+                    present_noty.close();
+                    present_noty = null;
+                }
+                noty_error(text);
+            } else if (state.success) {
+                console.log(text);
+
+                const finished = state.event !== undefined
+                    ? adjust_event_is_final(state.event)
+                    : state.message.startsWith('Настройка отменена');
+                if (finished && present_noty !== null) {
                     present_noty.close();
                     present_noty = null;
                 } else if (present_noty !== null) {
-                    present_noty.setText("<i class='fas fa-spinner fa-pulse'></i> " + state.message);
+                    present_noty.setText("<i class='fas fa-spinner fa-pulse'></i> " + text);
                 } else {
                     present_noty = noty({
                         type: "information",
-                        text: "<i class='fas fa-spinner fa-pulse'></i> " + state.message,
+                        text: "<i class='fas fa-spinner fa-pulse'></i> " + text,
                     });
                 }
             } else {
@@ -1,10 +1,89 @@
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
+use laser_precision_adjust::predict::CooldownModelKind;
+
 /// Try to approximate fragments of a file
 #[derive(Parser)]
 #[clap(version)]
 pub struct Cli {
-    /// The json-file with captured by `laser-precision-adjust-server`
-    pub json_file: PathBuf,
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Refit the single exponential and print it next to the stored coefficients
+    Refit {
+        /// The json-file with captured by `laser-precision-adjust-server`
+        json_file: PathBuf,
+    },
+
+    /// Fit candidate models to every fragment and rank them by AIC
+    Compare {
+        /// The json-file with captured by `laser-precision-adjust-server`
+        json_file: PathBuf,
+
+        /// Models to fit
+        #[clap(short, long, value_enum, value_delimiter = ',', default_values_t = Model::all())]
+        models: Vec<Model>,
+
+        /// Output format
+        #[clap(short, long, value_enum, default_value_t = Format::Csv)]
+        format: Format,
+
+        /// Output file (default: stdout)
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Render SVG plots of raw points, filtered series and fits for every fragment
+    Plot {
+        /// The json-file with captured by `laser-precision-adjust-server`
+        json_file: PathBuf,
+
+        /// Models to fit
+        #[clap(short, long, value_enum, value_delimiter = ',', default_values_t = Model::all())]
+        models: Vec<Model>,
+
+        /// Directory to write SVG files to
+        #[clap(short, long, default_value = ".")]
+        out_dir: PathBuf,
+
+        /// Plot only this channel
+        #[clap(short, long)]
+        channel: Option<usize>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Model {
+    /// y = a * (1 - exp(-x * b))
+    Exp,
+    /// y = a1 * (1 - exp(-x * b1)) + a2 * (1 - exp(-x * b2))
+    DoubleExp,
+    /// y = a * (1 - exp(-x * b)) + c * x
+    ExpLinear,
+}
+
+impl Model {
+    fn all() -> Vec<Model> {
+        vec![Model::Exp, Model::DoubleExp, Model::ExpLinear]
+    }
+}
+
+impl From<Model> for CooldownModelKind {
+    fn from(value: Model) -> Self {
+        match value {
+            Model::Exp => CooldownModelKind::Exp,
+            Model::DoubleExp => CooldownModelKind::DoubleExp,
+            Model::ExpLinear => CooldownModelKind::ExpLinear,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Csv,
+    Json,
 }
@@ -1,19 +1,164 @@
 mod cli;
+mod plot;
+
+use std::{io::Write, path::Path};
 
 use clap::Parser;
+use serde::Serialize;
 
-use laser_precision_adjust::predict::{aproximate_exp, find_min, Fragment, NORMAL_T};
+use laser_precision_adjust::predict::{
+    aproximate_exp, find_min, fit_cooldown_model, smooth_filter, CooldownFit, CooldownModelKind,
+    Fragment, NORMAL_T,
+};
 use laser_precision_adjust::IDataPoint;
 
+/// Фрагмент, подготовленный к аппроксимации
+struct Prepared {
+    t: Vec<f64>,
+    raw: Vec<f64>,
+    filtered: Vec<f64>,
+    min_index: usize,
+    min_f: f64,
+}
+
+impl Prepared {
+    fn new(fragment: &Fragment<f64>) -> Option<Self> {
+        let t = fragment.points().iter().map(|p| p.x()).collect::<Vec<_>>();
+        let raw = fragment.points().iter().map(|p| p.y()).collect::<Vec<_>>();
+        let filtered = smooth_filter(&t, &raw).ok()?;
+        let (min_index, min_f) = find_min(&filtered)?;
+
+        Some(Self {
+            t,
+            raw,
+            filtered,
+            min_index,
+            min_f,
+        })
+    }
+
+    fn t_zero(&self) -> f64 {
+        self.t[self.min_index]
+    }
+
+    /// Точки от минимума, смещенные в (0, 0), время в единицах NORMAL_T
+    fn normalized(&self) -> (Vec<f64>, Vec<f64>) {
+        let t_zero = self.t_zero();
+        let min_f = self.min_f;
+        (
+            self.t[self.min_index..]
+                .iter()
+                .map(move |t| (*t - t_zero) / NORMAL_T)
+                .collect(),
+            self.filtered[self.min_index..]
+                .iter()
+                .map(move |f| *f - min_f)
+                .collect(),
+        )
+    }
+
+    /// Все модели, отсортированные по возрастанию AIC
+    fn fit(&self, models: &[CooldownModelKind]) -> Vec<CooldownFit<f64>> {
+        let (tz, fz) = self.normalized();
+        let mut fits = models
+            .iter()
+            .filter_map(|kind| fit_cooldown_model(*kind, tz.clone(), &fz).ok())
+            .collect::<Vec<_>>();
+        fits.sort_by(|a, b| a.aic.partial_cmp(&b.aic).unwrap_or(std::cmp::Ordering::Equal));
+        fits
+    }
+
+    /// Кривая модели в исходных координатах
+    fn curve(&self, fit: &CooldownFit<f64>) -> Vec<(f64, f64)> {
+        const CURVE_POINTS: usize = 100;
+
+        let t_zero = self.t_zero();
+        let t_end = (self.t.last().unwrap() - t_zero) / NORMAL_T;
+        let x = nalgebra::DVector::<f64>::from_iterator(
+            CURVE_POINTS,
+            (0..CURVE_POINTS).map(|i| t_end * i as f64 / (CURVE_POINTS - 1) as f64),
+        );
+        let y = fit.model.evaluate(&x);
+        x.iter()
+            .zip(y.iter())
+            .map(|(x, y)| (t_zero + x * NORMAL_T, self.min_f + y))
+            .collect()
+    }
+}
+
+#[derive(Serialize)]
+struct FitRecord {
+    channel: usize,
+    fragment: usize,
+    start_timestamp: f64,
+    model: String,
+    rank: usize,
+    rss: f64,
+    aic: f64,
+    asymptote: f64,
+    target: f64,
+    score: Option<f64>,
+    asymptote_se: Option<f64>,
+    coeffs: Vec<f64>,
+}
+
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<(), std::io::Error> {
     let cmd = cli::Cli::parse();
 
-    let file = std::fs::File::open(cmd.json_file)?;
+    match cmd.command {
+        cli::Command::Refit { json_file } => refit(&load(&json_file)?),
+        cli::Command::Compare {
+            json_file,
+            models,
+            format,
+            output,
+        } => {
+            let models = models.into_iter().map(Into::into).collect::<Vec<_>>();
+            let records = compare(&load(&json_file)?, &models);
+
+            let mut out: Box<dyn Write> = if let Some(output) = output {
+                Box::new(std::fs::File::create(output)?)
+            } else {
+                Box::new(std::io::stdout())
+            };
+
+            match format {
+                cli::Format::Csv => write_csv(&mut out, &records)?,
+                cli::Format::Json => serde_json::to_writer_pretty(&mut out, &records)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?,
+            }
+
+            // сводка: сколько раз каждая модель оказалась лучшей
+            for kind in models {
+                let wins = records
+                    .iter()
+                    .filter(|r| r.rank == 1 && r.model == kind.to_string())
+                    .count();
+                eprintln!("{kind}: best for {wins} fragments");
+            }
+            Ok(())
+        }
+        cli::Command::Plot {
+            json_file,
+            models,
+            out_dir,
+            channel,
+        } => {
+            let models = models.into_iter().map(Into::into).collect::<Vec<_>>();
+            plot_all(&load(&json_file)?, &models, &out_dir, channel)
+        }
+    }
+}
+
+fn load(json_file: &Path) -> Result<Vec<Vec<Fragment<f64>>>, std::io::Error> {
+    let file = std::fs::File::open(json_file)?;
 
-    let data = serde_json::from_reader::<_, Vec<Vec<Fragment<f64>>>>(file)
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+    serde_json::from_reader::<_, Vec<Vec<Fragment<f64>>>>(file)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+}
 
+fn refit(data: &[Vec<Fragment<f64>>]) -> Result<(), std::io::Error> {
     for (ch_num, shots) in data.iter().enumerate() {
         println!("\nChannel #{}:", ch_num);
         for shot in shots {
@@ -33,11 +178,15 @@ async fn main() -> Result<(), std::io::Error> {
                     .collect::<Vec<_>>();
 
                 if let Ok(coeffs) = aproximate_exp(tz, &fz) {
-                    let orig_coeffs = shot.aprox_coeffs().unwrap();
-                    println!(
-                        "Coeffs:;{};{};;{};{}",
-                        coeffs.0, coeffs.1, orig_coeffs.0, orig_coeffs.1
-                    );
+                    let orig_coeffs = shot
+                        .model()
+                        .map(|m| m.coeffs())
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|c| c.to_string())
+                        .collect::<Vec<_>>()
+                        .join(";");
+                    println!("Coeffs:;{};{};;{}", coeffs.0, coeffs.1, orig_coeffs);
                     let new_fragment = Fragment::new(
                         shot.start_timestamp() as u128,
                         &data,
@@ -59,3 +208,127 @@ async fn main() -> Result<(), std::io::Error> {
 
     Ok(())
 }
+
+fn compare(data: &[Vec<Fragment<f64>>], models: &[CooldownModelKind]) -> Vec<FitRecord> {
+    let mut records = vec![];
+    for (channel, shots) in data.iter().enumerate() {
+        for (fragment, shot) in shots.iter().enumerate() {
+            if let Some(prepared) = Prepared::new(shot) {
+                for (i, fit) in prepared.fit(models).iter().enumerate() {
+                    records.push(FitRecord {
+                        channel,
+                        fragment,
+                        start_timestamp: shot.start_timestamp(),
+                        model: fit.model.kind().to_string(),
+                        rank: i + 1,
+                        rss: fit.rss,
+                        aic: fit.aic,
+                        asymptote: fit.model.asymptote(),
+                        target: prepared.min_f + fit.model.asymptote(),
+                        score: fit.quality.as_ref().map(|q| q.score),
+                        asymptote_se: fit.quality.as_ref().map(|q| q.asymptote_se),
+                        coeffs: fit.model.coeffs(),
+                    });
+                }
+            } else {
+                eprintln!("Channel {channel} fragment {fragment}: filter failed, skipped");
+            }
+        }
+    }
+    records
+}
+
+fn write_csv(out: &mut impl Write, records: &[FitRecord]) -> Result<(), std::io::Error> {
+    writeln!(
+        out,
+        "channel,fragment,start_timestamp,model,rank,rss,aic,asymptote,target,score,asymptote_se,c0,c1,c2,c3"
+    )?;
+    let opt = |v: Option<f64>| v.map(|v| v.to_string()).unwrap_or_default();
+    for r in records {
+        let mut coeffs = r.coeffs.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        coeffs.resize(4, String::new());
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{},{},{},{}",
+            r.channel,
+            r.fragment,
+            r.start_timestamp,
+            r.model,
+            r.rank,
+            r.rss,
+            r.aic,
+            r.asymptote,
+            r.target,
+            opt(r.score),
+            opt(r.asymptote_se),
+            coeffs.join(",")
+        )?;
+    }
+    Ok(())
+}
+
+fn plot_all(
+    data: &[Vec<Fragment<f64>>],
+    models: &[CooldownModelKind],
+    out_dir: &Path,
+    channel: Option<usize>,
+) -> Result<(), std::io::Error> {
+    const FIT_COLORS: [&str; 3] = ["#d62728", "#2ca02c", "#9467bd"];
+
+    std::fs::create_dir_all(out_dir)?;
+
+    for (ch_num, shots) in data.iter().enumerate() {
+        if channel.is_some_and(|c| c != ch_num) {
+            continue;
+        }
+
+        for (i, shot) in shots.iter().enumerate() {
+            let Some(prepared) = Prepared::new(shot) else {
+                eprintln!("Channel {ch_num} fragment {i}: filter failed, skipped");
+                continue;
+            };
+
+            let mut series = vec![
+                plot::Series {
+                    name: "raw".to_owned(),
+                    color: "#7f7f7f",
+                    points: prepared
+                        .t
+                        .iter()
+                        .copied()
+                        .zip(prepared.raw.iter().copied())
+                        .collect(),
+                    dots: true,
+                },
+                plot::Series {
+                    name: "filtered".to_owned(),
+                    color: "#1f77b4",
+                    points: prepared
+                        .t
+                        .iter()
+                        .copied()
+                        .zip(prepared.filtered.iter().copied())
+                        .collect(),
+                    dots: false,
+                },
+            ];
+
+            for (fit, color) in prepared.fit(models).iter().zip(FIT_COLORS.iter().cycle()) {
+                series.push(plot::Series {
+                    name: format!("{} (AIC={:.1})", fit.model.kind(), fit.aic),
+                    color,
+                    points: prepared.curve(fit),
+                    dots: false,
+                });
+            }
+
+            let path = out_dir.join(format!("ch{ch_num:02}_fragment{i:03}.svg"));
+            std::fs::write(
+                &path,
+                plot::render(&format!("Channel {ch_num}, fragment {i}"), &series),
+            )?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use std::fmt::Write;
+
+const WIDTH: f64 = 800.0;
+const HEIGHT: f64 = 450.0;
+const MARGIN: f64 = 60.0;
+const TICKS: usize = 5;
+
+/// Серия данных для отрисовки
+pub struct Series {
+    pub name: String,
+    pub color: &'static str,
+    pub points: Vec<(f64, f64)>,
+    /// Рисовать точками, а не линией
+    pub dots: bool,
+}
+
+/// Простейший SVG-график нескольких серий в общих осях
+pub fn render(title: &str, series: &[Series]) -> String {
+    let finite = series
+        .iter()
+        .flat_map(|s| s.points.iter())
+        .filter(|(x, y)| x.is_finite() && y.is_finite());
+    let (mut x_min, mut x_max, mut y_min, mut y_max) = finite.fold(
+        (f64::MAX, f64::MIN, f64::MAX, f64::MIN),
+        |(x_min, x_max, y_min, y_max), (x, y)| {
+            (x_min.min(*x), x_max.max(*x), y_min.min(*y), y_max.max(*y))
+        },
+    );
+    if x_min > x_max {
+        (x_min, x_max, y_min, y_max) = (0.0, 1.0, 0.0, 1.0);
+    }
+    if (x_max - x_min).abs() < f64::EPSILON {
+        x_max = x_min + 1.0;
+    }
+    if (y_max - y_min).abs() < f64::EPSILON {
+        y_max = y_min + 1.0;
+    }
+
+    let sx = |x: f64| MARGIN + (x - x_min) / (x_max - x_min) * (WIDTH - 2.0 * MARGIN);
+    let sy = |y: f64| HEIGHT - MARGIN - (y - y_min) / (y_max - y_min) * (HEIGHT - 2.0 * MARGIN);
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" font-family="sans-serif" font-size="11">"#
+    )
+    .ok();
+    writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#).ok();
+    writeln!(
+        svg,
+        r#"<text x="{}" y="20" text-anchor="middle" font-size="14">{title}</text>"#,
+        WIDTH / 2.0
+    )
+    .ok();
+    writeln!(
+        svg,
+        r#"<rect x="{MARGIN}" y="{MARGIN}" width="{}" height="{}" fill="none" stroke="black"/>"#,
+        WIDTH - 2.0 * MARGIN,
+        HEIGHT - 2.0 * MARGIN
+    )
+    .ok();
+
+    // оси
+    for i in 0..=TICKS {
+        let k = i as f64 / TICKS as f64;
+        let x = x_min + (x_max - x_min) * k;
+        let y = y_min + (y_max - y_min) * k;
+        writeln!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{:.0}</text>"#,
+            sx(x),
+            HEIGHT - MARGIN + 15.0,
+            x
+        )
+        .ok();
+        writeln!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="end">{:.2}</text>"#,
+            MARGIN - 5.0,
+            sy(y) + 4.0,
+            y
+        )
+        .ok();
+        writeln!(
+            svg,
+            r##"<line x1="{MARGIN}" y1="{0:.1}" x2="{1:.1}" y2="{0:.1}" stroke="#ddd"/>"##,
+            sy(y),
+            WIDTH - MARGIN
+        )
+        .ok();
+    }
+
+    for (i, s) in series.iter().enumerate() {
+        let points = s
+            .points
+            .iter()
+            .filter(|(x, y)| x.is_finite() && y.is_finite())
+            .map(|(x, y)| (sx(*x), sy(*y)));
+        if s.dots {
+            for (x, y) in points {
+                writeln!(
+                    svg,
+                    r#"<circle cx="{x:.1}" cy="{y:.1}" r="2" fill="{}"/>"#,
+                    s.color
+                )
+                .ok();
+            }
+        } else {
+            let path = points
+                .map(|(x, y)| format!("{x:.1},{y:.1}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(
+                svg,
+                r#"<polyline points="{path}" fill="none" stroke="{}" stroke-width="1.5"/>"#,
+                s.color
+            )
+            .ok();
+        }
+
+        // легенда
+        let ly = MARGIN + 15.0 + i as f64 * 15.0;
+        writeln!(
+            svg,
+            r#"<rect x="{}" y="{}" width="10" height="10" fill="{}"/><text x="{}" y="{}">{}</text>"#,
+            MARGIN + 10.0,
+            ly - 9.0,
+            s.color,
+            MARGIN + 25.0,
+            ly,
+            s.name
+        )
+        .ok();
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
@@ -1,7 +1,12 @@
+use std::collections::VecDeque;
+
 use num_traits::{Float, FromPrimitive, NumOps};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+/// Множитель межквартильного размаха для усов по умолчанию
+pub const DEFAULT_WHISKER: f64 = 2.0;
 
-#[derive(Clone, Copy, Debug, Serialize)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct BoxPlot<T: Serialize> {
     median: T,
     q1: T,
@@ -11,26 +16,104 @@ pub struct BoxPlot<T: Serialize> {
     upper_bound: T,
 }
 
+/// Способ вычисления квантиля по выборке
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuantileMethod {
+    /// Элемент с индексом floor(len * q) без интерполяции (исторический вариант)
+    #[default]
+    Nearest,
+    /// Линейная интерполяция между (len - 1) * q (R-7, numpy "linear", Excel QUARTILE.INC)
+    Linear,
+    /// Линейная интерполяция между len * q - 0.5 (R-5, Hazen)
+    Hazen,
+}
+
+/// Квантиль q (0..1) по отсортированной выборке без NaN
+pub fn quantile_sorted<T: Float + FromPrimitive>(
+    sorted: &[T],
+    q: f64,
+    method: QuantileMethod,
+) -> T {
+    let len = sorted.len();
+    if len == 0 {
+        return T::nan();
+    }
+
+    let interpolate = |pos: f64| {
+        let pos = pos.clamp(0.0, (len - 1) as f64);
+        let lo = pos.floor() as usize;
+        let hi = pos.ceil() as usize;
+        let frac = unsafe { T::from_f64(pos - lo as f64).unwrap_unchecked() };
+        sorted[lo] + (sorted[hi] - sorted[lo]) * frac
+    };
+
+    match method {
+        QuantileMethod::Nearest => sorted[((len as f64 * q) as usize).min(len - 1)],
+        QuantileMethod::Linear => interpolate((len - 1) as f64 * q),
+        QuantileMethod::Hazen => interpolate(len as f64 * q - 0.5),
+    }
+}
+
+/// Параметры BoxPlot окон измерения и отображения
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct BoxPlotConfig {
+    #[serde(rename = "Method", default)]
+    pub method: QuantileMethod,
+
+    /// Множитель межквартильного размаха для усов
+    #[serde(rename = "Whisker", default = "default_whisker")]
+    pub whisker: f64,
+}
+
+fn default_whisker() -> f64 {
+    DEFAULT_WHISKER
+}
+
+impl Default for BoxPlotConfig {
+    fn default() -> Self {
+        Self {
+            method: QuantileMethod::default(),
+            whisker: DEFAULT_WHISKER,
+        }
+    }
+}
+
+impl BoxPlotConfig {
+    pub fn box_plot<T>(&self, series: &[T]) -> BoxPlot<T>
+    where
+        T: Float + NumOps + FromPrimitive + Copy + Serialize,
+    {
+        BoxPlot::with_params(series, self.method, self.whisker)
+    }
+
+    pub fn rolling<T>(&self, capacity: usize) -> RollingBoxPlot<T>
+    where
+        T: Float + NumOps + FromPrimitive + Copy + Serialize,
+    {
+        RollingBoxPlot::with_params(capacity, self.method, self.whisker)
+    }
+}
+
 // Функция вычисления медианы вектора и квартилей 25% и 75%
-fn median_q1q3<T>(series: &[T]) -> (T, T, T)
+fn median_q1q3<T>(series: &[T], method: QuantileMethod) -> (T, T, T)
 where
-    T: Float + Copy,
+    T: Float + FromPrimitive + Copy,
 {
     let mut sorted_series = series
         .into_iter()
         .filter(|v| !v.is_nan())
         .copied()
         .collect::<Vec<_>>();
-    if sorted_series.is_empty() {
-        (T::nan(), T::nan(), T::nan())
-    } else {
-        sorted_series.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        let len = sorted_series.len();
-        let q1 = sorted_series[len / 4];
-        let q3 = sorted_series[len * 3 / 4];
-        let median = sorted_series[len / 2];
-        (median, q1, q3)
-    }
+    sorted_series.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    median_q1q3_sorted(&sorted_series, method)
+}
+
+fn median_q1q3_sorted<T: Float + FromPrimitive>(sorted: &[T], method: QuantileMethod) -> (T, T, T) {
+    (
+        quantile_sorted(sorted, 0.5, method),
+        quantile_sorted(sorted, 0.25, method),
+        quantile_sorted(sorted, 0.75, method),
+    )
 }
 
 #[allow(unused)]
@@ -40,12 +123,27 @@ where
     T: Serialize,
 {
     pub fn new(series: &[T]) -> Self {
-        let poltora = unsafe { T::from_f64(2.0).unwrap_unchecked() };
+        Self::with_params(series, QuantileMethod::default(), DEFAULT_WHISKER)
+    }
+
+    /// BoxPlot с выбранным способом вычисления квартилей и множителем усов
+    pub fn with_params(series: &[T], method: QuantileMethod, whisker: f64) -> Self {
+        let (median, q1, q3) = median_q1q3(series, method);
+        Self::from_quartiles(median, q1, q3, whisker)
+    }
+
+    /// BoxPlot по уже отсортированной выборке без NaN, без повторной сортировки
+    pub fn from_sorted(sorted: &[T], method: QuantileMethod, whisker: f64) -> Self {
+        let (median, q1, q3) = median_q1q3_sorted(sorted, method);
+        Self::from_quartiles(median, q1, q3, whisker)
+    }
+
+    pub fn from_quartiles(median: T, q1: T, q3: T, whisker: f64) -> Self {
+        let whisker = unsafe { T::from_f64(whisker).unwrap_unchecked() };
 
-        let (median, q1, q3) = median_q1q3(series);
         let iqr = q3 - q1;
-        let lower_bound = q1 - poltora * iqr;
-        let upper_bound = q3 + poltora * iqr;
+        let lower_bound = q1 - whisker * iqr;
+        let upper_bound = q3 + whisker * iqr;
         Self {
             median,
             q1,
@@ -95,3 +193,329 @@ where
         }
     }
 }
+
+/// BoxPlot по скользящему окну из последних `capacity` значений
+/// Окно хранится отсортированным, добавление значения - O(capacity) без полной сортировки
+#[derive(Clone, Debug)]
+pub struct RollingBoxPlot<T> {
+    capacity: usize,
+    order: VecDeque<T>,
+    sorted: Vec<T>,
+    method: QuantileMethod,
+    whisker: f64,
+}
+
+impl<T> RollingBoxPlot<T>
+where
+    T: Float + NumOps + FromPrimitive + Copy + Serialize,
+{
+    pub fn new(capacity: usize) -> Self {
+        Self::with_params(capacity, QuantileMethod::default(), DEFAULT_WHISKER)
+    }
+
+    /// Окно нулевого размера не вытесняло бы значения, capacity ограничивается снизу единицей
+    pub fn with_params(capacity: usize, method: QuantileMethod, whisker: f64) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            order: VecDeque::with_capacity(capacity + 1),
+            sorted: Vec::with_capacity(capacity + 1),
+            method,
+            whisker,
+        }
+    }
+
+    /// Добавить значение, при заполненном окне вытесняется самое старое. NaN игнорируются
+    pub fn push(&mut self, v: T) {
+        if v.is_nan() {
+            return;
+        }
+
+        if self.order.len() == self.capacity {
+            if let Some(old) = self.order.pop_front() {
+                let pos = self.position(old);
+                self.sorted.remove(pos);
+            }
+        }
+
+        let pos = self.position(v);
+        self.sorted.insert(pos, v);
+        self.order.push_back(v);
+    }
+
+    pub fn clear(&mut self) {
+        self.order.clear();
+        self.sorted.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.order.len() == self.capacity
+    }
+
+    pub fn quantile(&self, q: f64) -> T {
+        quantile_sorted(&self.sorted, q, self.method)
+    }
+
+    pub fn box_plot(&self) -> BoxPlot<T> {
+        BoxPlot::from_sorted(&self.sorted, self.method, self.whisker)
+    }
+
+    fn position(&self, v: T) -> usize {
+        self.sorted.partition_point(|x| *x < v)
+    }
+}
+
+/// Потоковая оценка квантиля алгоритмом P² (Jain, Chlamtac, 1985) без хранения выборки
+#[derive(Clone, Debug)]
+pub struct P2Quantile<T> {
+    p: T,
+    count: usize,
+    /// высоты маркеров
+    q: [T; 5],
+    /// позиции маркеров
+    n: [T; 5],
+    /// желаемые позиции маркеров
+    np: [T; 5],
+    /// приращения желаемых позиций
+    dn: [T; 5],
+}
+
+impl<T: Float + FromPrimitive> P2Quantile<T> {
+    pub fn new(p: f64) -> Self {
+        let c = |v: f64| unsafe { T::from_f64(v).unwrap_unchecked() };
+        Self {
+            p: c(p),
+            count: 0,
+            q: [T::zero(); 5],
+            n: [c(0.0), c(1.0), c(2.0), c(3.0), c(4.0)],
+            np: [c(0.0), c(2.0 * p), c(4.0 * p), c(2.0 + 2.0 * p), c(4.0)],
+            dn: [c(0.0), c(p / 2.0), c(p), c((1.0 + p) / 2.0), c(1.0)],
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn push(&mut self, x: T) {
+        if x.is_nan() {
+            return;
+        }
+
+        if self.count < 5 {
+            self.q[self.count] = x;
+            self.count += 1;
+            if self.count == 5 {
+                self.q.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            }
+            return;
+        }
+        self.count += 1;
+
+        // ячейка, в которую попало значение
+        let k = if x < self.q[0] {
+            self.q[0] = x;
+            0
+        } else if x >= self.q[4] {
+            self.q[4] = x;
+            3
+        } else {
+            (1..5).find(|i| x < self.q[*i]).unwrap() - 1
+        };
+
+        self.n
+            .iter_mut()
+            .skip(k + 1)
+            .for_each(|n| *n = *n + T::one());
+        self.np
+            .iter_mut()
+            .zip(self.dn.iter())
+            .for_each(|(np, dn)| *np = *np + *dn);
+
+        // коррекция средних маркеров
+        for i in 1..4 {
+            let d = self.np[i] - self.n[i];
+            if (d >= T::one() && self.n[i + 1] - self.n[i] > T::one())
+                || (d <= -T::one() && self.n[i - 1] - self.n[i] < -T::one())
+            {
+                let d = d.signum();
+                let qp = self.parabolic(i, d);
+                self.q[i] = if self.q[i - 1] < qp && qp < self.q[i + 1] {
+                    qp
+                } else {
+                    self.linear(i, d)
+                };
+                self.n[i] = self.n[i] + d;
+            }
+        }
+    }
+
+    /// Текущая оценка квантиля
+    pub fn value(&self) -> T {
+        if self.count >= 5 {
+            self.q[2]
+        } else {
+            let mut sorted = self.q[..self.count].to_vec();
+            sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            quantile_sorted(
+                &sorted,
+                self.p.to_f64().unwrap_or(0.5),
+                QuantileMethod::Linear,
+            )
+        }
+    }
+
+    fn parabolic(&self, i: usize, d: T) -> T {
+        let (q, n) = (&self.q, &self.n);
+        q[i] + d / (n[i + 1] - n[i - 1])
+            * ((n[i] - n[i - 1] + d) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                + (n[i + 1] - n[i] - d) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]))
+    }
+
+    fn linear(&self, i: usize, d: T) -> T {
+        let j = if d > T::zero() { i + 1 } else { i - 1 };
+        self.q[i] + d * (self.q[j] - self.q[i]) / (self.n[j] - self.n[i])
+    }
+}
+
+/// Потоковый BoxPlot: квартили оцениваются P², память не зависит от длины ряда
+#[derive(Clone, Debug)]
+pub struct StreamingBoxPlot<T> {
+    q1: P2Quantile<T>,
+    median: P2Quantile<T>,
+    q3: P2Quantile<T>,
+    whisker: f64,
+}
+
+impl<T> StreamingBoxPlot<T>
+where
+    T: Float + NumOps + FromPrimitive + Copy + Serialize,
+{
+    pub fn new(whisker: f64) -> Self {
+        Self {
+            q1: P2Quantile::new(0.25),
+            median: P2Quantile::new(0.5),
+            q3: P2Quantile::new(0.75),
+            whisker,
+        }
+    }
+
+    pub fn push(&mut self, v: T) {
+        self.q1.push(v);
+        self.median.push(v);
+        self.q3.push(v);
+    }
+
+    pub fn count(&self) -> usize {
+        self.median.count()
+    }
+
+    pub fn box_plot(&self) -> BoxPlot<T> {
+        BoxPlot::from_quartiles(
+            self.median.value(),
+            self.q1.value(),
+            self.q3.value(),
+            self.whisker,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // 1..=10
+    const SERIE: [f64; 10] = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0];
+    // пример квартилей из Википедии
+    const WIKI: [f64; 6] = [7.0, 15.0, 36.0, 39.0, 40.0, 41.0];
+
+    fn quartiles(data: &[f64], method: QuantileMethod) -> (f64, f64, f64) {
+        let b = BoxPlot::with_params(data, method, DEFAULT_WHISKER);
+        (b.q1(), b.median(), b.q3())
+    }
+
+    #[test]
+    fn nearest_matches_legacy() {
+        assert_eq!(quartiles(&SERIE, QuantileMethod::Nearest), (3.0, 6.0, 8.0));
+        let b = BoxPlot::new(&SERIE);
+        assert_eq!(b.upper_bound(), 8.0 + 2.0 * 5.0);
+    }
+
+    #[test]
+    fn linear_matches_numpy() {
+        // numpy.percentile(..., [25, 50, 75])
+        assert_eq!(quartiles(&SERIE, QuantileMethod::Linear), (3.25, 5.5, 7.75));
+        assert_eq!(
+            quartiles(&WIKI, QuantileMethod::Linear),
+            (20.25, 37.5, 39.75)
+        );
+    }
+
+    #[test]
+    fn hazen() {
+        assert_eq!(quartiles(&SERIE, QuantileMethod::Hazen), (3.0, 5.5, 8.0));
+        assert_eq!(quartiles(&WIKI, QuantileMethod::Hazen), (15.0, 37.5, 40.0));
+    }
+
+    #[test]
+    fn whisker_factor() {
+        let b = BoxPlot::with_params(&SERIE, QuantileMethod::Linear, 1.5);
+        assert_eq!(b.lower_bound(), 3.25 - 1.5 * 4.5);
+        assert_eq!(b.upper_bound(), 7.75 + 1.5 * 4.5);
+    }
+
+    #[test]
+    fn config_format() {
+        let config: BoxPlotConfig = serde_json::from_str(r#"{"Method": "Linear"}"#).unwrap();
+        assert_eq!(config.method, QuantileMethod::Linear);
+        assert_eq!(config.whisker, DEFAULT_WHISKER);
+    }
+
+    #[test]
+    fn rolling_window() {
+        let mut rolling = RollingBoxPlot::with_params(5, QuantileMethod::Linear, DEFAULT_WHISKER);
+        for v in [10.0, 1.0, 2.0, 3.0, 4.0, 5.0, f64::NAN] {
+            rolling.push(v);
+        }
+        assert!(rolling.is_full());
+        assert_eq!(rolling.box_plot().median(), 3.0);
+        assert_eq!(rolling.quantile(1.0), 5.0);
+
+        rolling.push(100.0);
+        assert_eq!(rolling.quantile(0.0), 2.0);
+        assert_eq!(rolling.quantile(1.0), 100.0);
+
+        // нулевое окно - хранится только последнее значение
+        let mut rolling = RollingBoxPlot::new(0);
+        rolling.push(1.0);
+        rolling.push(2.0);
+        assert!(rolling.is_full());
+        assert_eq!(rolling.quantile(0.0), 2.0);
+    }
+
+    #[test]
+    fn p2_converges() {
+        // детерминированная перестановка 0..1000
+        let mut median = P2Quantile::new(0.5);
+        let mut q90 = P2Quantile::new(0.9);
+        for i in 0..1000u64 {
+            let v = ((i * 7919) % 1000) as f64;
+            median.push(v);
+            q90.push(v);
+        }
+        assert!((median.value() - 499.5).abs() < 10.0);
+        assert!((q90.value() - 899.1).abs() < 10.0);
+
+        let mut few = P2Quantile::new(0.5);
+        [3.0, 1.0, 2.0].into_iter().for_each(|v| few.push(v));
+        assert_eq!(few.value(), 2.0);
+    }
+}
@@ -0,0 +1,264 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::fragment_store::{load_json, save_json};
+
+/// Индивидуальная цель канала для наборов с разными частотами (подобранные пары, лесенки)
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChannelTarget {
+    /// Номер позиции, начиная с 1, как в интерфейсе
+    #[serde(rename = "Channel")]
+    pub channel: usize,
+
+    /// Целевая частота, Гц
+    #[serde(rename = "TargetFreq")]
+    pub target: f32,
+
+    /// Допустимое отклонение, Гц, по умолчанию - общий допуск
+    #[serde(
+        rename = "ToleranceHz",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub tolerance_hz: Option<f32>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Json(serde_json::Error),
+    Csv { line: usize, message: String },
+    Channel(usize),
+    Value { channel: usize, message: String },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Json(e) => write!(f, "Ошибка JSON: {}", e),
+            Error::Csv { line, message } => write!(f, "Строка {}: {}", line, message),
+            Error::Channel(ch) => write!(f, "Неверный или повторяющийся номер канала {}", ch),
+            Error::Value { channel, message } => write!(f, "Канал {}: {}", channel, message),
+        }
+    }
+}
+
+/// Разбор таблицы целей каналов: JSON-массив [`ChannelTarget`] или CSV
+///
+/// CSV: `канал;частота[;допуск]`, разделитель `;`, `,` или табуляция.
+/// Строка заголовка и строки, начинающиеся с `#`, пропускаются.
+/// При разделителе `;` или табуляции допускается десятичная запятая, как при выгрузке из Excel
+pub fn parse_channel_targets(
+    text: &str,
+    channel_count: usize,
+) -> Result<Vec<ChannelTarget>, Error> {
+    let targets = if text.trim_start().starts_with('[') {
+        serde_json::from_str(text).map_err(Error::Json)?
+    } else {
+        parse_csv(text)?
+    };
+
+    validate(targets, channel_count)
+}
+
+/// Проверка номеров каналов, частот и допусков таблицы целей
+fn validate(
+    targets: Vec<ChannelTarget>,
+    channel_count: usize,
+) -> Result<Vec<ChannelTarget>, Error> {
+    let mut seen = vec![false; channel_count];
+    for t in &targets {
+        match t.channel.checked_sub(1).and_then(|i| seen.get_mut(i)) {
+            Some(s) if !*s => *s = true,
+            _ => return Err(Error::Channel(t.channel)),
+        }
+        if !(t.target.is_finite() && t.target > 0.0) {
+            return Err(Error::Value {
+                channel: t.channel,
+                message: format!("неверная частота {}", t.target),
+            });
+        }
+        if t.tolerance_hz
+            .is_some_and(|tol| !(tol.is_finite() && tol > 0.0))
+        {
+            return Err(Error::Value {
+                channel: t.channel,
+                message: format!("неверный допуск {:?}", t.tolerance_hz),
+            });
+        }
+    }
+
+    Ok(targets)
+}
+
+fn parse_csv(text: &str) -> Result<Vec<ChannelTarget>, Error> {
+    parse_rows(text)?
+        .into_iter()
+        .map(|row| {
+            Ok(ChannelTarget {
+                channel: row.channel,
+                target: row.value(0, "частота")?.ok_or(Error::Csv {
+                    line: row.line,
+                    message: "нет частоты".to_owned(),
+                })?,
+                tolerance_hz: row.value(1, "допуск")?,
+            })
+        })
+        .collect()
+}
+
+/// Последняя импортированная таблица целей каналов, восстанавливается при запуске
+#[derive(Clone, Debug)]
+pub struct ChannelTargetsStore {
+    path: PathBuf,
+}
+
+impl ChannelTargetsStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Загрузить таблицу, отсутствующий файл - целей нет.
+    /// Таблица проверяется заново: расстановка каналов могла измениться
+    pub fn load(&self, channel_count: usize) -> std::io::Result<Vec<ChannelTarget>> {
+        validate(load_json(&self.path)?.unwrap_or_default(), channel_count)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
+    }
+
+    /// Сохранить таблицу, пустая таблица удаляет файл
+    pub fn save(&self, targets: &[ChannelTarget]) -> std::io::Result<()> {
+        if targets.is_empty() {
+            return match std::fs::remove_file(&self.path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            };
+        }
+
+        save_json(&self.path, targets)
+    }
+}
+
+/// Строка таблицы каналов: номер канала и следующие за ним поля
+pub(crate) struct Row {
+    pub line: usize,
+    pub channel: usize,
+    fields: Vec<String>,
+}
+
+impl Row {
+    /// Числовое поле i после номера канала, пустое или отсутствующее - None
+    pub fn value(&self, i: usize, name: &str) -> Result<Option<f32>, Error> {
+        match self.fields.get(i).filter(|f| !f.is_empty()) {
+            Some(f) => f.parse::<f32>().map(Some).map_err(|e| Error::Csv {
+                line: self.line,
+                message: format!("{} '{}': {}", name, f, e),
+            }),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Разбор CSV таблицы каналов `канал;поле;поле...`, правила как у [`parse_channel_targets`]
+pub(crate) fn parse_rows(text: &str) -> Result<Vec<Row>, Error> {
+    let mut rows = vec![];
+    let mut first_row = true;
+
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let separator = if line.contains(';') {
+            ';'
+        } else if line.contains('\t') {
+            '\t'
+        } else {
+            ','
+        };
+        let mut fields = line
+            .split(separator)
+            .map(|f| f.trim().trim_matches('"').replace(',', "."))
+            .collect::<Vec<_>>();
+
+        let channel = match fields[0].parse::<usize>() {
+            Ok(ch) => ch,
+            // заголовок
+            Err(_) if first_row => {
+                first_row = false;
+                continue;
+            }
+            Err(e) => {
+                return Err(Error::Csv {
+                    line: n + 1,
+                    message: format!("номер канала '{}': {}", fields[0], e),
+                })
+            }
+        };
+        first_row = false;
+
+        fields.remove(0);
+        rows.push(Row {
+            line: n + 1,
+            channel,
+            fields,
+        });
+    }
+
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn csv_with_header_and_decimal_comma() {
+        let text = "Канал;Частота;Допуск\n1;32768,5;0,2\n# пустая позиция\n\n3;32770\n";
+        let targets = parse_channel_targets(text, 4).unwrap();
+        assert_eq!(
+            targets,
+            vec![
+                ChannelTarget {
+                    channel: 1,
+                    target: 32768.5,
+                    tolerance_hz: Some(0.2)
+                },
+                ChannelTarget {
+                    channel: 3,
+                    target: 32770.0,
+                    tolerance_hz: None
+                },
+            ]
+        );
+
+        let comma = parse_channel_targets("2,32760.25,0.1", 4).unwrap();
+        assert_eq!(comma[0].target, 32760.25);
+    }
+
+    #[test]
+    fn json_and_validation() {
+        let text = r#"[{"Channel": 2, "TargetFreq": 32768.0, "ToleranceHz": 0.5}]"#;
+        assert_eq!(
+            parse_channel_targets(text, 2).unwrap()[0].tolerance_hz,
+            Some(0.5)
+        );
+
+        assert!(matches!(
+            parse_channel_targets("1;32768\n1;32769", 2),
+            Err(Error::Channel(1))
+        ));
+        assert!(matches!(
+            parse_channel_targets("3;32768", 2),
+            Err(Error::Channel(3))
+        ));
+        assert!(matches!(
+            parse_channel_targets("1;-5", 2),
+            Err(Error::Value { channel: 1, .. })
+        ));
+        assert!(matches!(
+            parse_channel_targets("1;32768\nx;1", 2),
+            Err(Error::Csv { line: 2, .. })
+        ));
+    }
+}
@@ -2,6 +2,17 @@ use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
 
+use crate::box_plot::BoxPlotConfig;
+use crate::channel_targets::ChannelTargetsStore;
+use crate::crosstalk::{CrosstalkConfig, CrosstalkStore};
+use crate::drift::VerifySweepConfig;
+use crate::edge_store::EdgeStore;
+use crate::filters::{default_fragment_filters, FilterStage};
+use crate::fragment_store::{FragmentStore, HistoryConfig};
+use crate::kalman::MeasureKalmanConfig;
+use crate::noise::NoiseConfig;
+use crate::sealing::{SealingConfig, SealingModel, SealingStore};
+
 #[derive(Deserialize, Clone, Copy, Serialize)]
 pub struct ResonatroPlacement {
     #[serde(rename = "Xcenter")]
@@ -42,6 +53,36 @@ pub struct AxisConfig {
     pub reverse_y: bool,
 }
 
+/// Политика выбора следующего канала при автонастройке всех каналов
+/// Выбирается канал с максимальной оценкой:
+/// DistanceWeight * расстояние - TravelWeight * время_перемещения + AgeWeight * время_с_последнего_выбора
+#[derive(Deserialize, Clone, Copy, Serialize, Debug)]
+pub struct SchedulingConfig {
+    /// Вес расстояния между центрами резонаторов, 1/ед. лазера
+    #[serde(rename = "DistanceWeight", default = "default_distance_weight")]
+    pub distance_weight: f32,
+
+    /// Вес времени перемещения лазера, 1/с
+    #[serde(rename = "TravelWeight", default)]
+    pub travel_weight: f32,
+
+    /// Вес времени с последнего выбора канала, 1/с
+    #[serde(rename = "AgeWeight", default)]
+    pub age_weight: f32,
+
+    /// Скорость холостого перемещения лазера, ед./с
+    #[serde(rename = "TravelSpeed", default = "default_travel_speed")]
+    pub travel_speed: f32,
+}
+
+fn default_distance_weight() -> f32 {
+    1.0
+}
+
+fn default_travel_speed() -> f32 {
+    50.0
+}
+
 #[derive(Deserialize, Clone, Copy, Serialize)]
 pub struct ForecastConfig {
     #[serde(rename = "MinFreqGrow")]
@@ -52,6 +93,89 @@ pub struct ForecastConfig {
 
     #[serde(rename = "MedianFreqGrow")]
     pub median_freq_grow: f32,
+
+    /// Сколько последних фрагментов канала учитывать в адаптивном прогнозе
+    #[serde(rename = "AdaptiveWindow", default = "default_adaptive_window")]
+    pub adaptive_window: usize,
+
+    /// Количество выстрелов, при котором статистика канала и общая статистика имеют равный вес
+    #[serde(rename = "AdaptiveMinShots", default = "default_adaptive_min_shots")]
+    pub adaptive_min_shots: usize,
+
+    /// Допустимая полуширина интервала прогноза конечной частоты, чтобы не ждать полного охлаждения, Гц
+    #[serde(
+        rename = "ConfidentIntervalHz",
+        default = "default_confident_interval_hz"
+    )]
+    pub confident_interval_hz: f32,
+
+    /// Минимальное количество точек от минимума частоты для уверенного прогноза
+    #[serde(
+        rename = "ConfidentMinSamples",
+        default = "default_confident_min_samples"
+    )]
+    pub confident_min_samples: usize,
+
+    /// Количество выстрелов текущей партии, при котором она имеет равный вес с историей прошлых партий
+    #[serde(rename = "HistoryWeight", default = "default_history_weight")]
+    pub history_weight: usize,
+
+    /// Минимальная оценка качества аппроксимации фрагмента (0..1), модели хуже отбрасываются
+    #[serde(rename = "MinFitScore", default = "default_min_fit_score")]
+    pub min_fit_score: f64,
+
+    /// Наибольший прирост частоты фрагмента после охлаждения, Гц.
+    /// По умолчанию не ограничен: недостоверные модели отбрасываются по оценке качества
+    #[serde(
+        rename = "MaxFragmentGrowHz",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_fragment_grow_hz: Option<f32>,
+}
+
+fn default_adaptive_window() -> usize {
+    20
+}
+
+fn default_adaptive_min_shots() -> usize {
+    5
+}
+
+fn default_confident_interval_hz() -> f32 {
+    0.05
+}
+
+fn default_confident_min_samples() -> usize {
+    5
+}
+
+fn default_history_weight() -> usize {
+    20
+}
+
+fn default_min_fit_score() -> f64 {
+    0.3
+}
+
+/// Время остывания по постоянным времени аппроксимированных фрагментов
+#[derive(Deserialize, Clone, Copy, Serialize, Debug)]
+pub struct AdaptiveCooldownConfig {
+    /// Доля конечного прироста частоты, после которой резонатор считается остывшим
+    #[serde(rename = "SettleFraction", default = "default_settle_fraction")]
+    pub settle_fraction: f64,
+
+    /// Минимальное время остывания, мс
+    #[serde(rename = "MinMs")]
+    pub min_ms: u32,
+
+    /// Максимальное время остывания, мс
+    #[serde(rename = "MaxMs")]
+    pub max_ms: u32,
+}
+
+fn default_settle_fraction() -> f64 {
+    0.95
 }
 
 #[derive(Deserialize, Clone, Copy, Serialize)]
@@ -67,6 +191,65 @@ pub struct AutoAdjustLimits {
 
     #[serde(rename = "EdgeDetectSintervalSt")]
     pub edge_detect_interval: u32,
+
+    /// Шагов, оставляемых для точной настройки: грубые прожиги их не расходуют
+    #[serde(
+        rename = "PrecisionReserveSteps",
+        default = "default_precision_reserve_steps"
+    )]
+    pub precision_reserve_steps: u32,
+
+    /// Размах частоты после пробного выстрела, означающий реакцию резонатора (настройка канала), Гц
+    #[serde(rename = "EdgeDetectPeakHz", default = "default_edge_detect_peak_hz")]
+    pub edge_detect_peak_hz: f32,
+
+    /// Рост частоты после пробного выстрела, означающий реакцию резонатора (настройка всех), Гц
+    #[serde(
+        rename = "EdgeDetectOffsetHz",
+        default = "default_edge_detect_offset_hz"
+    )]
+    pub edge_detect_offset_hz: f32,
+
+    /// Наибольшее число делений пополам при поиске края (настройка всех).
+    /// Каждое - отдельное обращение к каналу с ожиданием остывания
+    #[serde(rename = "EdgeMaxBisections", default = "default_edge_max_bisections")]
+    pub edge_max_bisections: u32,
+}
+
+fn default_precision_reserve_steps() -> u32 {
+    3
+}
+
+fn default_edge_detect_peak_hz() -> f32 {
+    0.35
+}
+
+fn default_edge_detect_offset_hz() -> f32 {
+    0.25
+}
+
+fn default_edge_max_bisections() -> u32 {
+    2
+}
+
+#[derive(Deserialize, Clone, Serialize)]
+pub struct FiltersConfig {
+    /// Фильтры фрагментов остывания перед аппроксимацией
+    #[serde(rename = "Fragment", default = "default_fragment_filters")]
+    pub fragment: Vec<FilterStage>,
+
+    /// Фильтры окна измерения перед оценкой стабильности частоты
+    #[serde(rename = "Measure", default)]
+    pub measure: Vec<FilterStage>,
+}
+
+impl Default for FiltersConfig {
+    fn default() -> Self {
+        Self {
+            fragment: default_fragment_filters(),
+            measure: vec![],
+        }
+    }
 }
 
 #[derive(Deserialize, Clone, Serialize)]
@@ -143,12 +326,55 @@ pub struct Config {
     #[serde(rename = "CooldownTimeMs")]
     pub cooldown_time_ms: u32,
 
+    /// Адаптивное время остывания вместо CooldownTimeMs
+    #[serde(rename = "AdaptiveCooldown", default)]
+    pub adaptive_cooldown: Option<AdaptiveCooldownConfig>,
+
     #[serde(rename = "AutoAdjustLimits")]
     pub auto_adjust_limits: AutoAdjustLimits,
 
     #[serde(rename = "StableVal")]
     pub stable_val: f32,
 
+    #[serde(rename = "Filters", default)]
+    pub filters: FiltersConfig,
+
+    /// Квантили и усы BoxPlot окон измерения
+    #[serde(rename = "BoxPlot", default)]
+    pub box_plot: BoxPlotConfig,
+
+    /// Оценка стабильности частоты фильтром Калмана вместо разброса окна измерений
+    #[serde(rename = "MeasureKalman", default)]
+    pub measure_kalman: Option<MeasureKalmanConfig>,
+
+    /// Тип резонатора / рецепт, по нему ведутся история фрагментов, края каналов и замеры герметизации
+    #[serde(rename = "Recipe", default = "default_recipe")]
+    pub recipe: String,
+
+    /// История фрагментов прошлых партий для прогноза с первого выстрела
+    #[serde(rename = "History", default)]
+    pub history: Option<HistoryConfig>,
+
+    /// Модель теплового влияния прожига на соседние резонаторы
+    #[serde(rename = "Crosstalk", default)]
+    pub crosstalk: Option<CrosstalkConfig>,
+
+    /// Политика выбора следующего канала, по умолчанию - самый дальний из давно не выбиравшихся
+    #[serde(rename = "Scheduling", default)]
+    pub scheduling: Option<SchedulingConfig>,
+
+    /// Контрольные проходы и наблюдение за дрейфом после автонастройки всех каналов
+    #[serde(rename = "VerifySweep", default)]
+    pub verify_sweep: Option<VerifySweepConfig>,
+
+    /// Уход частоты при герметизации, учитываемый в цели настройки
+    #[serde(rename = "Sealing", default)]
+    pub sealing: Option<SealingConfig>,
+
+    /// Характеризация шума каналов
+    #[serde(rename = "Noise", default)]
+    pub noise: NoiseConfig,
+
     #[serde(rename = "ResonatorsPlacement")]
     pub resonator_placement: Vec<ResonatroPlacement>,
 
@@ -159,6 +385,10 @@ pub struct Config {
     pub report_directory: Option<PathBuf>,
 }
 
+fn default_recipe() -> String {
+    "default".to_owned()
+}
+
 impl Config {
     fn get_path() -> PathBuf {
         use std::path;
@@ -206,7 +436,8 @@ impl Config {
     }
 
     pub fn report_directory(&self) -> PathBuf {
-        let path = self.report_directory
+        let path = self
+            .report_directory
             .clone()
             .unwrap_or(Self::get_path().parent().unwrap().join("reports"));
 
@@ -216,6 +447,83 @@ impl Config {
 
         path
     }
+
+    /// Файл последней импортированной таблицы целей каналов рядом с файлом настроек
+    pub fn channel_targets_store(&self) -> ChannelTargetsStore {
+        ChannelTargetsStore::new(
+            Self::get_path()
+                .parent()
+                .unwrap()
+                .join("channel_targets.json"),
+        )
+    }
+
+    /// Хранилище замеров до и после герметизации, если учет ухода включен
+    pub fn sealing_store(&self) -> Option<SealingStore> {
+        self.sealing.as_ref().map(|sealing| {
+            SealingStore::new(
+                sealing
+                    .directory
+                    .clone()
+                    .unwrap_or(Self::get_path().parent().unwrap().join("sealing")),
+                &self.recipe,
+            )
+        })
+    }
+
+    /// Модель ухода частоты при герметизации по замерам прошлых партий
+    pub fn sealing_model(&self) -> Option<SealingModel> {
+        let (sealing, store) = self.sealing.as_ref().zip(self.sealing_store())?;
+        let samples = store.load().unwrap_or_else(|e| {
+            tracing::error!("Failed to load sealing samples {:?}: {e}", store);
+            vec![]
+        });
+        Some(SealingModel::fit(
+            sealing,
+            &samples,
+            self.resonator_placement.len(),
+        ))
+    }
+
+    /// Хранилище краев каналов прошлых партий, ведется вместе с историей фрагментов
+    pub fn edge_store(&self) -> Option<EdgeStore> {
+        self.history.as_ref().map(|history| {
+            EdgeStore::new(
+                history
+                    .directory
+                    .clone()
+                    .unwrap_or(Self::get_path().parent().unwrap().join("history")),
+                &self.recipe,
+            )
+        })
+    }
+
+    /// Модель влияния соседей прошлых партий, ведется вместе с историей фрагментов
+    pub fn crosstalk_store(&self) -> Option<CrosstalkStore> {
+        self.history.as_ref().map(|history| {
+            CrosstalkStore::new(
+                history
+                    .directory
+                    .clone()
+                    .unwrap_or(Self::get_path().parent().unwrap().join("history")),
+                &self.recipe,
+            )
+        })
+    }
+
+    /// Хранилище истории фрагментов, если она включена
+    pub fn history_store(&self) -> Option<FragmentStore> {
+        self.history.as_ref().map(|history| {
+            FragmentStore::new(
+                history
+                    .directory
+                    .clone()
+                    .unwrap_or(Self::get_path().parent().unwrap().join("history")),
+                &self.recipe,
+                history.max_fragments,
+            )
+        })
+    }
 }
 
 impl std::fmt::Display for Config {
@@ -253,8 +561,43 @@ impl std::fmt::Display for Config {
             "  MedianFreqGrow: {}",
             self.forecast_config.median_freq_grow
         )?;
+        writeln!(
+            f,
+            "  AdaptiveWindow: {}",
+            self.forecast_config.adaptive_window
+        )?;
+        writeln!(
+            f,
+            "  AdaptiveMinShots: {}",
+            self.forecast_config.adaptive_min_shots
+        )?;
+        writeln!(
+            f,
+            "  ConfidentIntervalHz: {}",
+            self.forecast_config.confident_interval_hz
+        )?;
+        writeln!(
+            f,
+            "  ConfidentMinSamples: {}",
+            self.forecast_config.confident_min_samples
+        )?;
+        writeln!(
+            f,
+            "  HistoryWeight: {}",
+            self.forecast_config.history_weight
+        )?;
+        writeln!(f, "  MinFitScore: {}", self.forecast_config.min_fit_score)?;
+        if let Some(max_grow) = self.forecast_config.max_fragment_grow_hz {
+            writeln!(f, "  MaxFragmentGrowHz: {}", max_grow)?;
+        }
 
         writeln!(f, "CooldownTimeMs: {}", self.cooldown_time_ms)?;
+        if let Some(cooldown) = &self.adaptive_cooldown {
+            writeln!(f, "AdaptiveCooldown:")?;
+            writeln!(f, "  SettleFraction: {}", cooldown.settle_fraction)?;
+            writeln!(f, "  MinMs: {}", cooldown.min_ms)?;
+            writeln!(f, "  MaxMs: {}", cooldown.max_ms)?;
+        }
 
         writeln!(f, "AutoAdjustLimits:")?;
         writeln!(
@@ -277,9 +620,99 @@ impl std::fmt::Display for Config {
             "  EdgeDetectSintervalSt: {}",
             self.auto_adjust_limits.edge_detect_interval
         )?;
+        writeln!(
+            f,
+            "  PrecisionReserveSteps: {}",
+            self.auto_adjust_limits.precision_reserve_steps
+        )?;
+        writeln!(
+            f,
+            "  EdgeDetectPeakHz: {}",
+            self.auto_adjust_limits.edge_detect_peak_hz
+        )?;
+        writeln!(
+            f,
+            "  EdgeDetectOffsetHz: {}",
+            self.auto_adjust_limits.edge_detect_offset_hz
+        )?;
+        writeln!(
+            f,
+            "  EdgeMaxBisections: {}",
+            self.auto_adjust_limits.edge_max_bisections
+        )?;
 
         writeln!(f, "StableVal: {}", self.stable_val)?;
 
+        let chain = |stages: &[FilterStage]| {
+            stages
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ")
+        };
+        writeln!(f, "Filters:")?;
+        writeln!(f, "  Fragment: {}", chain(&self.filters.fragment))?;
+        writeln!(f, "  Measure: {}", chain(&self.filters.measure))?;
+
+        writeln!(f, "BoxPlot:")?;
+        writeln!(f, "  Method: {:?}", self.box_plot.method)?;
+        writeln!(f, "  Whisker: {}", self.box_plot.whisker)?;
+
+        if let Some(kalman) = &self.measure_kalman {
+            writeln!(f, "MeasureKalman:")?;
+            writeln!(f, "  ProcessNoise: {}", kalman.process_noise)?;
+            writeln!(f, "  MeasurementNoise: {}", kalman.measurement_noise)?;
+            writeln!(f, "  MaxDrift: {}", kalman.max_drift)?;
+            writeln!(f, "  Confidence: {}", kalman.confidence)?;
+        }
+
+        writeln!(f, "Recipe: {}", self.recipe)?;
+
+        if let Some(history) = &self.history {
+            writeln!(f, "History:")?;
+            writeln!(f, "  Directory: {:?}", history.directory)?;
+            writeln!(f, "  MaxFragments: {}", history.max_fragments)?;
+        }
+
+        if let Some(crosstalk) = &self.crosstalk {
+            writeln!(f, "Crosstalk:")?;
+            writeln!(f, "  Amplitude: {}", crosstalk.amplitude)?;
+            writeln!(f, "  Length: {}", crosstalk.length)?;
+            writeln!(f, "  Tau: {}", crosstalk.tau)?;
+            writeln!(f, "  Tolerance: {}", crosstalk.tolerance)?;
+            writeln!(f, "  MinObservations: {}", crosstalk.min_observations)?;
+        }
+
+        if let Some(scheduling) = &self.scheduling {
+            writeln!(f, "Scheduling:")?;
+            writeln!(f, "  DistanceWeight: {}", scheduling.distance_weight)?;
+            writeln!(f, "  TravelWeight: {}", scheduling.travel_weight)?;
+            writeln!(f, "  AgeWeight: {}", scheduling.age_weight)?;
+            writeln!(f, "  TravelSpeed: {}", scheduling.travel_speed)?;
+        }
+
+        if let Some(verify) = &self.verify_sweep {
+            writeln!(f, "VerifySweep:")?;
+            writeln!(f, "  SettleTimeMs: {}", verify.settle_time_ms)?;
+            writeln!(f, "  Sweeps: {}", verify.sweeps)?;
+            writeln!(f, "  MonitorMinutes: {}", verify.monitor_minutes)?;
+            writeln!(f, "  MonitorIntervalS: {}", verify.monitor_interval_s)?;
+        }
+
+        if let Some(sealing) = &self.sealing {
+            writeln!(f, "Sealing:")?;
+            writeln!(f, "  OffsetHz: {}", sealing.offset_hz)?;
+            writeln!(f, "  OffsetPPM: {}", sealing.offset_ppm)?;
+            writeln!(f, "  LearnPerChannel: {}", sealing.learn_per_channel)?;
+            writeln!(f, "  MinSamples: {}", sealing.min_samples)?;
+            writeln!(f, "  Directory: {:?}", sealing.directory)?;
+        }
+
+        writeln!(f, "Noise:")?;
+        writeln!(f, "  ParkTimeMs: {}", self.noise.park_time_ms)?;
+        writeln!(f, "  UseForAdjust: {}", self.noise.use_for_adjust)?;
+        writeln!(f, "  StableMargin: {}", self.noise.stable_margin)?;
+
         // write resonators placement as a table
         writeln!(f, "ResonatorsPlacement:")?;
         writeln!(
@@ -23,6 +23,9 @@ pub trait CoordiantesCalc {
         current_side: Side,
         total_steps: u32,
     ) -> (f32, f32);
+
+    /// Центр резонатора в координатах лазера
+    fn center_abs(&self, axis_config: &AxisConfig) -> (f32, f32);
 }
 
 impl CoordiantesCalc for crate::config::ResonatroPlacement {
@@ -52,4 +55,13 @@ impl CoordiantesCalc for crate::config::ResonatroPlacement {
             (x, y)
         }
     }
+
+    fn center_abs(&self, axis_config: &AxisConfig) -> (f32, f32) {
+        // зеркалирование осей смещает только точки внутри резонатора, центр остается на месте
+        if axis_config.swap_xy {
+            (self.y, self.x)
+        } else {
+            (self.x, self.y)
+        }
+    }
 }
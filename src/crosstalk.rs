@@ -0,0 +1,269 @@
+use std::path::PathBuf;
+
+use nalgebra::{Matrix3, Vector3};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{AxisConfig, ResonatroPlacement},
+    coordinates::CoordiantesCalc,
+    fragment_store::{load_json, recipe_file_name, save_json},
+};
+
+/// Наибольшее количество итераций уточнения модели
+const FIT_ITERATIONS: usize = 100;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+pub struct CrosstalkConfig {
+    /// Начальная оценка смещения частоты соседа в момент прожига на нулевом расстоянии, Гц
+    #[serde(rename = "Amplitude")]
+    pub amplitude: f64,
+
+    /// Начальная оценка характерной длины затухания влияния, мм
+    #[serde(rename = "Length")]
+    pub length: f64,
+
+    /// Начальная оценка постоянной времени восстановления, с
+    #[serde(rename = "Tau")]
+    pub tau: f64,
+
+    /// Допустимое остаточное смещение частоты, при котором измерению канала можно доверять, Гц
+    #[serde(rename = "Tolerance")]
+    pub tolerance: f64,
+
+    /// Количество наблюдений, после которого модель уточняется по измерениям
+    #[serde(rename = "MinObservations", default = "default_min_observations")]
+    pub min_observations: usize,
+}
+
+fn default_min_observations() -> usize {
+    10
+}
+
+/// Влияние прожига одного резонатора на частоту другого:
+/// df(d, t) = -amplitude * exp(-d / length) * exp(-t / tau)
+/// d - расстояние между центрами резонаторов, t - время после прожига
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CrosstalkModel {
+    /// Гц
+    pub amplitude: f64,
+    /// мм
+    pub length: f64,
+    /// с
+    pub tau: f64,
+}
+
+/// Смещение частоты канала, измеренное через dt после прожига другого канала
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct CrosstalkObservation {
+    /// мм
+    pub distance: f64,
+    /// с
+    pub dt: f64,
+    /// Гц, нагрев соседа понижает частоту - смещение отрицательное
+    pub shift: f64,
+}
+
+impl From<&CrosstalkConfig> for CrosstalkModel {
+    fn from(config: &CrosstalkConfig) -> Self {
+        Self {
+            amplitude: config.amplitude,
+            length: config.length,
+            tau: config.tau,
+        }
+    }
+}
+
+impl CrosstalkModel {
+    /// Модуль смещения частоты на расстоянии distance через dt секунд после прожига
+    pub fn shift(&self, distance: f64, dt: f64) -> f64 {
+        self.amplitude * (-distance / self.length).exp() * (-dt.max(0.0) / self.tau).exp()
+    }
+
+    /// Время, через которое суммарное смещение current упадет до tolerance, с
+    /// Все составляющие затухают с одной постоянной времени, поэтому достаточно текущей суммы
+    pub fn settle_time(&self, current: f64, tolerance: f64) -> f64 {
+        if current > tolerance && tolerance > 0.0 {
+            self.tau * (current / tolerance).ln()
+        } else {
+            0.0
+        }
+    }
+
+    /// Аппроксимация -shift = amplitude * exp(-distance / length - dt / tau) методом
+    /// Левенберга-Марквардта, начиная с initial. Учитываются все наблюдения, включая шум
+    /// и смещения вверх: отбор по порогу смещал бы оценку, допуск применяется уже к модели
+    /// Возвращает None, если данных недостаточно или результат физически неправдоподобен
+    pub fn fit(observations: &[CrosstalkObservation], initial: &Self) -> Option<Self> {
+        if observations.len() < 3 {
+            return None;
+        }
+
+        // параметры: ln(amplitude), 1 / length, 1 / tau
+        let model = |p: &Vector3<f64>, o: &CrosstalkObservation| {
+            (p[0] - p[1] * o.distance - p[2] * o.dt).exp()
+        };
+        let sse = |p: &Vector3<f64>| {
+            observations
+                .iter()
+                .map(|o| (-o.shift - model(p, o)).powi(2))
+                .sum::<f64>()
+        };
+
+        let mut p = Vector3::new(
+            initial.amplitude.ln(),
+            1.0 / initial.length,
+            1.0 / initial.tau,
+        );
+        if !p.iter().all(|v| v.is_finite()) {
+            return None;
+        }
+
+        let mut current = sse(&p);
+        let mut lambda = 1e-3;
+        for _ in 0..FIT_ITERATIONS {
+            let mut jtj = Matrix3::<f64>::zeros();
+            let mut jtr = Vector3::<f64>::zeros();
+            for o in observations {
+                let m = model(&p, o);
+                let j = Vector3::new(m, -o.distance * m, -o.dt * m);
+                jtj += j * j.transpose();
+                jtr += j * (-o.shift - m);
+            }
+
+            let damped = jtj + Matrix3::from_diagonal(&jtj.diagonal()) * lambda;
+            let Some(inv) = damped.try_inverse() else {
+                break;
+            };
+            let candidate = p + inv * jtr;
+            let candidate_sse = sse(&candidate);
+            if candidate_sse < current {
+                let converged = current - candidate_sse <= current * 1e-12;
+                p = candidate;
+                current = candidate_sse;
+                lambda /= 10.0;
+                if converged {
+                    break;
+                }
+            } else {
+                lambda *= 10.0;
+                if lambda > 1e10 {
+                    break;
+                }
+            }
+        }
+
+        let (ln_a, inv_length, inv_tau) = (p[0], p[1], p[2]);
+        if inv_length > 0.0 && inv_tau > 0.0 && ln_a.is_finite() {
+            Some(Self {
+                amplitude: ln_a.exp(),
+                length: 1.0 / inv_length,
+                tau: 1.0 / inv_tau,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// Расстояние между центрами резонаторов в координатах лазера, мм
+pub fn placement_distance(
+    a: &ResonatroPlacement,
+    b: &ResonatroPlacement,
+    axis_config: &AxisConfig,
+) -> f64 {
+    let (ax, ay) = a.center_abs(axis_config);
+    let (bx, by) = b.center_abs(axis_config);
+    ((ax - bx) as f64).hypot((ay - by) as f64)
+}
+
+/// Модель, уточненная в прошлых партиях одного рецепта, в файле `<Recipe>.crosstalk.json`
+#[derive(Clone, Debug)]
+pub struct CrosstalkStore {
+    path: PathBuf,
+}
+
+impl CrosstalkStore {
+    pub fn new(directory: PathBuf, recipe: &str) -> Self {
+        Self {
+            path: directory.join(format!("{}.crosstalk.json", recipe_file_name(recipe))),
+        }
+    }
+
+    /// Загрузить модель, отсутствующий файл - модели еще нет
+    pub fn load(&self) -> std::io::Result<Option<CrosstalkModel>> {
+        load_json(&self.path)
+    }
+
+    pub fn save(&self, model: &CrosstalkModel) -> std::io::Result<()> {
+        save_json(&self.path, model)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const MODEL: CrosstalkModel = CrosstalkModel {
+        amplitude: 0.5,
+        length: 10.0,
+        tau: 4.0,
+    };
+
+    /// Начальная оценка из настроек
+    const INITIAL: CrosstalkModel = CrosstalkModel {
+        amplitude: 0.3,
+        length: 8.0,
+        tau: 3.0,
+    };
+
+    #[test]
+    fn fit_recovers_model() {
+        let observations = [0.0, 5.0, 10.0, 20.0]
+            .iter()
+            .flat_map(|d| {
+                [0.5, 2.0, 5.0].map(|dt| CrosstalkObservation {
+                    distance: *d,
+                    dt,
+                    shift: -MODEL.shift(*d, dt),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let fitted = CrosstalkModel::fit(&observations, &INITIAL).unwrap();
+        assert!((fitted.amplitude - MODEL.amplitude).abs() < 1e-6);
+        assert!((fitted.length - MODEL.length).abs() < 1e-6);
+        assert!((fitted.tau - MODEL.tau).abs() < 1e-6);
+
+        assert!(CrosstalkModel::fit(&observations[..2], &INITIAL).is_none());
+    }
+
+    #[test]
+    fn fit_noisy_unbiased() {
+        // шум частотомера больше смещения дальних соседей, часть смещений - вверх
+        const NOISE: [f64; 5] = [0.02, -0.015, 0.005, -0.02, 0.01];
+        let observations = [0.0, 5.0, 10.0, 20.0, 40.0, 60.0]
+            .iter()
+            .flat_map(|d| [0.5, 2.0, 5.0, 10.0].map(|dt| (*d, dt)))
+            .enumerate()
+            .map(|(i, (distance, dt))| CrosstalkObservation {
+                distance,
+                dt,
+                shift: -MODEL.shift(distance, dt) + NOISE[i % NOISE.len()],
+            })
+            .collect::<Vec<_>>();
+        assert!(observations.iter().any(|o| o.shift > 0.0));
+
+        let fitted = CrosstalkModel::fit(&observations, &INITIAL).unwrap();
+        assert!((fitted.amplitude - MODEL.amplitude).abs() < 0.05);
+        assert!((fitted.length - MODEL.length).abs() < 1.0);
+        assert!((fitted.tau - MODEL.tau).abs() < 0.5);
+    }
+
+    #[test]
+    fn settle_time() {
+        let current = MODEL.shift(0.0, 0.0);
+        let t = MODEL.settle_time(current, 0.05);
+        assert!((MODEL.shift(0.0, t) - 0.05).abs() < 1e-12);
+        assert_eq!(MODEL.settle_time(0.01, 0.05), 0.0);
+    }
+}
@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+
+/// Контрольные проходы по всем каналам после окончания настройки
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+pub struct VerifySweepConfig {
+    /// Выдержка после последнего прожига перед первым проходом, мс
+    #[serde(rename = "SettleTimeMs", default = "default_settle_time_ms")]
+    pub settle_time_ms: u32,
+
+    /// Количество контрольных проходов подряд
+    #[serde(rename = "Sweeps", default = "default_sweeps")]
+    pub sweeps: u32,
+
+    /// Продолжительность наблюдения за дрейфом после контрольных проходов, мин, 0 - не наблюдать
+    #[serde(rename = "MonitorMinutes", default)]
+    pub monitor_minutes: f32,
+
+    /// Интервал между проходами при наблюдении, с
+    #[serde(rename = "MonitorIntervalS", default = "default_monitor_interval_s")]
+    pub monitor_interval_s: f32,
+}
+
+fn default_settle_time_ms() -> u32 {
+    30000
+}
+
+fn default_sweeps() -> u32 {
+    1
+}
+
+fn default_monitor_interval_s() -> f32 {
+    60.0
+}
+
+/// Дрейф частоты канала после настройки
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChannelDrift {
+    /// Частота по окончании настройки, Гц
+    pub trimmed_freq: f32,
+
+    /// Контрольные измерения: (секунды после окончания настройки, частота)
+    pub samples: Vec<(f32, f32)>,
+}
+
+impl ChannelDrift {
+    pub fn new(trimmed_freq: f32) -> Self {
+        Self {
+            trimmed_freq,
+            samples: vec![],
+        }
+    }
+
+    pub fn push(&mut self, elapsed_s: f32, freq: f32) {
+        self.samples.push((elapsed_s, freq));
+    }
+
+    /// Частота последнего контрольного измерения
+    pub fn verified_freq(&self) -> Option<f32> {
+        self.samples.last().map(|(_, f)| *f)
+    }
+
+    /// Уход частоты от настроенной к последнему измерению, Гц
+    pub fn drift(&self) -> Option<f32> {
+        self.verified_freq().map(|f| f - self.trimmed_freq)
+    }
+
+    /// Наибольший по модулю уход частоты за время наблюдения, Гц
+    pub fn max_drift(&self) -> Option<f32> {
+        self.samples
+            .iter()
+            .map(|(_, f)| f - self.trimmed_freq)
+            .max_by(|a, b| a.abs().total_cmp(&b.abs()))
+    }
+
+    /// Скорость дрейфа по контрольным измерениям (МНК), Гц/мин
+    pub fn rate(&self) -> Option<f32> {
+        let n = self.samples.len() as f32;
+        if n < 2.0 {
+            return None;
+        }
+
+        let mean_t = self.samples.iter().map(|(t, _)| t).sum::<f32>() / n;
+        let mean_f = self.samples.iter().map(|(_, f)| f).sum::<f32>() / n;
+        let (cov, var) = self.samples.iter().fold((0.0, 0.0), |(cov, var), (t, f)| {
+            let dt = t - mean_t;
+            (cov + dt * (f - mean_f), var + dt * dt)
+        });
+
+        if var > 0.0 {
+            Some(cov / var * 60.0)
+        } else {
+            None
+        }
+    }
+
+    /// Последнее контрольное измерение в пределах [lower, upper],
+    /// None - канал не проверен: ни одного удачного контрольного измерения
+    pub fn in_tolerance(&self, lower: f32, upper: f32) -> Option<bool> {
+        self.verified_freq().map(|f| (lower..=upper).contains(&f))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn drift() {
+        let mut d = ChannelDrift::new(32768.0);
+        assert_eq!(d.drift(), None);
+        assert_eq!(d.rate(), None);
+        assert_eq!(d.in_tolerance(32767.0, 32769.0), None);
+
+        d.push(30.0, 32768.25);
+        d.push(90.0, 32767.5);
+        d.push(150.0, 32767.25);
+
+        assert_eq!(d.verified_freq(), Some(32767.25));
+        assert_eq!(d.drift(), Some(-0.75));
+        assert_eq!(d.max_drift(), Some(-0.75));
+        assert!((d.rate().unwrap() + 0.5).abs() < 1e-3);
+        assert_eq!(d.in_tolerance(32767.0, 32769.0), Some(true));
+        assert_eq!(d.in_tolerance(32767.5, 32768.5), Some(false));
+    }
+}
@@ -0,0 +1,94 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::fragment_store::{load_json, recipe_file_name, save_json};
+
+/// Сколько последних краев канала хранится
+const MAX_EDGES_PER_CHANNEL: usize = 20;
+
+/// Шаги краев, найденных в прошлых партиях, по каналам (нумерация с 0), от старых к новым
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EdgeHistory(Vec<Vec<u32>>);
+
+impl EdgeHistory {
+    pub fn push(&mut self, channel: usize, edge: u32) {
+        if self.0.len() <= channel {
+            self.0.resize(channel + 1, vec![]);
+        }
+
+        let edges = &mut self.0[channel];
+        edges.push(edge);
+        if edges.len() > MAX_EDGES_PER_CHANNEL {
+            edges.drain(..edges.len() - MAX_EDGES_PER_CHANNEL);
+        }
+    }
+
+    /// Ожидаемый шаг края канала - медиана краев прошлых партий
+    pub fn hint(&self, channel: usize) -> Option<u32> {
+        let mut edges = self.0.get(channel)?.clone();
+        edges.sort_unstable();
+        edges.get(edges.len() / 2).copied()
+    }
+}
+
+/// Края каналов всех партий одного рецепта в файле `<Recipe>.edges.json`
+#[derive(Clone, Debug)]
+pub struct EdgeStore {
+    path: PathBuf,
+}
+
+impl EdgeStore {
+    pub fn new(directory: PathBuf, recipe: &str) -> Self {
+        Self {
+            path: directory.join(format!("{}.edges.json", recipe_file_name(recipe))),
+        }
+    }
+
+    /// Загрузить края, отсутствующий файл - нет истории
+    pub fn load(&self) -> std::io::Result<EdgeHistory> {
+        Ok(load_json(&self.path)?.unwrap_or_default())
+    }
+
+    /// Дописать края партии: (канал, шаг края)
+    pub fn record(&self, edges: &[(usize, u32)]) -> std::io::Result<()> {
+        if edges.is_empty() {
+            return Ok(());
+        }
+
+        let mut history = self.load()?;
+        for (channel, edge) in edges {
+            history.push(*channel, *edge);
+        }
+        save_json(&self.path, &history)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn edge_history() {
+        let mut history = EdgeHistory::default();
+        assert_eq!(history.hint(0), None);
+
+        for (channel, edge) in [(0, 12), (2, 30), (0, 14), (0, 13)] {
+            history.push(channel, edge);
+        }
+        assert_eq!(history.hint(0), Some(13));
+        assert_eq!(history.hint(1), None);
+        assert_eq!(history.hint(2), Some(30));
+        assert_eq!(history.hint(3), None);
+
+        // хранятся только последние края канала
+        for edge in 100..100 + MAX_EDGES_PER_CHANNEL as u32 {
+            history.push(0, edge);
+        }
+        assert_eq!(history.0[0].len(), MAX_EDGES_PER_CHANNEL);
+        assert_eq!(
+            history.hint(0),
+            Some(100 + MAX_EDGES_PER_CHANNEL as u32 / 2)
+        );
+    }
+}
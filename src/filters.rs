@@ -0,0 +1,202 @@
+use num_traits::{Float, FromPrimitive, NumOps};
+use serde::{Deserialize, Serialize};
+
+use crate::predict::{hard_filter, smooth_filter_with};
+
+/// Звено цепочки фильтров. Все фильтры сохраняют длину ряда:
+/// отбракованные значения заменяются, а не удаляются
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(tag = "Type")]
+pub enum FilterStage {
+    /// Замена пропаданий сигнала (значения не больше Threshold) предыдущим корректным значением
+    ZeroDropout {
+        #[serde(rename = "Threshold", default)]
+        threshold: f64,
+    },
+
+    /// Замена значений вне усов BoxPlot предыдущим корректным значением
+    BoxPlot,
+
+    /// Скользящая медиана
+    Median {
+        #[serde(rename = "Window")]
+        window: usize,
+    },
+
+    /// Фильтр Хампеля: значения, отклоняющиеся от скользящей медианы более чем на
+    /// Sigmas * 1.4826 * MAD, заменяются медианой
+    Hampel {
+        #[serde(rename = "Window")]
+        window: usize,
+
+        #[serde(rename = "Sigmas")]
+        sigmas: f64,
+    },
+
+    /// Сглаживающий сплайн, значения, отклоняющиеся от сплайна, заменяются предыдущим корректным
+    Spline {
+        #[serde(rename = "Smooth")]
+        smooth: f64,
+    },
+
+    /// Экспоненциальное скользящее среднее
+    Ema {
+        #[serde(rename = "Alpha")]
+        alpha: f64,
+    },
+}
+
+impl std::fmt::Display for FilterStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FilterStage::ZeroDropout { threshold } => write!(f, "ZeroDropout({threshold})"),
+            FilterStage::BoxPlot => write!(f, "BoxPlot"),
+            FilterStage::Median { window } => write!(f, "Median({window})"),
+            FilterStage::Hampel { window, sigmas } => write!(f, "Hampel({window}, {sigmas})"),
+            FilterStage::Spline { smooth } => write!(f, "Spline({smooth})"),
+            FilterStage::Ema { alpha } => write!(f, "Ema({alpha})"),
+        }
+    }
+}
+
+/// Цепочка фильтров, по умолчанию используемая для фрагментов остывания
+pub fn default_fragment_filters() -> Vec<FilterStage> {
+    vec![FilterStage::BoxPlot, FilterStage::Spline { smooth: 0.85 }]
+}
+
+/// Применить цепочку фильтров к ряду (t, y)
+pub fn apply_filters<T>(stages: &[FilterStage], t: &[T], y: &[T]) -> csaps::Result<Vec<T>>
+where
+    T: Float + NumOps + FromPrimitive + csaps::Real + Serialize + Copy,
+{
+    let mut y = y.to_vec();
+    for stage in stages {
+        match *stage {
+            FilterStage::ZeroDropout { threshold } => zero_dropout(&mut y, from_f64(threshold)),
+            FilterStage::BoxPlot => hard_filter(&mut y),
+            FilterStage::Median { window } => y = median(&y, window),
+            FilterStage::Hampel { window, sigmas } => y = hampel(&y, window, from_f64(sigmas)),
+            FilterStage::Spline { smooth } => {
+                y = smooth_filter_with(&t.to_vec(), &y, from_f64(smooth))?
+            }
+            FilterStage::Ema { alpha } => ema(&mut y, from_f64(alpha)),
+        }
+    }
+    Ok(y)
+}
+
+/// Применить цепочку фильтров к ряду с равномерным шагом (время - номер точки)
+pub fn apply_filters_uniform<T>(stages: &[FilterStage], y: &[T]) -> csaps::Result<Vec<T>>
+where
+    T: Float + NumOps + FromPrimitive + csaps::Real + Serialize + Copy,
+{
+    let t = (0..y.len()).map(from_usize).collect::<Vec<T>>();
+    apply_filters(stages, &t, y)
+}
+
+fn from_f64<T: FromPrimitive>(v: f64) -> T {
+    unsafe { T::from_f64(v).unwrap_unchecked() }
+}
+
+fn from_usize<T: FromPrimitive>(v: usize) -> T {
+    unsafe { T::from_usize(v).unwrap_unchecked() }
+}
+
+fn zero_dropout<T: Float>(data: &mut [T], threshold: T) {
+    let first_valid = data.iter().copied().find(|v| *v > threshold);
+    let mut prev_y = first_valid;
+    data.iter_mut().for_each(|y| {
+        if *y > threshold {
+            prev_y = Some(*y);
+        } else if let Some(py) = prev_y {
+            *y = py;
+        }
+    });
+}
+
+/// Окно [i - window / 2, i + window / 2], обрезанное по краям ряда
+fn window_of<T>(data: &[T], i: usize, window: usize) -> &[T] {
+    let half = window / 2;
+    &data[i.saturating_sub(half)..(i + half + 1).min(data.len())]
+}
+
+fn median_of<T: Float>(window: &[T]) -> T {
+    let mut sorted = window.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    sorted[sorted.len() / 2]
+}
+
+fn median<T: Float>(data: &[T], window: usize) -> Vec<T> {
+    (0..data.len())
+        .map(|i| median_of(window_of(data, i, window)))
+        .collect()
+}
+
+fn hampel<T: Float + FromPrimitive>(data: &[T], window: usize, sigmas: T) -> Vec<T> {
+    // MAD -> стандартное отклонение для нормального распределения
+    let k: T = from_f64(1.4826);
+
+    (0..data.len())
+        .map(|i| {
+            let w = window_of(data, i, window);
+            let med = median_of(w);
+            let mad = median_of(&w.iter().map(|v| (*v - med).abs()).collect::<Vec<_>>());
+            if (data[i] - med).abs() > sigmas * k * mad {
+                med
+            } else {
+                data[i]
+            }
+        })
+        .collect()
+}
+
+fn ema<T: Float>(data: &mut [T], alpha: T) {
+    let mut prev = None;
+    data.iter_mut().for_each(|y| {
+        if let Some(p) = prev {
+            *y = p + (*y - p) * alpha;
+        }
+        prev = Some(*y);
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn zero_dropout_replaced() {
+        let y = [0.0, 10.0, 0.0, 11.0];
+        assert_eq!(
+            apply_filters_uniform(&[FilterStage::ZeroDropout { threshold: 1.0 }], &y).unwrap(),
+            vec![10.0, 10.0, 10.0, 11.0]
+        );
+    }
+
+    #[test]
+    fn hampel_removes_spike() {
+        let y = [10.0, 10.1, 9.9, 50.0, 10.0, 10.1, 9.9];
+        let filtered = apply_filters_uniform(
+            &[FilterStage::Hampel {
+                window: 5,
+                sigmas: 3.0,
+            }],
+            &y,
+        )
+        .unwrap();
+        assert_eq!(filtered[3], 10.1);
+        assert_eq!(filtered[0], 10.0);
+    }
+
+    #[test]
+    fn config_format() {
+        let stages: Vec<FilterStage> =
+            serde_json::from_str(r#"[{"Type": "Median", "Window": 3}, {"Type": "BoxPlot"}]"#)
+                .unwrap();
+        assert_eq!(
+            stages,
+            vec![FilterStage::Median { window: 3 }, FilterStage::BoxPlot]
+        );
+        assert_eq!(median(&[1.0, 5.0, 2.0, 3.0], 3), vec![5.0, 2.0, 3.0, 3.0]);
+    }
+}
@@ -0,0 +1,178 @@
+use std::path::{Path, PathBuf};
+
+use num_traits::Float;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::predict::Fragment;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct HistoryConfig {
+    /// Каталог хранилища фрагментов, краев каналов и модели влияния соседей,
+    /// по умолчанию - "history" рядом с файлом настроек
+    #[serde(rename = "Directory", default)]
+    pub directory: Option<PathBuf>,
+
+    /// Максимальное количество хранимых фрагментов, старые удаляются
+    #[serde(rename = "MaxFragments", default = "default_max_fragments")]
+    pub max_fragments: usize,
+}
+
+pub(crate) fn default_max_fragments() -> usize {
+    5000
+}
+
+/// Хранилище аппроксимированных фрагментов остывания прошлых партий.
+/// Фрагменты всех каналов одного рецепта хранятся в одном файле `<Recipe>.fragments.json`
+#[derive(Clone, Debug)]
+pub struct FragmentStore {
+    path: PathBuf,
+    max_fragments: usize,
+}
+
+impl FragmentStore {
+    pub fn new(directory: PathBuf, recipe: &str, max_fragments: usize) -> Self {
+        Self {
+            path: directory.join(format!("{}.fragments.json", recipe_file_name(recipe))),
+            max_fragments,
+        }
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    pub fn max_fragments(&self) -> usize {
+        self.max_fragments
+    }
+
+    /// Загрузить историю, отсутствующий файл - пустая история
+    pub fn load<T>(&self) -> std::io::Result<Vec<Fragment<T>>>
+    where
+        T: Float + Serialize + DeserializeOwned + Default,
+    {
+        Ok(load_json(&self.path)?.unwrap_or_default())
+    }
+
+    /// Дописать фрагменты к загруженной истории, сохраняются последние max_fragments
+    pub fn append<T>(&self, history: &mut Vec<Fragment<T>>, fragments: Vec<Fragment<T>>)
+    where
+        T: Float + Serialize,
+    {
+        history.extend(fragments);
+        if history.len() > self.max_fragments {
+            history.drain(..history.len() - self.max_fragments);
+        }
+    }
+
+    /// Перезаписать файл истории
+    pub fn save<T>(&self, history: &[Fragment<T>]) -> std::io::Result<()>
+    where
+        T: Float + Serialize,
+    {
+        save_json(&self.path, history)
+    }
+}
+
+/// Имя файла по рецепту: рецепт может содержать символы, недопустимые в имени файла
+pub(crate) fn recipe_file_name(recipe: &str) -> String {
+    recipe
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Загрузить JSON-файл хранилища, отсутствующий файл - None
+pub fn load_json<T: DeserializeOwned>(path: &Path) -> std::io::Result<Option<T>> {
+    match std::fs::File::open(path) {
+        Ok(file) => Ok(Some(serde_json::from_reader(std::io::BufReader::new(
+            file,
+        ))?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Записать JSON-файл хранилища через временный файл, чтобы сбой во время записи не испортил данные
+pub fn save_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let tmp_path = path.with_extension("json.tmp");
+    {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(&tmp_path)?);
+        serde_json::to_writer(&mut writer, value)?;
+        std::io::Write::flush(&mut writer)?;
+    }
+    std::fs::rename(tmp_path, path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::DataPoint;
+
+    /// Временный каталог теста, удаляется и при падении теста
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+            std::fs::remove_dir_all(&path).ok();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            std::fs::remove_dir_all(&self.0).ok();
+        }
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let dir = TempDir::new("store-json");
+        let path = dir.0.join("nested").join("RK-169.test.json");
+        assert_eq!(load_json::<Vec<u32>>(&path).unwrap(), None);
+
+        save_json(&path, &[1u32, 2, 3][..]).unwrap();
+        save_json(&path, &[4u32, 5][..]).unwrap();
+        assert_eq!(load_json::<Vec<u32>>(&path).unwrap(), Some(vec![4, 5]));
+        assert!(!path.with_extension("json.tmp").exists());
+
+        std::fs::write(&path, "{").unwrap();
+        assert!(load_json::<Vec<u32>>(&path).is_err());
+    }
+
+    fn fragment(start: u128) -> Fragment<f64> {
+        Fragment::new(
+            start,
+            &[DataPoint::new(0.0, 1.0), DataPoint::new(1.0, 2.0)],
+            Some((1.0, 1.0)),
+            0,
+        )
+    }
+
+    #[test]
+    fn append_and_trim() {
+        let store = FragmentStore::new(PathBuf::from("history"), "RK-169/32768", 3);
+        assert!(store.path().ends_with("RK-169_32768.fragments.json"));
+
+        let mut history = vec![];
+        store.append(&mut history, vec![fragment(1), fragment(2)]);
+        store.append(&mut history, vec![fragment(3), fragment(4)]);
+        assert_eq!(
+            history
+                .iter()
+                .map(|f| f.start_timestamp())
+                .collect::<Vec<_>>(),
+            vec![2.0, 3.0, 4.0]
+        );
+    }
+}
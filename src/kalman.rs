@@ -0,0 +1,166 @@
+use nalgebra::{Matrix2, Vector2};
+use serde::{Deserialize, Serialize};
+
+/// Начальная дисперсия дрейфа, (Гц/с)^2
+const INITIAL_DRIFT_VARIANCE: f64 = 1.0;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+pub struct MeasureKalmanConfig {
+    /// Спектральная плотность случайного изменения дрейфа, Гц^2/с^3
+    #[serde(rename = "ProcessNoise")]
+    pub process_noise: f64,
+
+    /// Дисперсия шума частотомера, Гц^2
+    #[serde(rename = "MeasurementNoise")]
+    pub measurement_noise: f64,
+
+    /// Максимальный дрейф, при котором частота считается стабильной, Гц/с
+    /// По окну из 4 отсчетов через 100 мс дрейф известен лишь с точностью ~0.4 Гц/с
+    #[serde(rename = "MaxDrift", default = "default_max_drift")]
+    pub max_drift: f64,
+
+    /// Количество стандартных отклонений, с которым проверяются условия стабильности
+    #[serde(rename = "Confidence", default = "default_confidence")]
+    pub confidence: f64,
+}
+
+fn default_max_drift() -> f64 {
+    1.0
+}
+
+fn default_confidence() -> f64 {
+    1.0
+}
+
+/// Фильтр Калмана частоты резонатора, состояние - (частота, скорость дрейфа)
+#[derive(Clone, Debug)]
+pub struct FrequencyKalman {
+    x: Vector2<f64>,
+    p: Matrix2<f64>,
+    q: f64,
+    r: f64,
+    initialized: bool,
+}
+
+impl FrequencyKalman {
+    pub fn new(process_noise: f64, measurement_noise: f64) -> Self {
+        Self {
+            x: Vector2::zeros(),
+            p: Matrix2::zeros(),
+            q: process_noise,
+            r: measurement_noise,
+            initialized: false,
+        }
+    }
+
+    pub fn from_config(config: &MeasureKalmanConfig) -> Self {
+        Self::new(config.process_noise, config.measurement_noise)
+    }
+
+    pub fn reset(&mut self) {
+        self.initialized = false;
+    }
+
+    /// Учесть измерение частоты z, полученное через dt секунд после предыдущего
+    pub fn update(&mut self, dt: f64, z: f64) {
+        if !self.initialized {
+            self.x = Vector2::new(z, 0.0);
+            self.p = Matrix2::new(self.r, 0.0, 0.0, INITIAL_DRIFT_VARIANCE);
+            self.initialized = true;
+            return;
+        }
+
+        // прогноз
+        let f = Matrix2::new(1.0, dt, 0.0, 1.0);
+        let q = Matrix2::new(dt.powi(3) / 3.0, dt.powi(2) / 2.0, dt.powi(2) / 2.0, dt) * self.q;
+        self.x = f * self.x;
+        self.p = f * self.p * f.transpose() + q;
+
+        // коррекция, измеряется только частота
+        let s = self.p[(0, 0)] + self.r;
+        let k = self.p.column(0) / s;
+        let innovation = z - self.x[0];
+        let kp = k * self.p.row(0);
+        self.x += k * innovation;
+        self.p -= kp;
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.initialized
+    }
+
+    /// Отфильтрованная частота, Гц
+    pub fn frequency(&self) -> f64 {
+        self.x[0]
+    }
+
+    /// Скорость дрейфа частоты, Гц/с
+    pub fn drift(&self) -> f64 {
+        self.x[1]
+    }
+
+    pub fn frequency_variance(&self) -> f64 {
+        self.p[(0, 0)]
+    }
+
+    pub fn drift_variance(&self) -> f64 {
+        self.p[(1, 1)]
+    }
+
+    /// Дрейф известен достаточно точно, чтобы судить о стабильности с порогом max_drift
+    pub fn is_converged(&self, max_drift: f64, confidence: f64) -> bool {
+        self.initialized && confidence * self.drift_variance().sqrt() <= max_drift
+    }
+
+    /// Модуль дрейфа не превышает max_drift с запасом в confidence стандартных отклонений
+    pub fn is_drift_below(&self, max_drift: f64, confidence: f64) -> bool {
+        self.initialized
+            && self.drift().abs() + confidence * self.drift_variance().sqrt() <= max_drift
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tracks_linear_drift() {
+        let mut kalman = FrequencyKalman::new(1e-4, 0.01);
+        for i in 0..200 {
+            let t = i as f64 * 0.1;
+            let noise = if i % 2 == 0 { 0.05 } else { -0.05 };
+            kalman.update(0.1, 32768.0 + 0.5 * t + noise);
+        }
+
+        assert!((kalman.drift() - 0.5).abs() < 0.05);
+        assert!((kalman.frequency() - (32768.0 + 0.5 * 19.9)).abs() < 0.1);
+        assert!(!kalman.is_drift_below(0.1, 2.0));
+    }
+
+    #[test]
+    fn stable_signal() {
+        let mut kalman = FrequencyKalman::new(1e-4, 0.01);
+        for i in 0..100 {
+            let noise = if i % 2 == 0 { 0.05 } else { -0.05 };
+            kalman.update(0.1, 32768.0 + noise);
+        }
+
+        assert!(kalman.drift().abs() < 0.05);
+        assert!(kalman.is_drift_below(0.2, 2.0));
+    }
+
+    #[test]
+    fn short_window_defaults() {
+        let config: MeasureKalmanConfig =
+            serde_json::from_str(r#"{"ProcessNoise": 0.0001, "MeasurementNoise": 0.01}"#).unwrap();
+
+        // 4 отсчета через 100 мс: дрейф еще плохо известен, но уже можно судить о стабильности
+        let mut kalman = FrequencyKalman::from_config(&config);
+        for (i, noise) in [0.05, -0.08, 0.02, -0.03].into_iter().enumerate() {
+            kalman.update(if i == 0 { 0.0 } else { 0.1 }, 32768.0 + noise);
+        }
+        assert!(kalman.is_converged(config.max_drift, config.confidence));
+        assert!(kalman.is_drift_below(config.max_drift, config.confidence));
+        assert!(!kalman.is_converged(0.05, 2.0));
+    }
+}
@@ -192,6 +192,19 @@ impl LaserController {
         burn_step: Option<i32>,
         trys: Option<usize>,
         soft_mode: bool,
+    ) -> Result<(), Error> {
+        self.burn_scaled(burn_count, burn_step, trys, soft_mode, 1.0)
+            .await
+    }
+
+    /// То же, что burn, но с дополнительным множителем мощности прожига
+    pub async fn burn_scaled(
+        &mut self,
+        burn_count: u32,
+        burn_step: Option<i32>,
+        trys: Option<usize>,
+        soft_mode: bool,
+        power_multiplier: f32,
     ) -> Result<(), Error> {
         // на самом деле ходим не прямоугольным зигзагом а "ёлочкой", чтобы меньше G-кода выполнять
 
@@ -203,6 +216,7 @@ impl LaserController {
         if soft_mode {
             s *= self.soft_mode_s_multiplier;
         }
+        s *= power_multiplier;
         let f = self.burn_laser_feedrate * ch_cfg.mul_laser_feedrate.unwrap_or(1.0);
 
         let mut commands = vec![GCodeCtrl::M3 { s }];
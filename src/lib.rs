@@ -4,16 +4,32 @@ mod laser_setup_controller;
 //mod precision_adjust;
 pub(crate) mod precision_adjust2;
 
+pub mod online_fit;
 pub mod predict;
 
+pub mod adjust_event;
 pub mod box_plot;
+pub mod channel_targets;
+pub mod crosstalk;
+pub mod drift;
+pub mod edge_store;
+pub mod filters;
+pub mod fragment_store;
+pub mod kalman;
+pub mod noise;
+pub mod sealing;
+pub mod shot_detector;
+pub mod strategy;
 pub mod coordinates;
 pub(crate) mod gcode_codec;
 pub(crate) mod gcode_ctrl;
 
 use num_traits::Float;
 
-pub use config::{AutoAdjustLimits, Config, ForecastConfig};
+pub use config::{
+    AdaptiveCooldownConfig, AutoAdjustLimits, Config, FiltersConfig, ForecastConfig,
+    AxisConfig, ResonatroPlacement, SchedulingConfig,
+};
 pub use laser_controller::LaserController;
 pub use laser_setup_controller::{LaserSetupController, LaserSetupStatus};
 pub use precision_adjust2::{Error, PrecisionAdjust2, Status, PrivStatusEvent};
@@ -23,6 +39,53 @@ pub struct AdjustConfig {
     pub target_freq: f32,
     pub work_offset_hz: f32,
     pub working_offset_ppm: f32,
+
+    /// Индивидуальные цели каналов, если заданы - важнее общей цели
+    pub channel_targets: Vec<channel_targets::ChannelTarget>,
+
+    /// Ожидаемый уход частоты при герметизации, если задан - настройка идет на смещенную цель
+    pub sealing: Option<sealing::SealingModel>,
+}
+
+impl AdjustConfig {
+    /// Запись таблицы целей для канала (нумерация с 0)
+    pub fn channel_target_entry(&self, channel: usize) -> Option<&channel_targets::ChannelTarget> {
+        self.channel_targets.iter().find(|t| t.channel == channel + 1)
+    }
+
+    /// Целевая частота канала (нумерация с 0), Гц
+    pub fn channel_target(&self, channel: usize) -> f32 {
+        self.channel_target_entry(channel)
+            .map(|t| t.target)
+            .unwrap_or(self.target_freq)
+    }
+
+    /// Ожидаемый уход частоты канала (нумерация с 0) с целью target при герметизации, Гц
+    pub fn sealing_shift_hz(&self, channel: usize, target: f32) -> f32 {
+        self.sealing
+            .as_ref()
+            .map(|m| m.shift(channel, target))
+            .unwrap_or_default()
+    }
+
+    /// Цель настройки канала (нумерация с 0) в вакууме с учетом ухода при герметизации, Гц
+    pub fn channel_trim_target(&self, channel: usize) -> f32 {
+        let target = self.channel_target(channel);
+        target - self.sealing_shift_hz(channel, target)
+    }
+
+    /// Допустимое отклонение частоты канала (нумерация с 0), Гц
+    pub fn channel_tolerance_hz(&self, channel: usize) -> f32 {
+        let target = self.channel_target(channel);
+        self.channel_target_entry(channel)
+            .and_then(|t| t.tolerance_hz)
+            .unwrap_or(target * self.working_offset_ppm / 1_000_000.0)
+    }
+
+    /// Допустимое отклонение частоты канала (нумерация с 0), ppm
+    pub fn channel_precision_ppm(&self, channel: usize) -> f32 {
+        self.channel_tolerance_hz(channel) / self.channel_target(channel) * 1_000_000.0
+    }
 }
 
 pub trait IDataPoint<T> {
@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+
+/// Отношение межквартильного размаха к стандартному отклонению нормального распределения
+const IQR_PER_SIGMA: f64 = 1.349;
+
+/// Минимальное количество усредняемых интервалов для оценки девиации Аллана
+const MIN_ALLAN_INTERVALS: usize = 2;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+pub struct NoiseConfig {
+    /// Время записи частоты каждого канала, мс
+    #[serde(rename = "ParkTimeMs", default = "default_park_time_ms")]
+    pub park_time_ms: u32,
+
+    /// Использовать результаты характеризации для окна измерения и порога стабильности автонастройки
+    #[serde(rename = "UseForAdjust", default)]
+    pub use_for_adjust: bool,
+
+    /// Запас порога стабильности относительно измеренного шума
+    #[serde(rename = "StableMargin", default = "default_stable_margin")]
+    pub stable_margin: f64,
+}
+
+fn default_park_time_ms() -> u32 {
+    30_000
+}
+
+fn default_stable_margin() -> f64 {
+    3.0
+}
+
+impl Default for NoiseConfig {
+    fn default() -> Self {
+        Self {
+            park_time_ms: default_park_time_ms(),
+            use_for_adjust: false,
+            stable_margin: default_stable_margin(),
+        }
+    }
+}
+
+/// Точка графика девиации Аллана
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct AllanPoint {
+    /// Время усреднения, с
+    pub tau: f64,
+    /// Коэффициент усреднения (tau / tau0)
+    pub m: usize,
+    /// Девиация Аллана, Гц
+    pub adev: f64,
+}
+
+/// Шумовые характеристики канала
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NoiseProfile {
+    /// Количество измерений
+    pub samples: usize,
+    /// Период измерений, с
+    pub tau0: f64,
+    /// Средняя частота, Гц
+    pub mean: f64,
+    /// Линейный дрейф частоты, Гц/с
+    pub drift: f64,
+    /// Уровень белого шума (девиация Аллана при tau0), Гц
+    pub white_noise: f64,
+    /// Девиация Аллана для tau = tau0 * 2^k
+    pub adev: Vec<AllanPoint>,
+}
+
+/// Перекрывающаяся девиация Аллана ряда частот с шагом tau0 для времени усреднения m * tau0
+pub fn allan_deviation(f: &[f64], m: usize) -> Option<f64> {
+    if m == 0 || f.len() < 2 * m + MIN_ALLAN_INTERVALS - 1 {
+        return None;
+    }
+
+    // скользящие средние по m точкам через накопленную сумму
+    let mut cumsum = Vec::with_capacity(f.len() + 1);
+    cumsum.push(0.0);
+    f.iter()
+        .for_each(|v| cumsum.push(cumsum.last().unwrap() + v));
+    let avg = |j: usize| (cumsum[j + m] - cumsum[j]) / m as f64;
+
+    let count = f.len() - 2 * m + 1;
+    let sum = (0..count)
+        .map(|j| (avg(j + m) - avg(j)).powi(2))
+        .sum::<f64>();
+
+    Some((sum / (2.0 * count as f64)).sqrt())
+}
+
+/// Линейная регрессия f(t), возвращает (наклон, среднее f)
+fn linear_drift(t: &[f64], f: &[f64]) -> (f64, f64) {
+    let n = t.len() as f64;
+    let t_mean = t.iter().sum::<f64>() / n;
+    let f_mean = f.iter().sum::<f64>() / n;
+
+    let (cov, var) = t
+        .iter()
+        .zip(f.iter())
+        .fold((0.0, 0.0), |(cov, var), (t, f)| {
+            (
+                cov + (t - t_mean) * (f - f_mean),
+                var + (t - t_mean).powi(2),
+            )
+        });
+
+    (if var > 0.0 { cov / var } else { 0.0 }, f_mean)
+}
+
+impl NoiseProfile {
+    /// Характеристики по ряду (время в секундах, частота в Гц), измерения считаются равноотстоящими
+    pub fn from_samples(t: &[f64], f: &[f64]) -> Option<Self> {
+        let samples = t.len().min(f.len());
+        if samples < 2 * MIN_ALLAN_INTERVALS + 1 {
+            return None;
+        }
+        let (t, f) = (&t[..samples], &f[..samples]);
+
+        let mut steps = t.windows(2).map(|w| w[1] - w[0]).collect::<Vec<_>>();
+        steps.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let tau0 = steps[steps.len() / 2];
+        if tau0.is_nan() || tau0 <= 0.0 {
+            return None;
+        }
+
+        let (drift, mean) = linear_drift(t, f);
+
+        let adev = std::iter::successors(Some(1usize), |m| Some(m * 2))
+            .map_while(|m| {
+                allan_deviation(f, m).map(|adev| AllanPoint {
+                    tau: tau0 * m as f64,
+                    m,
+                    adev,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Some(Self {
+            samples,
+            tau0,
+            mean,
+            drift,
+            white_noise: adev.first()?.adev,
+            adev,
+        })
+    }
+
+    /// Время усреднения с минимальной девиацией Аллана - дальше начинает преобладать дрейф
+    pub fn optimal_tau(&self) -> Option<AllanPoint> {
+        self.adev
+            .iter()
+            .min_by(|a, b| {
+                a.adev
+                    .partial_cmp(&b.adev)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .copied()
+    }
+
+    /// Рекомендуемое количество измерений в окне
+    pub fn suggested_measure_count(&self) -> usize {
+        self.optimal_tau().map(|p| p.m).unwrap_or(1)
+    }
+
+    /// Рекомендуемый порог межквартильного размаха окна измерений, при котором частота стабильна:
+    /// размах белого шума с запасом margin плюс уход частоты за время окна
+    pub fn suggested_stable_range(&self, margin: f64, window: usize) -> f64 {
+        margin * IQR_PER_SIGMA * self.white_noise + self.drift.abs() * self.tau0 * window as f64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // детерминированный шум с нулевым средним
+    fn noise(i: usize) -> f64 {
+        (((i * 7919) % 101) as f64 / 100.0 - 0.5) * 0.2
+    }
+
+    #[test]
+    fn allan_constant_and_alternating() {
+        assert_eq!(allan_deviation(&[5.0; 10], 1), Some(0.0));
+        assert_eq!(allan_deviation(&[5.0; 2], 1), None);
+
+        // чередование ±a: при m = 1 соседние разности 2a, девиация a * sqrt(2)
+        let f = (0..100)
+            .map(|i| if i % 2 == 0 { 1.0 } else { -1.0 })
+            .collect::<Vec<_>>();
+        assert!((allan_deviation(&f, 1).unwrap() - 2f64.sqrt()).abs() < 1e-12);
+        assert!(allan_deviation(&f, 2).unwrap() < 1e-12);
+    }
+
+    #[test]
+    fn profile_of_drifting_signal() {
+        let t = (0..400).map(|i| i as f64 * 0.1).collect::<Vec<_>>();
+        let f = t
+            .iter()
+            .enumerate()
+            .map(|(i, t)| 32768.0 + 0.01 * t + noise(i))
+            .collect::<Vec<_>>();
+
+        let profile = NoiseProfile::from_samples(&t, &f).unwrap();
+        assert!((profile.tau0 - 0.1).abs() < 1e-9);
+        assert!((profile.drift - 0.01).abs() < 1e-3);
+        assert!(profile.white_noise > 0.01 && profile.white_noise < 0.2);
+        assert_eq!(profile.adev.first().unwrap().m, 1);
+
+        // усреднение уменьшает белый шум, дрейф ограничивает оптимальное окно
+        let optimal = profile.optimal_tau().unwrap();
+        assert!(optimal.m > 1);
+        assert!(optimal.adev < profile.white_noise);
+        assert!(profile.suggested_stable_range(3.0, 5) > 3.0 * IQR_PER_SIGMA * profile.white_noise);
+    }
+}
@@ -0,0 +1,172 @@
+use nalgebra::{DVector, Scalar};
+use num_traits::{Float, FromPrimitive};
+use serde::Serialize;
+
+use crate::predict::{fit_quality, limit_exp, CooldownModel, NORMAL_T};
+
+/// Сетка скоростей остывания B (в единицах 1/NORMAL_T)
+const RATE_MIN: f64 = 0.1;
+const RATE_MAX: f64 = 100.0;
+const RATE_STEPS: usize = 64;
+
+/// Текущая оценка конечной частоты остывающего резонатора
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct OnlineEstimate<T> {
+    /// Прогноз частоты после охлаждения
+    pub target: T,
+    /// 95% интервал прогноза
+    pub interval: (T, T),
+    /// Количество точек от минимума
+    pub samples: usize,
+    /// Прогноз достаточно точен, ждать полного охлаждения не обязательно
+    pub confident: bool,
+}
+
+/// Рекурсивная аппроксимация y = A * (1 - exp(-x * B)) по мере поступления точек
+/// Для каждого B из логарифмической сетки накапливаются суммы МНК, по которым
+/// A находится без повторной аппроксимации всего фрагмента
+pub struct OnlineCooldownEstimator<T> {
+    rates: Vec<T>,
+    /// (sum(g^2), sum(g*y)) для каждого B, g = 1 - exp(-x * B)
+    sums: Vec<(T, T)>,
+    origin: Option<(T, T)>,
+    x: Vec<T>,
+    y: Vec<T>,
+    min_samples: usize,
+    confident_half_width: T,
+}
+
+impl<T> OnlineCooldownEstimator<T>
+where
+    T: Scalar + Float + FromPrimitive + nalgebra::RealField,
+{
+    /// min_samples - минимальное количество точек от минимума для уверенного прогноза
+    /// confident_half_width - допустимая полуширина интервала прогноза, Гц
+    pub fn new(min_samples: usize, confident_half_width: f64) -> Self {
+        let rates = (0..RATE_STEPS)
+            .map(|i| {
+                let k = i as f64 / (RATE_STEPS - 1) as f64;
+                let rate = RATE_MIN * (RATE_MAX / RATE_MIN).powf(k);
+                unsafe { <T as FromPrimitive>::from_f64(rate).unwrap_unchecked() }
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            sums: vec![(T::zero(), T::zero()); rates.len()],
+            rates,
+            origin: None,
+            x: vec![],
+            y: vec![],
+            min_samples,
+            confident_half_width: unsafe {
+                <T as FromPrimitive>::from_f64(confident_half_width).unwrap_unchecked()
+            },
+        }
+    }
+
+    /// Добавить точку (время в мс, частота), вернуть обновленную оценку
+    pub fn push(&mut self, t: T, f: T) -> Option<OnlineEstimate<T>> {
+        match self.origin {
+            Some((_, f_min)) if f >= f_min => {}
+            _ => {
+                // новый минимум - отсчет начинается заново
+                self.origin = Some((t, f));
+                self.sums
+                    .iter_mut()
+                    .for_each(|s| *s = (T::zero(), T::zero()));
+                self.x = vec![T::zero()];
+                self.y = vec![T::zero()];
+                return None;
+            }
+        }
+
+        let (t_zero, f_min) = self.origin.unwrap();
+        let normal_t = unsafe { <T as FromPrimitive>::from_f64(NORMAL_T).unwrap_unchecked() };
+        let x = (t - t_zero) / normal_t;
+        let y = f - f_min;
+        self.x.push(x);
+        self.y.push(y);
+
+        self.rates
+            .iter()
+            .zip(self.sums.iter_mut())
+            .for_each(|(b, (s_gg, s_gy))| {
+                let g = T::one() - Float::exp(-x * *b);
+                *s_gg += g * g;
+                *s_gy += g * y;
+            });
+
+        self.estimate()
+    }
+
+    /// Оценка по накопленным точкам
+    pub fn estimate(&self) -> Option<OnlineEstimate<T>> {
+        let (_, f_min) = self.origin?;
+        if self.x.len() < 3 {
+            return None;
+        }
+
+        // для фиксированного B: A = sum(g*y) / sum(g^2), RSS = sum(y^2) - A * sum(g*y)
+        let (a, b) = self
+            .rates
+            .iter()
+            .zip(self.sums.iter())
+            .filter(|(_, (s_gg, _))| *s_gg > T::zero())
+            .map(|(b, (s_gg, s_gy))| (*s_gy / *s_gg, *s_gy * *s_gy / *s_gg, *b))
+            .max_by(|l, r| l.1.partial_cmp(&r.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(a, _, b)| (a, b))?;
+
+        let model = CooldownModel::Exp { a, b };
+        let x = DVector::from_vec(self.x.clone());
+        let y = DVector::from_vec(self.y.clone());
+        let rss = (limit_exp(&x, b) * a - &y).norm_squared();
+
+        let (interval, confident) = if let Some(quality) = fit_quality(&model, &x, &y, rss) {
+            let half_width = (quality.interval.1 - quality.interval.0)
+                / unsafe { <T as FromPrimitive>::from_f64(2.0).unwrap_unchecked() };
+            (
+                (f_min + quality.interval.0, f_min + quality.interval.1),
+                self.x.len() >= self.min_samples
+                    && a >= T::zero()
+                    && half_width <= self.confident_half_width,
+            )
+        } else {
+            ((f_min + a, f_min + a), false)
+        };
+
+        Some(OnlineEstimate {
+            target: f_min + a,
+            interval,
+            samples: self.x.len(),
+            confident,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn converges_on_clean_exp() {
+        let mut estimator = OnlineCooldownEstimator::<f64>::new(5, 0.05);
+
+        // спад частоты при нагреве, затем остывание с A = 0.5, B = 3
+        let mut last = None;
+        for (i, f) in [100.0, 99.8, 99.6].into_iter().enumerate() {
+            last = estimator.push(i as f64 * 100.0, f);
+        }
+        assert!(last.is_none());
+
+        for i in 1..30 {
+            let t = i as f64 * 100.0;
+            let f = 99.6 + 0.5 * (1.0 - (-t / NORMAL_T * 3.0).exp());
+            last = estimator.push(200.0 + t, f);
+        }
+
+        let estimate = last.unwrap();
+        assert!((estimate.target - 100.1).abs() < 0.05);
+        assert!(estimate.interval.0 <= estimate.target && estimate.interval.1 >= estimate.target);
+        assert!(estimate.confident);
+    }
+}
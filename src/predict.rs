@@ -1,11 +1,22 @@
-use std::{fmt::Debug, marker::PhantomData, sync::Arc};
+use std::{collections::VecDeque, fmt::Debug, marker::PhantomData, sync::Arc};
 
-use nalgebra::{DVector, Scalar};
+use nalgebra::{DMatrix, DVector, Scalar};
 use num_traits::Float;
 use serde::Serialize;
-use tokio::sync::{watch::Receiver, Mutex};
-
-use crate::{box_plot::BoxPlot, DataPoint, ForecastConfig, IDataPoint, Status};
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    watch::Receiver,
+    Mutex,
+};
+
+use crate::{
+    box_plot::{quantile_sorted, BoxPlot, QuantileMethod},
+    filters::{apply_filters, FilterStage},
+    fragment_store::{default_max_fragments, FragmentStore},
+    online_fit::{OnlineCooldownEstimator, OnlineEstimate},
+    shot_detector::OnlineShotDetector,
+    AdaptiveCooldownConfig, DataPoint, ForecastConfig, IDataPoint, Status,
+};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Prediction<T: Float> {
@@ -19,34 +30,156 @@ pub struct Predictor<T: Float + Serialize> {
     forecast_config: ForecastConfig,
     serie_data: Arc<Mutex<Vec<(u128, f32)>>>,
     status_rx: Receiver<Status>,
+    fragment_len: usize,
+    shot_mismatches: Arc<Mutex<Vec<ShotMismatch>>>,
+    online: Arc<Mutex<Option<OnlineState<T>>>>,
+    history_store: Option<FragmentStore>,
+    adaptive_cooldown: Option<AdaptiveCooldownConfig>,
+    cooldown_cache: Arc<Mutex<CooldownCache>>,
+    /// Прирост частоты за выстрел по фрагментам прошлых партий
+    history_growth: Vec<T>,
     _t: PhantomData<T>,
 }
 
+/// Оценка конечной частоты для собираемого в данный момент фрагмента
+struct OnlineState<T> {
+    channel: usize,
+    start_timestamp: u128,
+    estimator: OnlineCooldownEstimator<T>,
+    last: Option<OnlineEstimate<T>>,
+}
+
+/// Общие с [`Predictor`] данные и настройки фоновой задачи разбора статуса
+struct TaskContext<T: Float + Serialize> {
+    fragments: Arc<Mutex<Vec<Vec<Fragment<T>>>>>,
+    fragment_len: usize,
+    serie_data: Arc<Mutex<Vec<(u128, f32)>>>,
+    shot_mismatches: Arc<Mutex<Vec<ShotMismatch>>>,
+    online: Arc<Mutex<Option<OnlineState<T>>>>,
+    forecast_config: ForecastConfig,
+    filters: Vec<FilterStage>,
+    adaptive_cooldown: Option<AdaptiveCooldownConfig>,
+    cooldown_cache: Arc<Mutex<CooldownCache>>,
+}
+
 pub const NORMAL_T: f64 = 1000.0;
 
+/// Количество шагов поиска времени установления двухэкспоненциальной модели
+const SETTLE_BISECTION_STEPS: usize = 50;
+
+/// Максимальное расхождение метки выстрела и обнаруженного по сигналу начала спада, мс
+const SHOT_MATCH_TOLERANCE_MS: u128 = 1000;
+/// Время ожидания парного события, после которого выстрел считается несовпавшим, мс
+const SHOT_CONFIRM_TIMEOUT_MS: u128 = 10000;
+/// Коэффициент сглаживания частоты для детектора выстрелов
+const SHOT_DETECTOR_ALPHA: f64 = 0.3;
+/// Сколько последних несовпавших выстрелов хранится
+const MAX_SHOT_MISMATCHES: usize = 1000;
+
+/// Вид расхождения меток выстрелов и выстрелов, обнаруженных по сигналу
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum ShotMismatchKind {
+    /// Метка выстрела есть, реакции в сигнале не обнаружено
+    MarkOnly,
+    /// В сигнале обнаружен выстрел без метки
+    DetectedOnly,
+}
+
+/// Несовпавший выстрел
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct ShotMismatch {
+    /// Время от начала работы, мс
+    pub timestamp: u128,
+    pub channel: u32,
+    pub kind: ShotMismatchKind,
+}
+
 impl<T> Predictor<T>
 where
-    T: Float + num_traits::FromPrimitive + csaps::Real + nalgebra::RealField + Serialize + 'static,
+    T: Float
+        + num_traits::FromPrimitive
+        + csaps::Real
+        + nalgebra::RealField
+        + Serialize
+        + serde::de::DeserializeOwned
+        + Default
+        + 'static,
 {
     pub fn new(
         rx: Receiver<Status>,
         forecast_config: ForecastConfig,
         channels_count: usize,
         fragment_len: usize,
+        filters: Vec<FilterStage>,
+        history_store: Option<FragmentStore>,
+        adaptive_cooldown: Option<AdaptiveCooldownConfig>,
     ) -> Self {
         let fragments = Arc::new(Mutex::new(vec![vec![]; channels_count]));
         let serie_data = Arc::new(Mutex::new(vec![]));
+        let shot_mismatches = Arc::new(Mutex::new(vec![]));
+        let online = Arc::new(Mutex::new(None));
+        let cooldown_cache = Arc::new(Mutex::new(CooldownCache::default()));
+
+        let history = match history_store.as_ref().map(|store| store.load::<T>()) {
+            Some(Ok(history)) => Some(history),
+            Some(Err(e)) => {
+                tracing::error!("Failed to load fragment history: {e}");
+                None
+            }
+            None => None,
+        };
+        let history_growth = history
+            .as_ref()
+            .map(|history| {
+                let growth = history
+                    .iter()
+                    .filter_map(|f| fragment_growth(f, fragment_len / 2))
+                    .collect::<Vec<_>>();
+                tracing::info!(
+                    "Loaded {} history fragments ({} usable)",
+                    history.len(),
+                    growth.len()
+                );
+                growth
+            })
+            .unwrap_or_default();
+
+        // завершенные фрагменты сохраняются в историю по мере поступления,
+        // непрочитанный файл истории не перезаписывается
+        let history_tx = history_store.clone().zip(history).map(|(store, history)| {
+            let (tx, rx) = unbounded_channel();
+            tokio::spawn(Self::history_task(store, history, rx));
+            tx
+        });
+
+        tokio::spawn(Self::task(
+            rx.clone(),
+            TaskContext {
+                fragments: fragments.clone(),
+                fragment_len,
+                serie_data: serie_data.clone(),
+                shot_mismatches: shot_mismatches.clone(),
+                online: online.clone(),
+                forecast_config,
+                filters,
+                adaptive_cooldown,
+                cooldown_cache: cooldown_cache.clone(),
+            },
+            history_tx,
+        ));
 
-        {
-            let fragments = fragments.clone();
-            let serie_data = serie_data.clone();
-            tokio::spawn(Self::task(rx.clone(), fragments, fragment_len, serie_data));
-        }
         Self {
             fragments,
             forecast_config,
             serie_data,
             status_rx: rx,
+            fragment_len,
+            shot_mismatches,
+            online,
+            history_store,
+            adaptive_cooldown,
+            cooldown_cache,
+            history_growth,
             _t: PhantomData::<T>,
         }
     }
@@ -65,25 +198,171 @@ where
         Ok(())
     }
 
+    /// Ограничение количества фрагментов истории
+    fn history_limit(&self) -> usize {
+        self.history_store
+            .as_ref()
+            .map(|store| store.max_fragments())
+            .unwrap_or_else(default_max_fragments)
+    }
+
+    /// Сброс перед новой партией, фрагменты текущей партии переносятся в историю
     pub async fn reset(&mut self) {
-        self.fragments
+        let mut fragments = self.fragments.lock().await;
+        let batch = fragments
+            .iter_mut()
+            .flat_map(|ch| ch.drain(..))
+            .filter(|f| f.is_valid())
+            .collect::<Vec<_>>();
+        drop(fragments);
+        self.cooldown_cache.lock().await.invalidate();
+
+        // в хранилище фрагменты уже записаны по завершении, здесь - только прогноз
+        let min_len = self.fragment_len / 2;
+        self.history_growth
+            .extend(batch.iter().filter_map(|f| fragment_growth(f, min_len)));
+        let limit = self.history_limit();
+        if self.history_growth.len() > limit {
+            let excess = self.history_growth.len() - limit;
+            self.history_growth.drain(..excess);
+        }
+
+        self.shot_mismatches.lock().await.clear();
+    }
+
+    /// Текущая оценка конечной частоты канала, если он сейчас остывает после выстрела
+    /// Возвращает таймштамп начала фрагмента (как Fragment::start_timestamp) и оценку
+    pub async fn get_online_estimate(&self, channel: u32) -> Option<(f64, OnlineEstimate<T>)> {
+        let guard = self.online.lock().await;
+        let state = guard.as_ref()?;
+        if state.channel == channel as usize {
+            state.last.map(|e| (state.start_timestamp as f64, e))
+        } else {
+            None
+        }
+    }
+
+    /// Время остывания канала по последним фрагментам, мс, если включено адаптивное остывание
+    pub async fn cooldown_time_ms(&self, channel: u32) -> Option<f64> {
+        let config = self.adaptive_cooldown.as_ref()?;
+        Some(
+            cooldown_ms(
+                &self.cooldown_cache,
+                &self.fragments,
+                channel as usize,
+                self.forecast_config.adaptive_window,
+                config,
+            )
+            .await,
+        )
+    }
+
+    /// Последние выстрелы канала, для которых метка и обнаружение по сигналу не совпали
+    pub async fn shot_mismatches(&self, channel: u32) -> Vec<ShotMismatch> {
+        self.shot_mismatches
             .lock()
             .await
-            .iter_mut()
-            .for_each(|ch| ch.clear());
+            .iter()
+            .filter(|m| m.channel == channel)
+            .copied()
+            .collect()
+    }
+
+    /// Запись завершенных фрагментов в хранилище истории, накопившиеся - одной записью.
+    /// История держится в памяти, файл только перезаписывается
+    async fn history_task(
+        store: FragmentStore,
+        mut history: Vec<Fragment<T>>,
+        mut rx: UnboundedReceiver<Fragment<T>>,
+    ) {
+        while let Some(fragment) = rx.recv().await {
+            let mut batch = vec![fragment];
+            while let Ok(fragment) = rx.try_recv() {
+                batch.push(fragment);
+            }
+            store.append(&mut history, batch);
+
+            let store = store.clone();
+            match tokio::task::spawn_blocking(move || {
+                let res = store.save(&history);
+                (history, res)
+            })
+            .await
+            {
+                Ok((h, res)) => {
+                    history = h;
+                    if let Err(e) = res {
+                        tracing::error!("Failed to save fragment history: {e}");
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Fragment history task failed: {e}");
+                    return;
+                }
+            }
+        }
     }
 
     async fn task(
         mut status_rx: Receiver<Status>,
-        fragments: Arc<Mutex<Vec<Vec<Fragment<T>>>>>,
-        fragment_len: usize,
-        serie_data: Arc<Mutex<Vec<(u128, f32)>>>,
+        ctx: TaskContext<T>,
+        history_tx: Option<UnboundedSender<Fragment<T>>>,
     ) {
+        let TaskContext {
+            fragments,
+            fragment_len,
+            serie_data,
+            shot_mismatches,
+            online,
+            forecast_config,
+            filters,
+            adaptive_cooldown,
+            cooldown_cache,
+        } = ctx;
         let mut current_chanel = None;
+        let mut shot_detector = OnlineShotDetector::<T>::new(SHOT_DETECTOR_ALPHA);
+        let mut cross_check = ShotCrossCheck::default();
+        let mut detector_channel = None;
         loop {
             status_rx.changed().await.ok();
 
-            let new_status = status_rx.borrow().clone();
+            let new_status = *status_rx.borrow();
+
+            // Независимое обнаружение выстрелов по сигналу и сверка с метками
+            {
+                let timestamp = new_status.since_start.as_millis();
+                if detector_channel != Some(new_status.current_channel) {
+                    // смена канала - скачок частоты, не выстрел
+                    detector_channel.replace(new_status.current_channel);
+                    shot_detector.reset();
+                }
+                if new_status.shot_mark {
+                    cross_check.mark(timestamp, new_status.current_channel);
+                }
+                if let Some(start) = shot_detector.push(timestamp, unsafe {
+                    T::from_f32(new_status.current_frequency).unwrap_unchecked()
+                }) {
+                    cross_check.detected(start, new_status.current_channel);
+                }
+
+                let expired = cross_check.expire(timestamp);
+                if !expired.is_empty() {
+                    expired.iter().for_each(|m| {
+                        tracing::warn!(
+                            "Shot mismatch: {:?} at {} ms, channel {}",
+                            m.kind,
+                            m.timestamp,
+                            m.channel
+                        )
+                    });
+                    let mut guard = shot_mismatches.lock().await;
+                    guard.extend(expired);
+                    if guard.len() > MAX_SHOT_MISMATCHES {
+                        let excess = guard.len() - MAX_SHOT_MISMATCHES;
+                        guard.drain(..excess);
+                    }
+                }
+            }
 
             if new_status.shot_mark {
                 // выстрел - фиксируем канал
@@ -93,25 +372,63 @@ where
                 {
                     let mut guard = serie_data.lock().await;
                     if guard.len() > 3 {
-                        consume_fragment(&guard, new_status.current_channel as usize, &fragments)
-                            .await;
+                        let fragment = consume_fragment(
+                            &guard,
+                            new_status.current_channel as usize,
+                            &fragments,
+                            &filters,
+                            &forecast_config,
+                        )
+                        .await;
+                        cooldown_cache.lock().await.invalidate();
+                        send_to_history(&history_tx, fragment);
                     }
                     // drop data
                     guard.clear();
                 }
+                online.lock().await.take();
             } else if current_chanel != Some(new_status.current_channel) {
                 // произошла смена канала, выстрелов не было, так что снимаем определенность канала.
                 current_chanel = None;
                 // drop data
                 serie_data.lock().await.clear();
+                online.lock().await.take();
             } else {
                 // продолжаем обработку текущего окна
+                let cooldown = match (adaptive_cooldown, current_chanel) {
+                    (Some(config), Some(ch)) => Some((
+                        cooldown_ms(
+                            &cooldown_cache,
+                            &fragments,
+                            ch as usize,
+                            forecast_config.adaptive_window,
+                            &config,
+                        )
+                        .await,
+                        config.max_ms as f64,
+                    )),
+                    _ => None,
+                };
                 let mut guard = serie_data.lock().await;
-                if current_chanel.is_some() && guard.len() < fragment_len {
-                    guard.push((
-                        new_status.since_start.as_millis(),
-                        new_status.current_frequency,
-                    ))
+                if current_chanel.is_some() && !cooldown_complete(&guard, fragment_len, cooldown) {
+                    let timestamp = new_status.since_start.as_millis();
+                    guard.push((timestamp, new_status.current_frequency));
+
+                    // уточнение прогноза по мере остывания
+                    let mut online = online.lock().await;
+                    let state = online.get_or_insert_with(|| OnlineState {
+                        channel: new_status.current_channel as usize,
+                        start_timestamp: timestamp,
+                        estimator: OnlineCooldownEstimator::new(
+                            forecast_config.confident_min_samples,
+                            forecast_config.confident_interval_hz as f64,
+                        ),
+                        last: None,
+                    });
+                    state.last = state.estimator.push(
+                        unsafe { T::from_u128(timestamp).unwrap_unchecked() },
+                        unsafe { T::from_f32(new_status.current_frequency).unwrap_unchecked() },
+                    );
                 } else {
                     let cc = current_chanel.unwrap() as usize;
 
@@ -119,7 +436,10 @@ where
                     current_chanel = None;
 
                     // Сбор закончен
-                    consume_fragment(&guard, cc, &fragments).await;
+                    let fragment =
+                        consume_fragment(&guard, cc, &fragments, &filters, &forecast_config).await;
+                    cooldown_cache.lock().await.invalidate();
+                    send_to_history(&history_tx, fragment);
                 }
             }
         }
@@ -159,18 +479,59 @@ where
 
     /// Получить прогноз для изменения частоты для текущего канала если произвести
     /// выстрел сейчас
-    pub async fn get_prediction(&self, _channel: u32, f_start: T) -> Option<Prediction<T>> {
-        // Static prediction
-        unsafe {
-            Some(Prediction {
-                minimal: T::from_f32(self.forecast_config.min_freq_grow).unwrap_unchecked()
-                    + f_start,
-                maximal: T::from_f32(self.forecast_config.max_freq_grow).unwrap_unchecked()
-                    + f_start,
-                median: T::from_f32(self.forecast_config.median_freq_grow).unwrap_unchecked()
-                    + f_start,
-            })
-        }
+    /// Распределение прироста частоты за выстрел оценивается по последним фрагментам канала,
+    /// при недостатке данных - смешивается с общей статистикой по всем каналам и статическим прогнозом
+    pub async fn get_prediction(&self, channel: u32, f_start: T) -> Option<Prediction<T>> {
+        let static_growth = unsafe {
+            Prediction {
+                minimal: T::from_f32(self.forecast_config.min_freq_grow).unwrap_unchecked(),
+                maximal: T::from_f32(self.forecast_config.max_freq_grow).unwrap_unchecked(),
+                median: T::from_f32(self.forecast_config.median_freq_grow).unwrap_unchecked(),
+            }
+        };
+
+        let (channel_growth, pooled_growth) = {
+            let guard = self.fragments.lock().await;
+            let window = self.forecast_config.adaptive_window;
+            let min_len = self.fragment_len / 2;
+
+            let channel_growth = guard
+                .get(channel as usize)
+                .map(|ch| recent_growth(ch, window, min_len))
+                .unwrap_or_default();
+            let pooled_growth = guard
+                .iter()
+                .flat_map(|ch| recent_growth(ch, window, min_len))
+                .collect::<Vec<_>>();
+            (channel_growth, pooled_growth)
+        };
+
+        let k = self.forecast_config.adaptive_min_shots;
+        // история прошлых партий заменяет статический прогноз,
+        // текущая партия перевешивает ее после history_weight выстрелов
+        let (history, k_pooled) = if self.history_growth.is_empty() {
+            (static_growth, k)
+        } else {
+            (
+                blend_growth(&self.history_growth, static_growth, k),
+                self.forecast_config.history_weight,
+            )
+        };
+        let pooled = blend_growth(&pooled_growth, history, k_pooled);
+        let growth = blend_growth(&channel_growth, pooled, k);
+
+        // прогноз без роста частоты бесполезен
+        let growth = if growth.maximal > T::zero() {
+            growth
+        } else {
+            static_growth
+        };
+
+        Some(Prediction {
+            minimal: growth.minimal + f_start,
+            maximal: growth.maximal + f_start,
+            median: growth.median + f_start,
+        })
     }
 
     pub async fn capture(&self) -> Vec<(u128, f32)> {
@@ -189,6 +550,12 @@ pub struct Fragment<T: Float + Serialize> {
     raw_points: Vec<DataPoint<T>>,
     coeffs: Option<(T, T)>,
     min_index: usize,
+    /// Выбранная модель остывания, в старых файлах отсутствует - тогда используется coeffs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model: Option<CooldownModel<T>>,
+    /// Оценка качества аппроксимации
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    quality: Option<FitQuality<T>>,
 }
 
 impl<T: Serialize> Fragment<T>
@@ -216,11 +583,43 @@ where
             raw_points: raw_points.to_vec(),
             coeffs,
             min_index,
+            model: None,
+            quality: None,
+        }
+    }
+
+    // Создать фрагмент с произвольной моделью остывания
+    // quality - оценка качества аппроксимации, если удалось рассчитать
+    pub fn with_model(
+        start_timestamp: u128,
+        raw_points: &[DataPoint<T>],
+        model: Option<CooldownModel<T>>,
+        quality: Option<FitQuality<T>>,
+        min_index: usize,
+    ) -> Self {
+        let coeffs = match model {
+            Some(CooldownModel::Exp { a, b }) => Some((a, b)),
+            _ => None,
+        };
+
+        Self {
+            start_timestamp: start_timestamp as f64,
+            raw_points: raw_points.to_vec(),
+            coeffs,
+            min_index,
+            model,
+            quality,
         }
     }
 
     pub fn is_valid(&self) -> bool {
-        self.coeffs.is_some()
+        self.model().is_some()
+    }
+
+    // Модель остывания, которой аппроксимирован фрагмент
+    pub fn model(&self) -> Option<CooldownModel<T>> {
+        self.model
+            .or_else(|| self.coeffs.map(|(a, b)| CooldownModel::Exp { a, b }))
     }
 
     pub fn points(&self) -> &[DataPoint<T>] {
@@ -235,6 +634,7 @@ where
     // Коэффициенты аппроксимации функцией y = A * (1 - exp(-x * B))
     // Принимается, что исходная кривя смещена таким образом, что минимум
     // находится в точке (0, 0)
+    // Для других моделей - None, см. model()
     pub fn aprox_coeffs(&self) -> Option<(T, T)> {
         self.coeffs
     }
@@ -242,7 +642,7 @@ where
     // Рассчитать аппроксимированную кривую начинаи подвинуть
     // её в точку (x_start, y_offset)
     pub fn evaluate(&self) -> Vec<DataPoint<T>> {
-        if let Some(coeffs) = &self.coeffs {
+        if let Some(model) = self.model() {
             let normal_t = unsafe { T::from_f64(NORMAL_T).unwrap_unchecked() };
 
             self.raw_points
@@ -273,7 +673,8 @@ where
                     );
 
                     x /= normal_t;
-                    let y = (limit_exp(&x, coeffs.1) * coeffs.0)
+                    let y = model
+                        .evaluate(&x)
                         .add_scalar(self.raw_points[self.min_index].y);
 
                     x.iter()
@@ -287,6 +688,29 @@ where
         }
     }
 
+    // Оценка качества аппроксимации
+    pub fn fit_quality(&self) -> Option<&FitQuality<T>> {
+        self.quality.as_ref()
+    }
+
+    // Интервал прогноза конечной частоты после охлаждения
+    pub fn target_interval(&self) -> Option<(T, T)> {
+        self.quality.as_ref().map(|q| {
+            let y_min = self.raw_points[self.min_index].y();
+            (y_min + q.interval.0, y_min + q.interval.1)
+        })
+    }
+
+    // Время от минимума частоты до достижения доли fraction конечного прироста, мс
+    pub fn settle_time_ms(&self, fraction: f64) -> Option<f64> {
+        let fraction = T::from_f64(fraction)?;
+        self.model()?
+            .settle_time(fraction)?
+            .to_f64()
+            .map(|x| x * NORMAL_T)
+            .filter(|t| t.is_finite())
+    }
+
     // Таймштамп начала фрагмента
     pub fn start_timestamp(&self) -> f64 {
         self.start_timestamp
@@ -297,9 +721,10 @@ where
     }
 
     // Предсказать конечную частоту после охлаждения
+    // Линейный дрейф модели ExpLinear не учитывается
     pub fn target(&self) -> T {
-        if let Some((a, _)) = self.coeffs {
-            self.raw_points[self.min_index].y() + a
+        if let Some(model) = self.model() {
+            self.raw_points[self.min_index].y() + model.asymptote()
         } else {
             self.box_plot().upper_bound()
         }
@@ -310,7 +735,279 @@ unsafe impl<T: Float + Serialize> Send for Fragment<T> {}
 
 //-----------------------------------------------------------------------------
 
-fn hard_filter<T: Float + num_traits::NumOps + num_traits::FromPrimitive + serde::Serialize + Copy>(data: &mut [T]) {
+/// Сверка меток выстрелов с выстрелами, обнаруженными по сигналу
+#[derive(Default)]
+struct ShotCrossCheck {
+    pending_marks: VecDeque<(u128, u32)>,
+    pending_detected: VecDeque<(u128, u32)>,
+}
+
+impl ShotCrossCheck {
+    fn mark(&mut self, timestamp: u128, channel: u32) {
+        if !Self::take_match(&mut self.pending_detected, timestamp, channel) {
+            self.pending_marks.push_back((timestamp, channel));
+        }
+    }
+
+    fn detected(&mut self, timestamp: u128, channel: u32) {
+        if !Self::take_match(&mut self.pending_marks, timestamp, channel) {
+            self.pending_detected.push_back((timestamp, channel));
+        }
+    }
+
+    /// Удалить и вернуть события, не дождавшиеся пары
+    fn expire(&mut self, now: u128) -> Vec<ShotMismatch> {
+        let mut res = vec![];
+        for (queue, kind) in [
+            (&mut self.pending_marks, ShotMismatchKind::MarkOnly),
+            (&mut self.pending_detected, ShotMismatchKind::DetectedOnly),
+        ] {
+            while let Some((timestamp, channel)) = queue.front().copied() {
+                if now.saturating_sub(timestamp) <= SHOT_CONFIRM_TIMEOUT_MS {
+                    break;
+                }
+                queue.pop_front();
+                res.push(ShotMismatch {
+                    timestamp,
+                    channel,
+                    kind,
+                });
+            }
+        }
+        res
+    }
+
+    fn take_match(queue: &mut VecDeque<(u128, u32)>, timestamp: u128, channel: u32) -> bool {
+        if let Some(pos) = queue.iter().position(|(ts, ch)| {
+            *ch == channel && ts.abs_diff(timestamp) <= SHOT_MATCH_TOLERANCE_MS
+        }) {
+            queue.remove(pos);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+//-----------------------------------------------------------------------------
+
+/// Квантили прогноза: минимальный и максимальный прирост частоты за выстрел
+const GROWTH_Q_MIN: f64 = 0.05;
+const GROWTH_Q_MAX: f64 = 0.95;
+
+/// Прирост частоты за выстрел для последних `window` удачно-аппроксимированных фрагментов
+/// не короче `min_len` точек (прерванные следующим выстрелом фрагменты не учитываются)
+fn recent_growth<T>(fragments: &[Fragment<T>], window: usize, min_len: usize) -> Vec<T>
+where
+    T: Float
+        + num_traits::FromPrimitive
+        + nalgebra::Scalar
+        + std::ops::MulAssign
+        + std::ops::AddAssign
+        + std::ops::DivAssign
+        + Serialize,
+{
+    fragments
+        .iter()
+        .rev()
+        .filter(|f| f.is_valid() && f.points().len() >= min_len)
+        .take(window)
+        .filter_map(|f| fragment_growth(f, min_len))
+        .collect()
+}
+
+/// Оценки времени остывания по каналам: пересчет только после появления нового фрагмента,
+/// а не на каждое измерение
+#[derive(Default)]
+struct CooldownCache {
+    values: Vec<Option<f64>>,
+    /// Номер сброса: оценка, начатая до сброса, не сохраняется
+    generation: u64,
+}
+
+impl CooldownCache {
+    fn cached(&self, channel: usize) -> Option<f64> {
+        self.values.get(channel).copied().flatten()
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn insert(&mut self, channel: usize, value: f64, generation: u64) {
+        if generation != self.generation {
+            return;
+        }
+        if self.values.len() <= channel {
+            self.values.resize(channel + 1, None);
+        }
+        self.values[channel] = Some(value);
+    }
+
+    /// Фрагменты изменились: при недостатке своих оценка канала берется по всем каналам,
+    /// поэтому сбрасываются все
+    fn invalidate(&mut self) {
+        self.values.clear();
+        self.generation += 1;
+    }
+}
+
+/// Время остывания канала из кэша, при отсутствии - оценка по фрагментам.
+/// Блокировки кэша и фрагментов берутся по очереди и одновременно не удерживаются
+async fn cooldown_ms<T>(
+    cache: &Mutex<CooldownCache>,
+    fragments: &Mutex<Vec<Vec<Fragment<T>>>>,
+    channel: usize,
+    window: usize,
+    config: &AdaptiveCooldownConfig,
+) -> f64
+where
+    T: Float
+        + num_traits::FromPrimitive
+        + nalgebra::Scalar
+        + std::ops::MulAssign
+        + std::ops::AddAssign
+        + std::ops::DivAssign
+        + Serialize,
+{
+    let generation = {
+        let guard = cache.lock().await;
+        if let Some(value) = guard.cached(channel) {
+            return value;
+        }
+        guard.generation()
+    };
+
+    let value = estimate_cooldown_ms(&fragments.lock().await, channel, window, config);
+    cache.lock().await.insert(channel, value, generation);
+    value
+}
+
+/// Время остывания канала: медиана времени установления последних `window` фрагментов канала,
+/// при их отсутствии - всех каналов, при отсутствии фрагментов - максимальное.
+/// Результат ограничен пределами из config
+fn estimate_cooldown_ms<T>(
+    fragments: &[Vec<Fragment<T>>],
+    channel: usize,
+    window: usize,
+    config: &AdaptiveCooldownConfig,
+) -> f64
+where
+    T: Float
+        + num_traits::FromPrimitive
+        + nalgebra::Scalar
+        + std::ops::MulAssign
+        + std::ops::AddAssign
+        + std::ops::DivAssign
+        + Serialize,
+{
+    let settle_times = |ch: &[Fragment<T>]| {
+        ch.iter()
+            .rev()
+            .filter_map(|f| f.settle_time_ms(config.settle_fraction))
+            .take(window)
+            .collect::<Vec<_>>()
+    };
+
+    let mut times = fragments
+        .get(channel)
+        .map(|ch| settle_times(ch))
+        .unwrap_or_default();
+    if times.is_empty() {
+        times = fragments.iter().flat_map(|ch| settle_times(ch)).collect();
+    }
+    if times.is_empty() {
+        return config.max_ms as f64;
+    }
+
+    times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    quantile_sorted(&times, 0.5, QuantileMethod::Linear)
+        .clamp(config.min_ms as f64, config.max_ms as f64)
+}
+
+/// Сбор фрагмента закончен: по количеству точек, либо, при адаптивном остывании
+/// cooldown = (время установления, максимальное время), - по времени от минимума частоты
+fn cooldown_complete(
+    data: &[(u128, f32)],
+    fragment_len: usize,
+    cooldown: Option<(f64, f64)>,
+) -> bool {
+    match cooldown {
+        None => data.len() >= fragment_len,
+        Some((settle_ms, max_ms)) => {
+            let (Some(first), Some(last)) = (data.first(), data.last()) else {
+                return false;
+            };
+            let t_min = data
+                .iter()
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+                .map_or(first.0, |p| p.0);
+
+            (last.0 - t_min) as f64 >= settle_ms || (last.0 - first.0) as f64 >= max_ms
+        }
+    }
+}
+
+/// Прирост частоты за выстрел по фрагменту не короче `min_len` точек
+fn fragment_growth<T>(fragment: &Fragment<T>, min_len: usize) -> Option<T>
+where
+    T: Float
+        + num_traits::FromPrimitive
+        + nalgebra::Scalar
+        + std::ops::MulAssign
+        + std::ops::AddAssign
+        + std::ops::DivAssign
+        + Serialize,
+{
+    if !fragment.is_valid() || fragment.points().len() < min_len {
+        return None;
+    }
+
+    let growth = fragment.target() - fragment.points().first()?.y();
+    if growth.is_finite() {
+        Some(growth)
+    } else {
+        None
+    }
+}
+
+/// Оценка распределения прироста по выборке, смешанная с `fallback` с весом n / (n + k)
+fn blend_growth<T: Float + num_traits::FromPrimitive>(
+    samples: &[T],
+    fallback: Prediction<T>,
+    k: usize,
+) -> Prediction<T> {
+    if samples.is_empty() {
+        return fallback;
+    }
+
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let n = sorted.len();
+    let w = unsafe { T::from_f64(n as f64 / (n + k) as f64).unwrap_unchecked() };
+    let mix = |v: T, fb: T| v * w + fb * (T::one() - w);
+
+    Prediction {
+        minimal: mix(
+            quantile_sorted(&sorted, GROWTH_Q_MIN, QuantileMethod::Linear),
+            fallback.minimal,
+        ),
+        maximal: mix(
+            quantile_sorted(&sorted, GROWTH_Q_MAX, QuantileMethod::Linear),
+            fallback.maximal,
+        ),
+        median: mix(
+            quantile_sorted(&sorted, 0.5, QuantileMethod::Linear),
+            fallback.median,
+        ),
+    }
+}
+
+//-----------------------------------------------------------------------------
+
+/// Грубая фильтрация: значения вне усов BoxPlot заменяются предыдущим корректным
+pub fn hard_filter<T: Float + num_traits::NumOps + num_traits::FromPrimitive + serde::Serialize + Copy>(data: &mut [T]) {
     let raw_box_plot = BoxPlot::new(data);
     let mut prev_y = None;
 
@@ -323,7 +1020,21 @@ fn hard_filter<T: Float + num_traits::NumOps + num_traits::FromPrimitive + serde
     });
 }
 
-fn smooth_filter<'a, T>(x: &Vec<T>, y: &Vec<T>) -> csaps::Result<Vec<T>>
+/// Сглаживание сплайном и удаление иголок, отклоняющихся от сплайна
+pub fn smooth_filter<'a, T>(x: &Vec<T>, y: &Vec<T>) -> csaps::Result<Vec<T>>
+where
+    T: Float
+        + num_traits::NumOps
+        + num_traits::FromPrimitive
+        + csaps::Real
+        + serde::Serialize
+        + Copy,
+{
+    smooth_filter_with(x, y, unsafe { T::from_f64(0.85).unwrap_unchecked() })
+}
+
+/// То же, что smooth_filter, с заданным коэффициентом сглаживания сплайна pg (0..1)
+pub fn smooth_filter_with<T>(x: &Vec<T>, y: &Vec<T>, pg: T) -> csaps::Result<Vec<T>>
 where
     T: Float
         + num_traits::NumOps
@@ -332,7 +1043,6 @@ where
         + serde::Serialize
         + Copy,
 {
-    let pg = unsafe { T::from_f64(0.85).unwrap_unchecked() };
     let mut y = y.clone();
 
     // Апроксимация сплайном
@@ -417,13 +1127,418 @@ where
     }
 }
 
+/// Вид модели остывания
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum CooldownModelKind {
+    /// y = a * (1 - exp(-x * b))
+    Exp,
+    /// y = a1 * (1 - exp(-x * b1)) + a2 * (1 - exp(-x * b2))
+    DoubleExp,
+    /// y = a * (1 - exp(-x * b)) + c * x
+    ExpLinear,
+}
+
+impl CooldownModelKind {
+    pub const ALL: [CooldownModelKind; 3] = [
+        CooldownModelKind::Exp,
+        CooldownModelKind::DoubleExp,
+        CooldownModelKind::ExpLinear,
+    ];
+
+    /// Общее количество параметров модели (линейных и нелинейных)
+    pub fn params_count(&self) -> usize {
+        match self {
+            CooldownModelKind::Exp => 2,
+            CooldownModelKind::DoubleExp => 4,
+            CooldownModelKind::ExpLinear => 3,
+        }
+    }
+}
+
+impl std::fmt::Display for CooldownModelKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CooldownModelKind::Exp => write!(f, "exp"),
+            CooldownModelKind::DoubleExp => write!(f, "double-exp"),
+            CooldownModelKind::ExpLinear => write!(f, "exp-linear"),
+        }
+    }
+}
+
+/// Модель остывания с коэффициентами, x в единицах NORMAL_T от точки минимума
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub enum CooldownModel<T> {
+    Exp { a: T, b: T },
+    DoubleExp { a1: T, b1: T, a2: T, b2: T },
+    ExpLinear { a: T, b: T, c: T },
+}
+
+impl<T: Scalar + Float> CooldownModel<T> {
+    pub fn kind(&self) -> CooldownModelKind {
+        match self {
+            CooldownModel::Exp { .. } => CooldownModelKind::Exp,
+            CooldownModel::DoubleExp { .. } => CooldownModelKind::DoubleExp,
+            CooldownModel::ExpLinear { .. } => CooldownModelKind::ExpLinear,
+        }
+    }
+
+    /// Значение модели в точках x
+    pub fn evaluate(&self, x: &DVector<T>) -> DVector<T> {
+        match *self {
+            CooldownModel::Exp { a, b } => limit_exp(x, b).map(|v| v * a),
+            CooldownModel::DoubleExp { a1, b1, a2, b2 } => {
+                limit_exp(x, b1).zip_map(&limit_exp(x, b2), |v1, v2| v1 * a1 + v2 * a2)
+            }
+            CooldownModel::ExpLinear { a, b, c } => {
+                limit_exp(x, b).zip_map(x, |v, x| v * a + x * c)
+            }
+        }
+    }
+
+    /// Прирост частоты после затухания экспоненциальных составляющих (без учета дрейфа)
+    pub fn asymptote(&self) -> T {
+        match *self {
+            CooldownModel::Exp { a, .. } | CooldownModel::ExpLinear { a, .. } => a,
+            CooldownModel::DoubleExp { a1, a2, .. } => a1 + a2,
+        }
+    }
+
+    /// Матрица Якоби модели по всем коэффициентам в порядке coeffs()
+    pub fn jacobian(&self, x: &DVector<T>) -> DMatrix<T> {
+        let columns = match *self {
+            CooldownModel::Exp { a, b } => {
+                vec![limit_exp(x, b), dlimit_exp_db(x, b).map(|v| v * a)]
+            }
+            CooldownModel::DoubleExp { a1, b1, a2, b2 } => vec![
+                limit_exp(x, b1),
+                dlimit_exp_db(x, b1).map(|v| v * a1),
+                limit_exp(x, b2),
+                dlimit_exp_db(x, b2).map(|v| v * a2),
+            ],
+            CooldownModel::ExpLinear { a, b, .. } => vec![
+                limit_exp(x, b),
+                dlimit_exp_db(x, b).map(|v| v * a),
+                x.clone(),
+            ],
+        };
+        DMatrix::from_columns(&columns)
+    }
+
+    /// Градиент asymptote() по коэффициентам в порядке coeffs()
+    fn asymptote_gradient(&self) -> DVector<T> {
+        let (o, z) = (T::one(), T::zero());
+        match self {
+            CooldownModel::Exp { .. } => DVector::from_vec(vec![o, z]),
+            CooldownModel::DoubleExp { .. } => DVector::from_vec(vec![o, z, o, z]),
+            CooldownModel::ExpLinear { .. } => DVector::from_vec(vec![o, z, z]),
+        }
+    }
+
+    /// Физически правдоподобная модель: составляющие нагрева неотрицательны,
+    /// постоянные времени положительны, итоговый прирост не больше limit_a, если он задан
+    pub fn is_plausible(&self, limit_a: Option<T>) -> bool {
+        let components_ok = match *self {
+            CooldownModel::Exp { a, b } | CooldownModel::ExpLinear { a, b, .. } => {
+                a >= T::zero() && b >= T::zero()
+            }
+            CooldownModel::DoubleExp { a1, b1, a2, b2 } => {
+                a1 >= T::zero() && b1 >= T::zero() && a2 >= T::zero() && b2 >= T::zero()
+            }
+        };
+        components_ok && limit_a.is_none_or(|limit| self.asymptote() <= limit)
+    }
+
+    /// Время от минимума (в единицах NORMAL_T), за которое экспоненциальные составляющие
+    /// достигают доли fraction от asymptote(). Линейный дрейф ExpLinear не учитывается
+    pub fn settle_time(&self, fraction: T) -> Option<T> {
+        if fraction.is_nan() || fraction <= T::zero() || fraction >= T::one() {
+            return None;
+        }
+        // -ln(1 - fraction): для одной экспоненты время установления равно k / b
+        let k = -(T::one() - fraction).ln();
+
+        match *self {
+            CooldownModel::Exp { b, .. } | CooldownModel::ExpLinear { b, .. } => {
+                if b > T::zero() {
+                    Some(k / b)
+                } else {
+                    None
+                }
+            }
+            CooldownModel::DoubleExp { a1, b1, a2, b2 } => {
+                if b1 <= T::zero() || b2 <= T::zero() || a1 + a2 <= T::zero() {
+                    return None;
+                }
+
+                // сумма экспонент монотонна, решение между временами установления составляющих
+                let target = fraction * (a1 + a2);
+                let value =
+                    |x: T| a1 * (T::one() - (-x * b1).exp()) + a2 * (T::one() - (-x * b2).exp());
+                let (mut lo, mut hi) = (k / b1.max(b2), k / b1.min(b2));
+                for _ in 0..SETTLE_BISECTION_STEPS {
+                    let mid = (lo + hi) / (T::one() + T::one());
+                    if value(mid) < target {
+                        lo = mid;
+                    } else {
+                        hi = mid;
+                    }
+                }
+                Some(hi)
+            }
+        }
+    }
+
+    /// Все коэффициенты модели
+    pub fn coeffs(&self) -> Vec<T> {
+        match *self {
+            CooldownModel::Exp { a, b } => vec![a, b],
+            CooldownModel::DoubleExp { a1, b1, a2, b2 } => vec![a1, b1, a2, b2],
+            CooldownModel::ExpLinear { a, b, c } => vec![a, b, c],
+        }
+    }
+}
+
+/// Результат аппроксимации фрагмента моделью
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct CooldownFit<T> {
+    pub model: CooldownModel<T>,
+    /// Сумма квадратов остатков
+    pub rss: T,
+    /// Информационный критерий Акаике (с поправкой на малую выборку, если возможно)
+    pub aic: T,
+    /// Неопределенность коэффициентов, None если точек слишком мало или матрица вырождена
+    pub quality: Option<FitQuality<T>>,
+}
+
+/// Квантиль нормального распределения для 95% интервала прогноза
+const PREDICTION_Z: f64 = 1.96;
+
+/// Оценка качества аппроксимации
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct FitQuality<T> {
+    /// Стандартные ошибки коэффициентов в порядке CooldownModel::coeffs()
+    pub std_errors: Vec<T>,
+    /// Стандартная ошибка прироста частоты (asymptote)
+    pub asymptote_se: T,
+    /// 95% интервал прогноза прироста частоты после охлаждения, относительно минимума
+    pub interval: (T, T),
+    /// Коэффициент детерминации
+    pub r_squared: T,
+    /// Итоговая оценка 0..1: R^2, уменьшенный пропорционально относительной ширине интервала
+    pub score: T,
+}
+
+/// Ковариационная матрица коэффициентов модели: s^2 * (J^T * J)^-1, s^2 = rss / (n - k)
+pub fn parameter_covariance<T>(
+    model: &CooldownModel<T>,
+    x: &DVector<T>,
+    rss: T,
+) -> Option<DMatrix<T>>
+where
+    T: Scalar + Float + nalgebra::RealField,
+{
+    let n = x.len();
+    let k = model.kind().params_count();
+    if n <= k {
+        return None;
+    }
+
+    let j = model.jacobian(x);
+    let s2 =
+        rss / unsafe { <T as num_traits::FromPrimitive>::from_usize(n - k).unwrap_unchecked() };
+    (j.transpose() * j).try_inverse().map(|inv| inv * s2)
+}
+
+/// Рассчитать оценку качества аппроксимации точек (x, y) моделью
+pub fn fit_quality<T>(
+    model: &CooldownModel<T>,
+    x: &DVector<T>,
+    y: &DVector<T>,
+    rss: T,
+) -> Option<FitQuality<T>>
+where
+    T: Scalar + Float + nalgebra::RealField,
+{
+    let cov = parameter_covariance(model, x, rss)?;
+
+    let std_errors = cov
+        .diagonal()
+        .iter()
+        .map(|v| Float::sqrt(Float::max(*v, T::zero())))
+        .collect::<Vec<_>>();
+
+    let g = model.asymptote_gradient();
+    let asymptote_var = Float::max((g.transpose() * &cov * &g)[0], T::zero());
+    let asymptote_se = Float::sqrt(asymptote_var);
+
+    let n = x.len();
+    let k = model.kind().params_count();
+    let nf = unsafe { <T as num_traits::FromPrimitive>::from_usize(n).unwrap_unchecked() };
+    let kf = unsafe { <T as num_traits::FromPrimitive>::from_usize(k).unwrap_unchecked() };
+    let z = unsafe { <T as num_traits::FromPrimitive>::from_f64(PREDICTION_Z).unwrap_unchecked() };
+
+    // неопределенность асимптоты + шум единичного измерения
+    let half_width = z * Float::sqrt(asymptote_var + rss / (nf - kf));
+    let asymptote = model.asymptote();
+
+    let mean = y.iter().fold(T::zero(), |acc, v| acc + *v) / nf;
+    let tss = y
+        .iter()
+        .fold(T::zero(), |acc, v| acc + (*v - mean) * (*v - mean));
+    if Float::is_nan(tss) || tss <= T::zero() {
+        return None;
+    }
+    let r_squared = T::one() - rss / tss;
+
+    let relative_width = half_width / Float::max(Float::abs(asymptote), T::epsilon());
+    let score = Float::max(r_squared, T::zero()) / (T::one() + relative_width);
+
+    Some(FitQuality {
+        std_errors,
+        asymptote_se,
+        interval: (asymptote - half_width, asymptote + half_width),
+        r_squared,
+        score,
+    })
+}
+
+/// Информационный критерий Акаике для МНК: n * ln(rss / n) + 2k (+ поправка AICc)
+pub fn information_criterion<T: Float + num_traits::FromPrimitive>(
+    rss: T,
+    n: usize,
+    k: usize,
+) -> T {
+    let nf = unsafe { T::from_usize(n).unwrap_unchecked() };
+    let kf = unsafe { T::from_usize(k).unwrap_unchecked() };
+    let two = unsafe { T::from_f64(2.0).unwrap_unchecked() };
+
+    // защита от идеального совпадения, ln(0)
+    let rss = rss.max(T::epsilon());
+
+    let aic = nf * (rss / nf).ln() + two * kf;
+    if n > k + 1 {
+        aic + two * kf * (kf + T::one()) / (nf - kf - T::one())
+    } else {
+        aic
+    }
+}
+
+/// Аппроксимировать точки (x, y) выбранной моделью остывания
+/// x и y должны быть смещены так, чтобы минимум находился в точке (0, 0)
+pub fn fit_cooldown_model<T>(
+    kind: CooldownModelKind,
+    x: Vec<T>,
+    y: &[T],
+) -> Result<CooldownFit<T>, ()>
+where
+    T: Scalar + Float + nalgebra::ComplexField + nalgebra::RealField + num_traits::FromPrimitive,
+{
+    use varpro::model::SeparableModel;
+    use varpro::prelude::*;
+    use varpro::solvers::levmar::{LevMarProblemBuilder, LevMarSolver};
+
+    let n = x.len();
+    let xv = nalgebra::DVector::<T>::from_vec(x.clone());
+    let yv = nalgebra::DVector::<T>::from_vec(y.to_vec());
+
+    let model = match kind {
+        CooldownModelKind::Exp => {
+            let (a, b) = aproximate_exp(x, y)?;
+            CooldownModel::Exp { a, b }
+        }
+        CooldownModelKind::DoubleExp => {
+            let model = SeparableModelBuilder::<T>::new(&["b1", "b2"])
+                .independent_variable(xv.clone())
+                .function(&["b1"], limit_exp) // быстрая составляющая
+                .partial_deriv("b1", dlimit_exp_db)
+                .function(&["b2"], limit_exp) // медленная составляющая
+                .partial_deriv("b2", dlimit_exp_db)
+                .initial_parameters(vec![
+                    unsafe { T::from_f64(5.0).unwrap_unchecked() },
+                    unsafe { T::from_f64(0.5).unwrap_unchecked() },
+                ])
+                .build()
+                .map_err(|_| ())?;
+            let problem = LevMarProblemBuilder::<SeparableModel<T>>::new(model)
+                .observations(yv.clone())
+                .build()
+                .map_err(|_| ())?;
+            let (solved_problem, report) = LevMarSolver::new().minimize(problem);
+            if !report.termination.was_successful() {
+                return Err(());
+            }
+            let b = solved_problem.params();
+            let a = solved_problem.linear_coefficients().ok_or(())?;
+            CooldownModel::DoubleExp {
+                a1: a[0],
+                b1: b[0],
+                a2: a[1],
+                b2: b[1],
+            }
+        }
+        CooldownModelKind::ExpLinear => {
+            let model = SeparableModelBuilder::<T>::new(&["b"])
+                .independent_variable(xv.clone())
+                .function(&["b"], limit_exp)
+                .partial_deriv("b", dlimit_exp_db)
+                .invariant_function(|x: &DVector<T>| x.clone()) // линейный дрейф
+                .initial_parameters(vec![T::one()])
+                .build()
+                .map_err(|_| ())?;
+            let problem = LevMarProblemBuilder::<SeparableModel<T>>::new(model)
+                .observations(yv.clone())
+                .build()
+                .map_err(|_| ())?;
+            let (solved_problem, report) = LevMarSolver::new().minimize(problem);
+            if !report.termination.was_successful() {
+                return Err(());
+            }
+            let b = solved_problem.params()[0];
+            let a = solved_problem.linear_coefficients().ok_or(())?;
+            CooldownModel::ExpLinear {
+                a: a[0],
+                b,
+                c: a[1],
+            }
+        }
+    };
+
+    let rss = model
+        .evaluate(&xv)
+        .iter()
+        .zip(yv.iter())
+        .fold(T::zero(), |acc, (m, y)| acc + (*y - *m) * (*y - *m));
+
+    Ok(CooldownFit {
+        model,
+        rss,
+        aic: information_criterion(rss, n, kind.params_count()),
+        quality: fit_quality(&model, &xv, &yv, rss),
+    })
+}
+
 //-----------------------------------------------------------------------------
 
+/// Отправить успешно аппроксимированный фрагмент на запись в историю
+fn send_to_history<T: Float + Serialize>(
+    history_tx: &Option<UnboundedSender<Fragment<T>>>,
+    fragment: Option<Fragment<T>>,
+) {
+    if let (Some(tx), Some(fragment)) = (history_tx, fragment) {
+        tx.send(fragment).ok();
+    }
+}
+
+/// Аппроксимировать фрагмент и добавить его к каналу,
+/// возвращает копию добавленного фрагмента, если аппроксимация удалась
 async fn consume_fragment<T>(
     serie_data: &[(u128, f32)],
     channel: usize,
     fragments: &Mutex<Vec<Vec<Fragment<T>>>>,
-) where
+    filters: &[FilterStage],
+    forecast_config: &ForecastConfig,
+) -> Option<Fragment<T>>
+where
     T: Float
         + num_traits::NumOps
         + num_traits::FromPrimitive
@@ -440,11 +1555,8 @@ async fn consume_fragment<T>(
         f.push(unsafe { T::from_f32(v.1).unwrap_unchecked() });
     });
 
-    // Грубая фильтрация от всяких выбросов в 0
-    hard_filter(&mut f);
-
-    // сглаживающая фильтрация
-    if let Ok(filtred_f) = smooth_filter(&t, &f) {
+    // фильтрация выбросов и сглаживание
+    if let Ok(filtred_f) = apply_filters(filters, &t, &f) {
         if let Some((f_min_index, min_f)) = find_min(&filtred_f) {
             let fz = filtred_f
                 .iter()
@@ -452,42 +1564,66 @@ async fn consume_fragment<T>(
                 .map(move |f| *f - min_f)
                 .collect::<Vec<_>>();
 
-            // Апроксимация экспонентой
+            // Апроксимация всеми моделями остывания, выбирается лучшая по AIC
             let t_zero = t[f_min_index];
-            if let Ok(coeffs) = aproximate_exp(
-                t[f_min_index..]
-                    .iter()
-                    .map(move |t| (*t - t_zero) / normal_t)
-                    .collect::<Vec<_>>(),
-                &fz,
-            ) {
-                const LIMIT_A: f64 = 5.0;
-
-                let coeffs = if coeffs.0 > unsafe { T::from_f64(LIMIT_A).unwrap_unchecked() }
-                    || coeffs.0 < T::zero()
-                    || coeffs.1 < T::zero()
-                {
-                    tracing::error!("Aprox fragment failed!");
-                    None
+            let tz = t[f_min_index..]
+                .iter()
+                .map(move |t| (*t - t_zero) / normal_t)
+                .collect::<Vec<_>>();
+            let fits = CooldownModelKind::ALL
+                .iter()
+                .filter_map(|kind| fit_cooldown_model(*kind, tz.clone(), &fz).ok())
+                .collect::<Vec<_>>();
+
+            if !fits.is_empty() {
+                let limit_a = forecast_config
+                    .max_fragment_grow_hz
+                    .and_then(|limit| T::from_f32(limit));
+                let min_score = T::from_f64(forecast_config.min_fit_score).unwrap_or_else(T::zero);
+
+                // модели с неоцениваемым качеством не отбрасываются
+                let best = fits
+                    .into_iter()
+                    .filter(|fit| fit.model.is_plausible(limit_a))
+                    .filter(|fit| fit.quality.as_ref().is_none_or(|q| q.score >= min_score))
+                    .min_by(|a, b| {
+                        a.aic
+                            .partial_cmp(&b.aic)
+                            .unwrap_or(std::cmp::Ordering::Equal)
+                    });
+
+                let (model, quality) = if let Some(fit) = best {
+                    tracing::trace!(
+                        "Aprox fragment: {}, coeffs={:?}, score={:?}",
+                        fit.model.kind(),
+                        fit.model.coeffs(),
+                        fit.quality.as_ref().map(|q| q.score)
+                    );
+                    (Some(fit.model), fit.quality)
                 } else {
-                    tracing::trace!("Aprox fragment: a={}, b={}", coeffs.0, coeffs.1);
-                    Some(coeffs)
+                    tracing::error!("Aprox fragment failed!");
+                    (None, None)
                 };
 
                 let mut guard = fragments.lock().await;
                 let serie = guard.get_mut(channel).unwrap();
+                // во фрагменте - исходные измерения, фильтрованный ряд нужен только для аппроксимации
                 let data = t
                     .iter()
                     .zip(f)
                     .map(|(t, f)| DataPoint::new(*t, f))
                     .collect::<Vec<_>>();
-                serie.push(Fragment::new(serie_data[0].0, &data, coeffs, f_min_index));
-                return;
+                let fragment =
+                    Fragment::with_model(serie_data[0].0, &data, model, quality, f_min_index);
+                serie.push(fragment.clone());
+                return Some(fragment).filter(|f| f.is_valid());
             } else {
                 tracing::warn!("Fragment approximation failed!");
             }
         }
     }
+
+    None
 }
 
 //-----------------------------------------------------------------------------
@@ -589,4 +1725,133 @@ mod test {
         let a = solved_problem.linear_coefficients().unwrap()[0];
         println!("a = {a}, b = {b}")
     }
+
+    #[test]
+    fn settle_time() {
+        // 1 - exp(-x * 3) = 0.95 -> x = ln(20) / 3
+        let exp = CooldownModel::Exp { a: 0.5, b: 3.0 };
+        let expected = 20f64.ln() / 3.0;
+        assert!((exp.settle_time(0.95).unwrap() - expected).abs() < 1e-12);
+        assert!(exp.settle_time(1.0).is_none());
+
+        // две одинаковые экспоненты - то же время
+        let double = CooldownModel::DoubleExp {
+            a1: 0.2,
+            b1: 3.0,
+            a2: 0.3,
+            b2: 3.0,
+        };
+        assert!((double.settle_time(0.95).unwrap() - expected).abs() < 1e-9);
+
+        // медленная составляющая затягивает установление
+        let slow = CooldownModel::DoubleExp {
+            a1: 0.4,
+            b1: 3.0,
+            a2: 0.1,
+            b2: 0.5,
+        };
+        let t = slow.settle_time(0.95).unwrap();
+        assert!(t > expected && t < 20f64.ln() / 0.5);
+    }
+
+    #[test]
+    fn adaptive_cooldown_complete() {
+        // спад до минимума в 200 мс, затем рост
+        let data = [(0, 10.0), (100, 9.5), (200, 9.0), (300, 9.4), (400, 9.6)];
+        assert!(!cooldown_complete(&data, 10, None));
+        assert!(cooldown_complete(&data, 5, None));
+        assert!(cooldown_complete(&data, 10, Some((200.0, 1000.0))));
+        assert!(!cooldown_complete(&data, 10, Some((250.0, 1000.0))));
+        assert!(cooldown_complete(&data, 10, Some((250.0, 400.0))));
+    }
+
+    #[test]
+    fn cooldown_cache() {
+        let config = AdaptiveCooldownConfig {
+            settle_fraction: 0.95,
+            min_ms: 100,
+            max_ms: 10000,
+        };
+        let points = [DataPoint::new(0.0, 10.0), DataPoint::new(100.0, 10.5)];
+        let fragment = |b: f64| {
+            Fragment::with_model(0, &points, Some(CooldownModel::Exp { a: 1.0, b }), None, 0)
+        };
+
+        let mut fragments = vec![vec![fragment(1.0)], vec![]];
+        let t = estimate_cooldown_ms(&fragments, 0, 5, &config);
+        assert!((t - 2995.7).abs() < 1.0);
+        // канал без фрагментов - по всем каналам
+        assert_eq!(estimate_cooldown_ms(&fragments, 1, 5, &config), t);
+
+        let mut cache = CooldownCache::default();
+        assert_eq!(cache.cached(0), None);
+        cache.insert(0, t, cache.generation());
+        assert_eq!(cache.cached(0), Some(t));
+        assert_eq!(cache.cached(1), None);
+
+        // оценка, начатая до появления нового фрагмента, не сохраняется
+        fragments[0] = vec![fragment(2.0)];
+        let generation = cache.generation();
+        cache.invalidate();
+        cache.insert(0, t, generation);
+        assert_eq!(cache.cached(0), None);
+        assert!((estimate_cooldown_ms(&fragments, 0, 5, &config) - t / 2.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn fragment_legacy_coeffs() {
+        // файлы фрагментов, сохраненные до появления поля model
+        let json = r#"{"start_timestamp":0.0,"raw_points":[{"x":0.0,"y":10.0},{"x":100.0,"y":9.0},{"x":200.0,"y":9.5}],"coeffs":[1.0,2.0],"min_index":1}"#;
+        let fragment: Fragment<f64> = serde_json::from_str(json).unwrap();
+
+        assert!(fragment.is_valid());
+        assert!(matches!(
+            fragment.model(),
+            Some(CooldownModel::Exp { a, b }) if a == 1.0 && b == 2.0
+        ));
+        assert_eq!(fragment.target(), 10.0);
+    }
+
+    #[test]
+    fn fragment_with_model_target() {
+        let points = [
+            DataPoint::new(0.0, 10.0),
+            DataPoint::new(100.0, 9.0),
+            DataPoint::new(200.0, 9.5),
+        ];
+        let model = CooldownModel::DoubleExp {
+            a1: 0.5,
+            b1: 5.0,
+            a2: 0.25,
+            b2: 0.5,
+        };
+        let fragment = Fragment::with_model(0, &points, Some(model), None, 1);
+
+        assert!(fragment.aprox_coeffs().is_none());
+        assert_eq!(fragment.target(), 9.75);
+        assert_eq!(fragment.evaluate().len(), 1 + (points.len() - 1) * 2);
+        assert!(model.is_plausible(Some(5.0)));
+        assert!(!model.is_plausible(Some(0.5)));
+        assert!(model.is_plausible(None));
+    }
+
+    #[test]
+    fn fit_quality_interval() {
+        let model = CooldownModel::Exp { a: 0.5, b: 3.0 };
+        let x = nalgebra::DVector::<f64>::from_iterator(30, (0..30).map(|i| i as f64 * 0.05));
+        let y = model
+            .evaluate(&x)
+            .map_with_location(|i, _, v| v + 2e-3 * if i % 2 == 0 { 1.0 } else { -1.0 });
+        let rss = (model.evaluate(&x) - &y).norm_squared();
+
+        let quality = fit_quality(&model, &x, &y, rss).unwrap();
+        assert_eq!(quality.std_errors.len(), 2);
+        assert!(quality.interval.0 < 0.5 && quality.interval.1 > 0.5);
+        assert!(quality.r_squared > 0.99);
+        assert!(quality.score > 0.9 && quality.score <= 1.0);
+
+        // слишком мало точек для оценки ковариации
+        let x2 = x.rows(0, 2).into_owned();
+        assert!(parameter_covariance(&model, &x2, rss).is_none());
+    }
 }
@@ -0,0 +1,202 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    channel_targets::{parse_rows, Error},
+    fragment_store::{load_json, recipe_file_name, save_json},
+};
+
+/// Уход частоты при герметизации: настройка идет в вакууме, а заказчик измеряет резонатор в корпусе,
+/// поэтому настраивать нужно на цель, смещенную на ожидаемый уход
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SealingConfig {
+    /// Постоянная часть ухода частоты после герметизации, Гц
+    #[serde(rename = "OffsetHz", default)]
+    pub offset_hz: f32,
+
+    /// Часть ухода, пропорциональная частоте, ppm
+    #[serde(rename = "OffsetPPM", default)]
+    pub offset_ppm: f32,
+
+    /// Уточнять уход каждого канала по замерам прошлых партий
+    #[serde(rename = "LearnPerChannel", default)]
+    pub learn_per_channel: bool,
+
+    /// Количество замеров канала, при котором его поправка и общая модель имеют равный вес
+    #[serde(rename = "MinSamples", default = "default_min_samples")]
+    pub min_samples: usize,
+
+    /// Каталог замеров, по умолчанию - "sealing" рядом с файлом настроек
+    #[serde(rename = "Directory", default)]
+    pub directory: Option<PathBuf>,
+}
+
+fn default_min_samples() -> usize {
+    5
+}
+
+/// Замер канала до и после герметизации
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct SealingSample {
+    /// Номер позиции, начиная с 1, как в интерфейсе
+    #[serde(rename = "Channel")]
+    pub channel: usize,
+
+    /// Частота после настройки в вакууме, Гц
+    #[serde(rename = "Before")]
+    pub before: f32,
+
+    /// Частота в корпусе, Гц
+    #[serde(rename = "After")]
+    pub after: f32,
+}
+
+/// Ожидаемый уход частоты каналов при герметизации
+#[derive(Clone, Debug, Default)]
+pub struct SealingModel {
+    offset_hz: f32,
+    offset_ppm: f32,
+    /// Поправки каналов к общей модели по прошлым партиям, Гц
+    corrections: Vec<f32>,
+}
+
+impl SealingModel {
+    /// Общая модель, уточненная по замерам прошлых партий
+    /// Поправка канала - средний остаток общей модели, сжатый к нулю при малом числе замеров
+    pub fn fit(config: &SealingConfig, samples: &[SealingSample], channel_count: usize) -> Self {
+        let mut model = Self {
+            offset_hz: config.offset_hz,
+            offset_ppm: config.offset_ppm,
+            corrections: vec![0.0; channel_count],
+        };
+
+        if config.learn_per_channel {
+            for (ch, correction) in model.corrections.iter_mut().enumerate() {
+                let residuals = samples
+                    .iter()
+                    .filter(|s| s.channel == ch + 1)
+                    .map(|s| {
+                        s.after
+                            - s.before
+                            - (config.offset_hz + s.before * config.offset_ppm / 1_000_000.0)
+                    })
+                    .collect::<Vec<_>>();
+                *correction =
+                    residuals.iter().sum::<f32>() / (residuals.len() + config.min_samples) as f32;
+            }
+        }
+
+        model
+    }
+
+    /// Ожидаемый уход частоты канала (нумерация с 0) с частотой f после герметизации, Гц
+    pub fn shift(&self, channel: usize, f: f32) -> f32 {
+        self.offset_hz
+            + f * self.offset_ppm / 1_000_000.0
+            + self.corrections.get(channel).copied().unwrap_or_default()
+    }
+
+    /// Частота в вакууме, которая после герметизации даст target
+    pub fn trim_target(&self, channel: usize, target: f32) -> f32 {
+        target - self.shift(channel, target)
+    }
+}
+
+/// Замеры до и после герметизации всех партий одного рецепта в файле `<Recipe>.sealing.json`
+#[derive(Clone, Debug)]
+pub struct SealingStore {
+    path: PathBuf,
+}
+
+impl SealingStore {
+    pub fn new(directory: PathBuf, recipe: &str) -> Self {
+        Self {
+            path: directory.join(format!("{}.sealing.json", recipe_file_name(recipe))),
+        }
+    }
+
+    /// Загрузить замеры, отсутствующий файл - нет замеров
+    pub fn load(&self) -> std::io::Result<Vec<SealingSample>> {
+        Ok(load_json(&self.path)?.unwrap_or_default())
+    }
+
+    /// Дописать замеры партии
+    pub fn append(&self, samples: &[SealingSample]) -> std::io::Result<()> {
+        let mut all = self.load()?;
+        all.extend_from_slice(samples);
+        save_json(&self.path, &all)
+    }
+}
+
+/// Разбор таблицы замеров: CSV `канал;до;после`, правила как у таблицы целей каналов
+pub fn parse_sealing_table(text: &str, channel_count: usize) -> Result<Vec<SealingSample>, Error> {
+    parse_rows(text)?
+        .into_iter()
+        .map(|row| {
+            if row.channel == 0 || row.channel > channel_count {
+                return Err(Error::Channel(row.channel));
+            }
+
+            let value = |i: usize, name: &str| -> Result<f32, Error> {
+                row.value(i, name)?
+                    .filter(|f| f.is_finite() && *f > 0.0)
+                    .ok_or(Error::Value {
+                        channel: row.channel,
+                        message: format!("нет частоты {}", name),
+                    })
+            };
+            Ok(SealingSample {
+                channel: row.channel,
+                before: value(0, "до герметизации")?,
+                after: value(1, "после герметизации")?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config(learn_per_channel: bool) -> SealingConfig {
+        SealingConfig {
+            offset_hz: 1.5,
+            offset_ppm: 0.0,
+            learn_per_channel,
+            min_samples: 2,
+            directory: None,
+        }
+    }
+
+    #[test]
+    fn fit() {
+        let samples = parse_sealing_table(
+            "Канал;До;После\n1;32766,5;32768,5\n1;32766;32768\n2;32767;32768,5",
+            2,
+        )
+        .unwrap();
+        assert_eq!(samples.len(), 3);
+
+        let fixed = SealingModel::fit(&config(false), &samples, 2);
+        assert_eq!(fixed.shift(0, 32768.0), 1.5);
+        assert_eq!(fixed.trim_target(1, 32768.0), 32766.5);
+
+        // канал 1 уходит на 2 Гц: остаток 0.5 Гц по 2 замерам сжимается вдвое
+        let learned = SealingModel::fit(&config(true), &samples, 2);
+        assert_eq!(learned.shift(0, 32768.0), 1.75);
+        assert_eq!(learned.shift(1, 32768.0), 1.5);
+    }
+
+    #[test]
+    fn table_errors() {
+        assert!(matches!(
+            parse_sealing_table("3;32766;32768", 2),
+            Err(Error::Channel(3))
+        ));
+        assert!(matches!(
+            parse_sealing_table("1;32766", 2),
+            Err(Error::Value { channel: 1, .. })
+        ));
+    }
+}
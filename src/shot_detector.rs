@@ -0,0 +1,205 @@
+use num_traits::{Float, FromPrimitive};
+
+/// Состояние детектора выстрелов
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    /// Ожидание начала спада
+    Wait,
+    /// Спад
+    Falling,
+    /// Рост
+    Raizing,
+}
+
+/// Минимальное количество точек спада и роста, чтобы считать их выстрелом
+pub const MIN_SHOT_POINTS: usize = 4;
+
+/// Детектор выстрелов по производной частоты (порт find_shot_v1/shot_detector.py)
+/// После выстрела частота резко падает (нагрев), затем растет (остывание),
+/// выстрел засчитывается после спада с последующим ускоряющимся ростом
+/// P - позиция точки (индекс, таймштамп), возвращается позиция начала спада
+#[derive(Clone, Debug)]
+pub struct ShotDetector<T, P> {
+    state: State,
+    start: Option<P>,
+    count: usize,
+    prev: Option<T>,
+}
+
+impl<T: Float, P: Copy> Default for ShotDetector<T, P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Float, P: Copy> ShotDetector<T, P> {
+    pub fn new() -> Self {
+        Self {
+            state: State::Wait,
+            start: None,
+            count: 0,
+            prev: None,
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Обработать очередное значение производной, если обнаружен выстрел -
+    /// вернуть позицию начала спада
+    pub fn push(&mut self, position: P, v: T) -> Option<P> {
+        let prev = self.prev.replace(v);
+        let mut res = None;
+
+        match self.state {
+            State::Wait => {
+                if v < T::zero() {
+                    self.start = Some(position);
+                    self.count = 1;
+                    self.state = State::Falling;
+                }
+            }
+            State::Falling => {
+                if prev.is_some_and(|p| v < p) {
+                    self.count += 1;
+                } else if v > T::zero() {
+                    // сброс
+                    self.state = State::Wait;
+                } else {
+                    self.count += 1;
+                    self.state = State::Raizing;
+                }
+            }
+            State::Raizing => {
+                if prev.is_some_and(|p| v > p) {
+                    self.count += 1;
+                    return None;
+                } else if v > T::zero() && self.count > MIN_SHOT_POINTS {
+                    res = self.start;
+                }
+
+                self.state = State::Wait;
+            }
+        }
+
+        res
+    }
+}
+
+/// Найти выстрелы в ряду значений производной, вернуть индексы начала спада
+pub fn detect_shots<T: Float>(derivative: &[T]) -> Vec<usize> {
+    if derivative.len() <= MIN_SHOT_POINTS {
+        return vec![];
+    }
+
+    let mut detector = ShotDetector::new();
+    derivative
+        .iter()
+        .enumerate()
+        .filter_map(|(i, v)| detector.push(i, *v))
+        .collect()
+}
+
+/// Производная сглаженного сплайном ряда (t, f), конечные разности
+pub fn smooth_derivative<T>(t: &[T], f: &[T], smooth: T) -> csaps::Result<Vec<T>>
+where
+    T: Float + csaps::Real,
+{
+    let spline = csaps::CubicSmoothingSpline::new(t, f)
+        .with_smooth(smooth)
+        .make()?;
+    let values = spline.evaluate(t)?.iter().copied().collect::<Vec<_>>();
+
+    let mut derivative = values
+        .windows(2)
+        .zip(t.windows(2))
+        .map(|(v, t)| (v[1] - v[0]) / (t[1] - t[0]))
+        .collect::<Vec<_>>();
+    // производная первой точки принимается равной производной второй
+    if let Some(first) = derivative.first().copied() {
+        derivative.insert(0, first);
+    }
+    Ok(derivative)
+}
+
+/// Детектор выстрелов для потока измерений (таймштамп, частота)
+/// Производная считается по экспоненциально-сглаженной частоте
+#[derive(Clone, Debug)]
+pub struct OnlineShotDetector<T> {
+    alpha: T,
+    smoothed: Option<(u128, T)>,
+    detector: ShotDetector<T, u128>,
+}
+
+impl<T: Float + FromPrimitive> OnlineShotDetector<T> {
+    /// alpha - коэффициент экспоненциального сглаживания частоты (0..1]
+    pub fn new(alpha: f64) -> Self {
+        Self {
+            alpha: unsafe { T::from_f64(alpha).unwrap_unchecked() },
+            smoothed: None,
+            detector: ShotDetector::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.smoothed = None;
+        self.detector.reset();
+    }
+
+    /// Обработать измерение, если обнаружен выстрел - вернуть таймштамп начала спада
+    pub fn push(&mut self, timestamp: u128, f: T) -> Option<u128> {
+        match self.smoothed {
+            None => {
+                self.smoothed = Some((timestamp, f));
+                None
+            }
+            Some((prev_ts, prev_f)) if timestamp > prev_ts => {
+                let f = prev_f + (f - prev_f) * self.alpha;
+                self.smoothed = Some((timestamp, f));
+
+                let dt = unsafe { T::from_u128(timestamp - prev_ts).unwrap_unchecked() };
+                self.detector.push(prev_ts, (f - prev_f) / dt)
+            }
+            // повтор таймштампа
+            Some(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // производная одиночного выстрела: спад, затем ускоряющийся рост
+    const SHOT: [f64; 9] = [-0.1, -0.5, -0.2, 0.1, 0.4, 0.6, 0.3, 0.2, 0.1];
+
+    #[test]
+    fn detect_single_shot() {
+        let mut derivative = vec![0.0; 5];
+        derivative.extend_from_slice(&SHOT);
+        derivative.extend_from_slice(&[0.0; 5]);
+
+        assert_eq!(detect_shots(&derivative), vec![5]);
+    }
+
+    #[test]
+    fn short_fall_ignored() {
+        // спад сразу сменился ростом, без ускорения роста
+        let derivative = [0.0, -0.1, 0.2, 0.1, 0.0, 0.0, 0.0];
+        assert!(detect_shots(&derivative).is_empty());
+    }
+
+    #[test]
+    fn detect_two_shots() {
+        let mut derivative = SHOT.to_vec();
+        derivative.extend_from_slice(&[0.0; 3]);
+        derivative.extend_from_slice(&SHOT);
+
+        assert_eq!(detect_shots(&derivative), vec![0, SHOT.len() + 3]);
+    }
+}
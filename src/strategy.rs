@@ -0,0 +1,761 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::box_plot::BoxPlot;
+
+/// Пределы настройки канала
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdjustGoal {
+    /// Целевая частота, Гц
+    pub target: f32,
+
+    /// Допустимое отклонение от цели, Гц
+    pub tolerance: f32,
+
+    /// Частота, ниже которой канал считается нерабочим, Гц
+    pub absolute_low: f32,
+
+    /// Лимит шагов прожига
+    pub max_steps: Option<u32>,
+
+    /// Множитель мощности прожига
+    pub power: f32,
+}
+
+impl AdjustGoal {
+    pub fn lower(&self) -> f32 {
+        self.target - self.tolerance
+    }
+
+    pub fn upper(&self) -> f32 {
+        self.target + self.tolerance
+    }
+
+    /// Сколько шагов еще можно сделать
+    pub fn steps_left(&self, step: u32) -> u32 {
+        self.max_steps
+            .map_or(u32::MAX, |max| max.saturating_sub(step))
+    }
+}
+
+/// Ожидаемый прирост частоты от одного шага прожига, Гц
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GrowForecast {
+    pub median: f32,
+    pub maximal: f32,
+}
+
+/// Наблюдение за каналом перед очередным решением
+#[derive(Clone, Copy, Debug)]
+pub struct Observation<'a> {
+    /// Текущая частота канала, Гц
+    pub freq: f32,
+
+    /// Отсчеты частоты последнего измерения, может быть пустым
+    pub samples: &'a [f32],
+
+    /// Предыдущие стабильные частоты канала, от старых к новым
+    pub history: &'a [f32],
+
+    /// Использовано шагов прожига
+    pub step: u32,
+
+    pub goal: AdjustGoal,
+    pub forecast: GrowForecast,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BanReason {
+    /// Исчерпан лимит шагов
+    StepLimit,
+    /// Частота ниже минимально-допустимой
+    TooLow,
+    /// Частота выше максимально-допустимой
+    TooHigh,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    /// Прожиг steps шагов с перемещением после каждого
+    Burn { steps: u32, soft: bool, power: f32 },
+
+    /// Одиночный пробный выстрел без перемещения, наблюдение сразу после него
+    Probe,
+
+    /// Перемещение на steps шагов без прожига, отрицательное - назад
+    Skip(i32),
+
+    /// Подождать и повторить наблюдение
+    Wait(Duration),
+
+    /// Цель достигнута, нужно контрольное измерение
+    Verify,
+
+    /// Дальнейшая настройка канала невозможна
+    Ban(BanReason),
+}
+
+/// Алгоритм настройки одного канала: по наблюдению решает, что делать дальше
+/// Сам с оборудованием не работает, поэтому проверяется без него
+pub trait AdjustStrategy: Send {
+    fn decide(&mut self, obs: &Observation) -> Action;
+
+    /// Шаг, на котором найден край, если поиск закончен
+    fn edge(&self) -> Option<u32> {
+        None
+    }
+
+    /// Текущая стадия настройки, None - стратегия без стадий
+    fn stage(&self) -> Option<Stage> {
+        None
+    }
+}
+
+//-----------------------------------------------------------------------------
+
+/// Поиск края пробными выстрелами: сначала через interval шагов до первой реакции,
+/// затем делением пополам промежутка между последним выстрелом без реакции и первым с реакцией
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EdgeSearch {
+    interval: u32,
+    /// Шаг следующего пробного выстрела
+    next: u32,
+    /// Последний шаг без реакции
+    lo: Option<u32>,
+    /// Первый шаг с реакцией
+    hi: Option<u32>,
+    edge: Option<u32>,
+    /// Сделано делений пополам
+    bisections: u32,
+    max_bisections: u32,
+}
+
+impl EdgeSearch {
+    /// Поиск с шага start
+    pub fn new(start: u32, interval: u32) -> Self {
+        Self {
+            interval: interval.max(1),
+            next: start,
+            lo: None,
+            hi: None,
+            edge: None,
+            bisections: 0,
+            max_bisections: u32::MAX,
+        }
+    }
+
+    /// Не больше n делений пополам, дальше край - первый шаг с реакцией.
+    /// Каждый пробный выстрел - отдельное обращение к каналу, а пропущенные шаги
+    /// обходятся дешевле ожидания остывания
+    pub fn with_max_bisections(mut self, n: u32) -> Self {
+        self.max_bisections = n;
+        self
+    }
+
+    /// Поиск около края, найденного в прошлых партиях: первый выстрел на interval шагов раньше
+    pub fn near(hint: u32, interval: u32) -> Self {
+        Self::new(hint.saturating_sub(interval), interval)
+    }
+
+    /// Край уже известен, например из контрольной точки
+    pub fn found(edge: u32) -> Self {
+        Self {
+            edge: Some(edge),
+            ..Self::new(edge, 1)
+        }
+    }
+
+    /// Шаг следующего пробного выстрела, None - край найден
+    pub fn next_probe(&self) -> Option<u32> {
+        match self.edge {
+            Some(_) => None,
+            None => Some(self.next),
+        }
+    }
+
+    pub fn edge(&self) -> Option<u32> {
+        self.edge
+    }
+
+    /// Шаг, на котором должен стоять лазер: следующий пробный выстрел или найденный край
+    pub fn position(&self) -> u32 {
+        self.edge.unwrap_or(self.next)
+    }
+
+    /// Результат пробного выстрела на шаге [`EdgeSearch::next_probe`]
+    pub fn report(&mut self, reacted: bool) {
+        if self.edge.is_some() {
+            return;
+        }
+
+        if reacted {
+            self.hi = Some(self.next);
+        } else {
+            self.lo = Some(self.next);
+        }
+
+        match (self.lo, self.hi) {
+            (Some(lo), Some(hi)) if hi <= lo + 1 || self.bisections >= self.max_bisections => {
+                self.edge = Some(hi)
+            }
+            (Some(lo), Some(hi)) => {
+                self.bisections += 1;
+                self.next = lo + (hi - lo) / 2;
+            }
+            // реакция на первом же выстреле - назад, пока не найдем шаг без реакции
+            (None, Some(0)) => self.edge = Some(0),
+            (None, Some(hi)) => self.next = hi.saturating_sub(self.interval),
+            // реакции еще нет - вперед
+            (_, None) => self.next += self.interval,
+        }
+    }
+}
+
+//-----------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug)]
+pub struct InterleavedConfig {
+    /// Шагов между пробными выстрелами при грубом поиске края
+    pub edge_detect_interval: u32,
+    /// Максимум шагов за один прожиг
+    pub step_limit: u32,
+    /// Рост частоты после пробного выстрела, означающий реакцию резонатора, Гц
+    pub edge_offset: f32,
+    /// Шагов, оставляемых для точной настройки
+    pub precision_reserve: u32,
+}
+
+/// Настройка с чередованием каналов: за одно обращение к каналу один прожиг,
+/// количество шагов по разнице с целью и прогнозу максимального прироста.
+/// Реакция на пробный выстрел видна только при следующем обращении, obs.step - текущий шаг канала
+#[derive(Clone, Copy, Debug)]
+pub struct InterleavedStrategy {
+    config: InterleavedConfig,
+    edge: EdgeSearch,
+    /// Частота перед последним пробным выстрелом
+    probe_freq: Option<f32>,
+}
+
+impl InterleavedStrategy {
+    pub fn new(config: InterleavedConfig) -> Self {
+        Self {
+            config,
+            edge: EdgeSearch::new(0, config.edge_detect_interval),
+            probe_freq: None,
+        }
+    }
+
+    /// Начать с другого состояния поиска края: с подсказки прошлых партий или уже найденного
+    pub fn with_edge_search(mut self, edge: EdgeSearch) -> Self {
+        self.edge = edge;
+        self
+    }
+}
+
+impl AdjustStrategy for InterleavedStrategy {
+    fn decide(&mut self, obs: &Observation) -> Action {
+        let goal = &obs.goal;
+
+        if obs.freq > goal.lower() && obs.freq + obs.forecast.median > goal.target {
+            return Action::Verify;
+        }
+
+        let steps_left = goal.steps_left(obs.step);
+        if steps_left == 0 {
+            return Action::Ban(BanReason::StepLimit);
+        }
+
+        if let Some(f0) = self.probe_freq.take() {
+            self.edge.report(obs.freq - f0 > self.config.edge_offset);
+            if self.edge.edge().is_some_and(|edge| edge != obs.step) {
+                // край найден, настройка начинается с него
+                return Action::Skip(self.edge.position() as i32 - obs.step as i32);
+            }
+        }
+        if self.edge.edge().is_none() {
+            let position = self.edge.position();
+            if position != obs.step {
+                return Action::Skip(position as i32 - obs.step as i32);
+            }
+            self.probe_freq = Some(obs.freq);
+            return Action::Probe;
+        }
+
+        // грубые прожиги не расходуют запас для точной настройки,
+        // когда остался только запас - дальше только точный режим
+        let coarse_left = steps_left.saturating_sub(self.config.precision_reserve);
+        let soft = obs.freq > goal.lower() || coarse_left == 0;
+        let steps = if soft {
+            // всегда 1, самый точный режим
+            1
+        } else {
+            // кол_во = (целевое_изменеие / прогноз.макс_изменение).ceil()
+            self.config
+                .step_limit
+                .min(((goal.target - obs.freq) / obs.forecast.maximal).ceil() as u32)
+                .min(coarse_left)
+        };
+
+        Action::Burn {
+            steps: steps.min(steps_left),
+            soft,
+            power: goal.power,
+        }
+    }
+
+    fn edge(&self) -> Option<u32> {
+        self.edge.edge()
+    }
+}
+
+//-----------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug)]
+pub struct StagedConfig {
+    /// Шагов перемещения между пробными выстрелами при грубом поиске края
+    pub edge_detect_interval: u32,
+    /// Максимум шагов за один прожиг грубой настройки
+    pub step_limit: u32,
+    /// Шагов, оставляемых для точной настройки
+    pub precision_reserve: u32,
+    /// Размах частоты после пробного выстрела, означающий реакцию резонатора, Гц
+    pub edge_detect_peak: f32,
+    /// Ожидание перед повторным измерением
+    pub recheck_delay: Duration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Stage {
+    /// Поиск края
+    EdgeSearch,
+    /// "Грубая" настройка: разница с целью делится на прогноз наибольшего прироста
+    FastForward,
+    /// "Точный" шаг: по одному шагу до попадания в допуск
+    Precision,
+    /// Конец
+    Done,
+}
+
+/// Настройка одного канала до конца: поиск края, грубая настройка, точная настройка.
+/// Пробный выстрел не перемещает лазер, поэтому текущий шаг стратегия считает сама
+#[derive(Clone, Copy, Debug)]
+pub struct StagedStrategy {
+    config: StagedConfig,
+    stage: Stage,
+    edge: EdgeSearch,
+    /// Текущий шаг при поиске края
+    position: u32,
+    probed: bool,
+    probes: u32,
+    recheck: bool,
+    fast_forward_steps: u32,
+}
+
+impl StagedStrategy {
+    pub fn new(config: StagedConfig) -> Self {
+        Self {
+            config,
+            stage: Stage::EdgeSearch,
+            edge: EdgeSearch::new(0, config.edge_detect_interval),
+            position: 0,
+            probed: false,
+            probes: 0,
+            recheck: false,
+            fast_forward_steps: 0,
+        }
+    }
+
+    /// Начать с другого состояния поиска края, например с подсказки прошлых партий
+    pub fn with_edge_search(mut self, edge: EdgeSearch) -> Self {
+        self.edge = edge;
+        self
+    }
+
+    fn edge_search(&mut self, obs: &Observation) -> Option<Action> {
+        if self.probed {
+            self.probed = false;
+
+            let samples = if obs.samples.is_empty() {
+                std::slice::from_ref(&obs.freq)
+            } else {
+                obs.samples
+            };
+            let box_plot = BoxPlot::new(samples);
+
+            if box_plot.q1() < obs.goal.absolute_low && self.probes == 1 {
+                return Some(Action::Ban(BanReason::TooLow));
+            } else if box_plot.q3() > obs.goal.target {
+                return Some(Action::Ban(BanReason::TooHigh));
+            }
+
+            self.edge.report(
+                box_plot.q3() - box_plot.q1() > self.config.edge_detect_peak
+                    && (samples[0] - samples[samples.len() - 1]).abs()
+                        > self.config.edge_detect_peak,
+            );
+        }
+
+        let position = self.edge.position();
+        if position != self.position {
+            let steps = position as i32 - self.position as i32;
+            self.position = position;
+            Some(Action::Skip(steps))
+        } else if self.edge.edge().is_none() {
+            self.probed = true;
+            self.probes += 1;
+            Some(Action::Probe)
+        } else {
+            // нашли, лазер на краю
+            self.stage = Stage::FastForward;
+            None
+        }
+    }
+
+    fn fast_forward(&mut self, obs: &Observation) -> Option<Action> {
+        let goal = &obs.goal;
+        let budget = goal
+            .steps_left(obs.step)
+            .saturating_sub(self.config.precision_reserve);
+
+        if self.fast_forward_steps > 0 && (budget == 0 || obs.freq > goal.lower()) {
+            self.stage = Stage::Precision;
+            return None;
+        }
+
+        let steps = ((goal.target - obs.freq) / obs.forecast.maximal).floor();
+        let steps = if steps < 1.0 {
+            if !self.recheck {
+                self.recheck = true;
+                return Some(Action::Wait(self.config.recheck_delay));
+            }
+            self.recheck = false;
+
+            if obs.freq + obs.forecast.median >= goal.lower() {
+                self.stage = Stage::Precision;
+                return None;
+            }
+            // ложное срабатывание: прогноз еще ниже допуска
+            1
+        } else {
+            self.recheck = false;
+            steps as u32
+        };
+
+        let steps = steps.min(self.config.step_limit).min(budget);
+        if steps == 0 {
+            self.stage = Stage::Precision;
+            return None;
+        }
+
+        self.fast_forward_steps += steps;
+        Some(Action::Burn {
+            steps,
+            soft: false,
+            power: goal.power,
+        })
+    }
+
+    fn precision(&mut self, obs: &Observation) -> Action {
+        let goal = &obs.goal;
+        let lower_stop = goal.target - goal.tolerance * 2.0 / 3.0;
+
+        if obs.freq > lower_stop {
+            self.stage = Stage::Done;
+            return Action::Verify;
+        }
+
+        if obs.freq + obs.forecast.maximal >= goal.upper() {
+            // Иногда прогноз дает очень большое преввышение, проверим на всякий случай
+            if !self.recheck {
+                self.recheck = true;
+                return Action::Wait(Duration::ZERO);
+            }
+            self.recheck = false;
+
+            if obs.freq > goal.lower() {
+                self.stage = Stage::Done;
+                return Action::Verify;
+            }
+        } else {
+            self.recheck = false;
+        }
+
+        if goal.steps_left(obs.step) == 0 {
+            self.stage = Stage::Done;
+            return Action::Ban(BanReason::StepLimit);
+        }
+
+        Action::Burn {
+            steps: 1,
+            soft: true,
+            power: goal.power,
+        }
+    }
+}
+
+impl AdjustStrategy for StagedStrategy {
+    fn decide(&mut self, obs: &Observation) -> Action {
+        loop {
+            let action = match self.stage {
+                Stage::EdgeSearch => self.edge_search(obs),
+                Stage::FastForward => self.fast_forward(obs),
+                Stage::Precision => Some(self.precision(obs)),
+                Stage::Done => Some(Action::Verify),
+            };
+            if let Some(action) = action {
+                return action;
+            }
+        }
+    }
+
+    fn edge(&self) -> Option<u32> {
+        self.edge.edge()
+    }
+
+    fn stage(&self) -> Option<Stage> {
+        Some(self.stage)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const GOAL: AdjustGoal = AdjustGoal {
+        target: 32768.0,
+        tolerance: 1.0,
+        absolute_low: 32700.0,
+        max_steps: Some(100),
+        power: 1.0,
+    };
+
+    const FORECAST: GrowForecast = GrowForecast {
+        median: 0.3,
+        maximal: 0.5,
+    };
+
+    fn observe<'a>(
+        freq: f32,
+        samples: &'a [f32],
+        history: &'a [f32],
+        step: u32,
+    ) -> Observation<'a> {
+        Observation {
+            freq,
+            samples,
+            history,
+            step,
+            goal: GOAL,
+            forecast: FORECAST,
+        }
+    }
+
+    #[test]
+    fn edge_search() {
+        // край на шаге 13: грубо через 5 шагов, затем делением пополам
+        let mut e = EdgeSearch::new(0, 5);
+        let mut probes = vec![];
+        while let Some(step) = e.next_probe() {
+            probes.push(step);
+            e.report(step >= 13);
+        }
+        assert_eq!(probes, [0, 5, 10, 15, 12, 13]);
+        assert_eq!(e.edge(), Some(13));
+        assert_eq!(e.position(), 13);
+
+        // подсказка прошлых партий: реакция сразу, возврат назад
+        let mut e = EdgeSearch::near(14, 5);
+        let mut probes = vec![];
+        while let Some(step) = e.next_probe() {
+            probes.push(step);
+            e.report(step >= 7);
+        }
+        assert_eq!(probes, [9, 4, 6, 7]);
+        assert_eq!(e.edge(), Some(7));
+
+        // край в самом начале
+        let mut e = EdgeSearch::new(0, 5);
+        e.report(true);
+        assert_eq!(e.edge(), Some(0));
+
+        // деление пополам ограничено: край - первый шаг с реакцией
+        let mut e = EdgeSearch::new(0, 5).with_max_bisections(1);
+        let mut probes = vec![];
+        while let Some(step) = e.next_probe() {
+            probes.push(step);
+            e.report(step >= 13);
+        }
+        assert_eq!(probes, [0, 5, 10, 15, 12]);
+        assert_eq!(e.edge(), Some(15));
+
+        assert_eq!(EdgeSearch::found(21).next_probe(), None);
+    }
+
+    #[test]
+    fn interleaved() {
+        let config = InterleavedConfig {
+            edge_detect_interval: 20,
+            step_limit: 10,
+            edge_offset: 0.25,
+            precision_reserve: 3,
+        };
+
+        // край еще не найден: пробный выстрел на месте, реакция видна при следующем обращении
+        let mut s = InterleavedStrategy::new(config);
+        assert_eq!(s.decide(&observe(32760.0, &[], &[], 0)), Action::Probe);
+        assert_eq!(
+            s.decide(&observe(32760.1, &[], &[32760.0], 0)),
+            Action::Skip(20)
+        );
+        assert_eq!(
+            s.decide(&observe(32760.1, &[], &[32760.0], 20)),
+            Action::Probe
+        );
+        assert_eq!(
+            s.decide(&observe(32760.5, &[], &[32760.1], 20)),
+            Action::Skip(-10)
+        );
+        assert_eq!(s.edge(), None);
+
+        let mut s = InterleavedStrategy::new(config).with_edge_search(EdgeSearch::found(20));
+        assert_eq!(s.edge(), Some(20));
+        // край найден, шаги по прогнозу, но не более step_limit
+        assert_eq!(
+            s.decide(&observe(32765.0, &[], &[32760.0], 20)),
+            Action::Burn {
+                steps: 6,
+                soft: false,
+                power: 1.0
+            }
+        );
+        assert_eq!(
+            s.decide(&observe(32762.0, &[], &[32760.0], 20)),
+            Action::Burn {
+                steps: 10,
+                soft: false,
+                power: 1.0
+            }
+        );
+        // в допуске, но ниже цели - по одному шагу
+        assert_eq!(
+            s.decide(&observe(32767.2, &[], &[32760.0], 40)),
+            Action::Burn {
+                steps: 1,
+                soft: true,
+                power: 1.0
+            }
+        );
+        assert_eq!(
+            s.decide(&observe(32767.8, &[], &[32760.0], 41)),
+            Action::Verify
+        );
+        // запас для точной настройки грубым прожигом не расходуется
+        assert_eq!(
+            s.decide(&observe(32762.0, &[], &[32760.0], 95)),
+            Action::Burn {
+                steps: 2,
+                soft: false,
+                power: 1.0
+            }
+        );
+        // остался только запас - точный режим, хотя до допуска далеко
+        assert_eq!(
+            s.decide(&observe(32762.0, &[], &[32760.0], 97)),
+            Action::Burn {
+                steps: 1,
+                soft: true,
+                power: 1.0
+            }
+        );
+        assert_eq!(
+            s.decide(&observe(32762.0, &[], &[32760.0], 99)),
+            Action::Burn {
+                steps: 1,
+                soft: true,
+                power: 1.0
+            }
+        );
+        assert_eq!(
+            s.decide(&observe(32765.0, &[], &[32760.0], 100)),
+            Action::Ban(BanReason::StepLimit)
+        );
+    }
+
+    #[test]
+    fn staged() {
+        let mut s = StagedStrategy::new(StagedConfig {
+            edge_detect_interval: 5,
+            step_limit: 10,
+            precision_reserve: 3,
+            edge_detect_peak: 0.35,
+            recheck_delay: Duration::from_secs(1),
+        });
+
+        // нет реакции на пробный выстрел - сдвиг
+        let flat = [32750.0, 32750.1, 32750.0, 32750.1];
+        assert_eq!(s.decide(&observe(32750.0, &flat, &[], 0)), Action::Probe);
+        assert_eq!(s.decide(&observe(32750.0, &flat, &[], 0)), Action::Skip(5));
+        assert_eq!(s.decide(&observe(32750.0, &flat, &[], 0)), Action::Probe);
+
+        // реакция есть - возврат на середину промежутка
+        let edge = [32753.0, 32752.0, 32751.0, 32750.0];
+        assert_eq!(s.decide(&observe(32750.5, &edge, &[], 0)), Action::Skip(-3));
+        assert_eq!(s.decide(&observe(32750.5, &edge, &[], 0)), Action::Probe);
+        assert_eq!(s.decide(&observe(32750.5, &flat, &[], 0)), Action::Skip(1));
+        assert_eq!(s.decide(&observe(32750.5, &flat, &[], 0)), Action::Probe);
+        assert_eq!(s.edge(), None);
+
+        // край найден на шаге 3 - грубая настройка
+        assert_eq!(
+            s.decide(&observe(32750.5, &edge, &[], 0)),
+            Action::Burn {
+                steps: 10,
+                soft: false,
+                power: 1.0
+            }
+        );
+        assert_eq!(s.stage(), Some(Stage::FastForward));
+        assert_eq!(s.edge(), Some(3));
+
+        // попали в допуск - точная настройка по шагу
+        assert_eq!(
+            s.decide(&observe(32767.1, &[], &[], 10)),
+            Action::Burn {
+                steps: 1,
+                soft: true,
+                power: 1.0
+            }
+        );
+        assert_eq!(s.stage(), Some(Stage::Precision));
+
+        // близко к цели - конец
+        assert_eq!(s.decide(&observe(32767.4, &[], &[], 11)), Action::Verify);
+        assert_eq!(s.stage(), Some(Stage::Done));
+    }
+
+    #[test]
+    fn staged_rejects_out_of_range() {
+        let config = StagedConfig {
+            edge_detect_interval: 5,
+            step_limit: 10,
+            precision_reserve: 3,
+            edge_detect_peak: 0.35,
+            recheck_delay: Duration::from_secs(1),
+        };
+
+        let mut s = StagedStrategy::new(config);
+        s.decide(&observe(32600.0, &[], &[], 0));
+        assert_eq!(
+            s.decide(&observe(32600.0, &[], &[], 0)),
+            Action::Ban(BanReason::TooLow)
+        );
+
+        let mut s = StagedStrategy::new(config);
+        s.decide(&observe(32770.0, &[], &[], 0));
+        assert_eq!(
+            s.decide(&observe(32770.0, &[], &[], 0)),
+            Action::Ban(BanReason::TooHigh)
+        );
+    }
+}